@@ -323,6 +323,7 @@ rec {
         features = {
           "extra_debug" = [ "ledger-log/log_trace" ];
           "speculos" = [ "nanos_sdk/speculos" "ledger-log/speculos" "ledger-log/log_error" "ledger-parser-combinators/logging" ];
+          "test_descriptor_key" = [ ];
         };
         resolvedDefaultFeatures = [ "default" "extra_debug" "speculos" ];
       };
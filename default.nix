@@ -76,9 +76,12 @@ rec {
     exec ${pkgs.nodejs-14_x}/bin/npm --offline test -- "$@"
   '';
 
+  # The descriptor tests sign with the key only app-for-tests trusts, so they run on their own
+  descriptorTests = "^Capability descriptor tests";
+
   apiPort = 5005;
 
-  runTests = { appExe, device, variant ? "", speculosCmd }:
+  runTests = { appExe, device, variant ? "", speculosCmd, mochaArgs ? [ "--grep" descriptorTests "--invert" ] }:
   pkgs.runCommandNoCC "run-tests-${device}${variant}" {
     nativeBuildInputs = [
       pkgs.wget alamgu.speculos.speculos testScript
@@ -91,7 +94,7 @@ rec {
 
     until wget -O/dev/null -o/dev/null http://localhost:${toString apiPort}; do sleep 0.1; done;
 
-    ${testScript}/bin/mocha-wrapper
+    DEVICE=${device} ${testScript}/bin/mocha-wrapper ${pkgs.lib.escapeShellArgs mochaArgs}
     rv=$?
     kill -9 $SPECULOS
     exit $rv) | tee $out/short |& tee $out/full &
@@ -114,6 +117,11 @@ rec {
 
   appForDevice = device: rec {
     app = makeApp { inherit device; };
+    # The tests sign capability descriptors with a key release builds do not trust
+    app-for-tests = makeApp {
+      inherit device;
+      rootFeatures = [ "default" "test_descriptor_key" ];
+    };
     app-with-logging = makeApp {
       inherit device;
       release = false;
@@ -134,6 +142,7 @@ rec {
     };
 
     rootCrate = app.rootCrate.build;
+    rootCrate-for-tests = app-for-tests.rootCrate.build;
     rootCrate-with-logging = app-with-logging.rootCrate.build;
 
     appExe = rootCrate + "/bin/" + appName;
@@ -163,6 +172,12 @@ rec {
     ] ++ speculosDeviceFlags;
 
     test = runTests { inherit appExe speculosCmd device; };
+    test-descriptors = runTests {
+      inherit speculosCmd device;
+      appExe = rootCrate-for-tests + "/bin/" + appName;
+      variant = "-descriptors";
+      mochaArgs = [ "--grep" descriptorTests ];
+    };
     test-with-logging = runTests {
       inherit speculosCmd device;
       appExe = rootCrate-with-logging + "/bin/" + appName;
@@ -9,4 +9,21 @@ fn main() {
         _ => 1024 * 7,
     };
     println!("cargo:rustc-link-arg=--defsym=_reloc_size={reloc_size}");
+
+    // Shown in the "About" menu. Builds from a tarball have no git checkout, so this can
+    // be provided through the environment instead.
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    let commit = std::env::var("GIT_COMMIT")
+        .ok()
+        .or_else(|| {
+            std::process::Command::new("git")
+                .args(["rev-parse", "--short", "HEAD"])
+                .output()
+                .ok()
+                .filter(|o| o.status.success())
+                .and_then(|o| String::from_utf8(o.stdout).ok())
+                .map(|s| s.trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GIT_COMMIT={commit}");
 }
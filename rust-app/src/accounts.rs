@@ -0,0 +1,77 @@
+//! The accounts of this device's own keys, so that prompts can point them out.
+//!
+//! The keys of the first OWN_ACCOUNTS accounts, at 44'/626'/n', are derived the first time a
+//! prompt shows a key or a `k:` account, and kept until the app is closed. Only keys written in
+//! plain hex are looked up; anything else is shown as it is, without a note.
+
+use core::convert::TryInto;
+use core::fmt;
+use ledger_crypto_helpers::common::try_option;
+use ledger_crypto_helpers::eddsa::{
+    ed25519_public_key_bytes, with_public_keys, Ed25519RawPubKeyAddress,
+};
+
+const OWN_ACCOUNTS: usize = 5;
+
+const HARDENED: u32 = 0x8000_0000;
+const KEY_SIZE: usize = 32;
+
+// None until the keys are first needed; a key which could not be derived is never matched
+static mut OWN_KEYS: Option<[Option<[u8; KEY_SIZE]>; OWN_ACCOUNTS]> = None;
+
+fn own_keys() -> &'static [Option<[u8; KEY_SIZE]>; OWN_ACCOUNTS] {
+    let keys = unsafe { &mut OWN_KEYS };
+    keys.get_or_insert_with(|| {
+        let mut keys = [None; OWN_ACCOUNTS];
+        for (n, slot) in keys.iter_mut().enumerate() {
+            let path = [44 | HARDENED, 626 | HARDENED, n as u32 | HARDENED];
+            let _ = with_public_keys(&path, |key: &_, _: &Ed25519RawPubKeyAddress| {
+                try_option(|| -> Option<()> {
+                    *slot = Some(ed25519_public_key_bytes(key).try_into().ok()?);
+                    Some(())
+                }())
+            });
+        }
+        keys
+    })
+}
+
+fn parse_hex_key(hex: &[u8]) -> Option<[u8; KEY_SIZE]> {
+    if hex.len() != 2 * KEY_SIZE {
+        return None;
+    }
+    let nibble = |c: u8| (c as char).to_digit(16).map(|d| d as u8);
+    let mut key = [0; KEY_SIZE];
+    for (byte, pair) in key.iter_mut().zip(hex.chunks(2)) {
+        *byte = nibble(pair[0])? << 4 | nibble(pair[1])?;
+    }
+    Some(key)
+}
+
+/// The account number of a public key of this device, written in hex.
+pub fn own_key_index(hex: &[u8]) -> Option<u32> {
+    // Checked first, so that other text does not derive the keys
+    let key = parse_hex_key(hex)?;
+    own_keys()
+        .iter()
+        .position(|k| *k == Some(key))
+        .map(|n| n as u32)
+}
+
+/// The account number of a `k:` account of this device.
+pub fn own_account_index(account: &[u8]) -> Option<u32> {
+    own_key_index(account.strip_prefix(b"k:")?)
+}
+
+/// Written after a key or account: " (your account #n)" for one of this device, else nothing.
+#[derive(Clone, Copy)]
+pub struct OwnAccountNote(pub Option<u32>);
+
+impl fmt::Display for OwnAccountNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(n) => write!(f, " (your account #{})", n),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! The address book: accounts the user has named, kept in NVM next to the settings.
+//!
+//! Entries are added and deleted by the host, and each change is confirmed on the device. A
+//! transfer to an account of the book shows its label, like "Treasury (k:ab12...)"; with the
+//! 'Only Known Recipients' setting, transfers to other accounts are refused.
+
+use crate::accounts::own_account_index;
+use crate::display::Sanitized;
+use crate::implementation::{reject_with, scroller, scroller_paginated, take_reject_reason};
+use crate::interface::KadenaStatusWord;
+use crate::settings::{SettingFlag, Settings};
+use arrayvec::ArrayVec;
+use core::fmt;
+use core::fmt::Write;
+use core::str::from_utf8;
+use ledger_prompts_ui::final_accept_prompt;
+#[cfg(target_os = "nanos")]
+use nanos_sdk::nvm::*;
+#[cfg(target_os = "nanos")]
+use nanos_sdk::Pic;
+
+pub const LABEL_SIZE: usize = 16;
+// A `k:` account, or any other account name as long
+pub const ACCOUNT_SIZE: usize = 66;
+
+#[cfg(target_os = "nanos")]
+pub const MAX_ENTRIES: usize = 8;
+#[cfg(not(target_os = "nanos"))]
+pub const MAX_ENTRIES: usize = 16;
+
+// How much of the account is shown after the label
+const ACCOUNT_PREFIX_SIZE: usize = 6;
+
+// The count, then a label and an account with their lengths
+pub const LIST_REPLY_SIZE: usize = 3 + LABEL_SIZE + ACCOUNT_SIZE;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Entry {
+    label_len: u8,
+    label: [u8; LABEL_SIZE],
+    account_len: u8,
+    account: [u8; ACCOUNT_SIZE],
+}
+
+impl Entry {
+    const EMPTY: Entry = Entry {
+        label_len: 0,
+        label: [0; LABEL_SIZE],
+        account_len: 0,
+        account: [0; ACCOUNT_SIZE],
+    };
+
+    pub fn label(&self) -> &[u8] {
+        &self.label[..(self.label_len as usize).min(LABEL_SIZE)]
+    }
+
+    pub fn account(&self) -> &[u8] {
+        &self.account[..(self.account_len as usize).min(ACCOUNT_SIZE)]
+    }
+}
+
+// Entries are checked to be printable ASCII when added, but NVM could hold anything
+fn shown(v: &[u8]) -> Result<Sanitized, fmt::Error> {
+    from_utf8(v).map(Sanitized).map_err(|_| fmt::Error)
+}
+
+/// The label, then the start of the account, like "Treasury (k:ab12...)".
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let account = self.account();
+        if account.len() > ACCOUNT_PREFIX_SIZE + 3 {
+            let prefix = shown(&account[..ACCOUNT_PREFIX_SIZE])?;
+            write!(f, "{} ({}...)", shown(self.label())?, prefix)
+        } else {
+            write!(f, "{} ({})", shown(self.label())?, shown(account)?)
+        }
+    }
+}
+
+/// What is kept in NVM.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct StoredAddressBook {
+    count: u8,
+    entries: [Entry; MAX_ENTRIES],
+}
+
+impl StoredAddressBook {
+    const EMPTY: StoredAddressBook = StoredAddressBook {
+        count: 0,
+        entries: [Entry::EMPTY; MAX_ENTRIES],
+    };
+
+    fn entries(&self) -> &[Entry] {
+        &self.entries[..(self.count as usize).min(MAX_ENTRIES)]
+    }
+}
+
+// Like `SETTINGS`, this must only be used through `stored` and `store`; a reference from
+// `stored` must not be kept across a call to `store`, which may move the value.
+#[cfg(target_os = "nanos")]
+#[link_section = ".nvm_data"]
+static mut ADDRESS_BOOK: Pic<AtomicStorage<StoredAddressBook>> =
+    Pic::new(AtomicStorage::new(&StoredAddressBook::EMPTY));
+
+#[cfg(target_os = "nanos")]
+#[inline(never)]
+fn stored() -> &'static StoredAddressBook {
+    unsafe { ADDRESS_BOOK.get_mut() }.get_ref()
+}
+
+#[cfg(target_os = "nanos")]
+#[inline(never)]
+fn store(v: &StoredAddressBook) {
+    unsafe { ADDRESS_BOOK.get_mut() }.update(v);
+}
+
+// On the other devices the book is only kept in RAM, like the settings
+#[cfg(not(target_os = "nanos"))]
+static mut ADDRESS_BOOK: StoredAddressBook = StoredAddressBook::EMPTY;
+
+#[cfg(not(target_os = "nanos"))]
+fn stored() -> &'static StoredAddressBook {
+    unsafe { &ADDRESS_BOOK }
+}
+
+#[cfg(not(target_os = "nanos"))]
+fn store(v: &StoredAddressBook) {
+    unsafe { ADDRESS_BOOK = *v };
+}
+
+/// The entry of an account, written as in the JSON string of a transaction.
+pub fn find(account: &[u8]) -> Option<Entry> {
+    stored()
+        .entries()
+        .iter()
+        .find(|e| e.account() == account)
+        .copied()
+}
+
+/// With 'Only Known Recipients' set, refuses transfers to accounts which are neither in the
+/// book nor of one of the keys of this device.
+pub fn check_recipient(account: &[u8]) -> Option<()> {
+    if !Settings::new().is_enabled(SettingFlag::KnownRecipientsOnly)
+        || find(account).is_some()
+        || own_account_index(account).is_some()
+    {
+        return Some(());
+    }
+    // The reason is shown even if the user rejects this screen
+    let _ = scroller("Refused", |w| {
+        Ok(write!(
+            w,
+            "Recipient is not in the address book. Allow Any Recipient in Settings to sign"
+        )?)
+    });
+    reject_with(KadenaStatusWord::RecipientNotAllowed)
+}
+
+/// With 'Only Known Recipients' set, refuses transfers whose receiver cannot be checked, like
+/// those of capabilities without the args the app expects.
+pub fn check_unknown_recipient() -> Option<()> {
+    if !Settings::new().is_enabled(SettingFlag::KnownRecipientsOnly) {
+        return Some(());
+    }
+    // The reason is shown even if the user rejects this screen
+    let _ = scroller("Refused", |w| {
+        Ok(write!(
+            w,
+            "Recipient cannot be checked against the address book. Allow Any Recipient in Settings to sign"
+        )?)
+    });
+    reject_with(KadenaStatusWord::RecipientNotAllowed)
+}
+
+// Reads the length prefixed text of an entry; accounts must match JSON strings as they are
+// written, so they cannot have quotes or escapes.
+fn text<const N: usize>(data: &mut &[u8], account: bool) -> Option<([u8; N], u8)> {
+    let (len, rest) = data.split_first()?;
+    let bytes = rest.get(..*len as usize)?;
+    *data = &rest[bytes.len()..];
+    let allowed = |b: &u8| (b' '..=b'~').contains(b) && !(account && matches!(b, b'"' | b'\\'));
+    if bytes.is_empty() || bytes.len() > N || !bytes.iter().all(allowed) {
+        return None;
+    }
+    let mut out = [0; N];
+    out[..bytes.len()].copy_from_slice(bytes);
+    Some((out, *len))
+}
+
+fn parse_account(mut data: &[u8]) -> Option<([u8; ACCOUNT_SIZE], u8)> {
+    let account = text(&mut data, true)?;
+    data.is_empty().then(|| account)
+}
+
+fn parse_entry(mut data: &[u8]) -> Option<Entry> {
+    let (label, label_len) = text(&mut data, false)?;
+    let (account, account_len) = text(&mut data, true)?;
+    data.is_empty().then(|| Entry {
+        label_len,
+        label,
+        account_len,
+        account,
+    })
+}
+
+// Shows an entry under the title of the change, and asks the user to confirm it
+fn confirm(title: &str, entry: &Entry) -> Result<(), KadenaStatusWord> {
+    scroller(title, |w| Ok(write!(w, "{}", shown(entry.label())?)?))
+        .and_then(|()| {
+            scroller_paginated("Account", |w| Ok(write!(w, "{}", shown(entry.account())?)?))
+        })
+        .and_then(|()| final_accept_prompt(&[]))
+        .ok_or_else(|| take_reject_reason().unwrap_or(KadenaStatusWord::UserRejected))
+}
+
+/// Adds an entry after the user confirms it; an entry for the same account is replaced.
+pub fn add_address(data: &[u8]) -> Result<(), KadenaStatusWord> {
+    let entry = parse_entry(data).ok_or(KadenaStatusWord::InvalidAddress)?;
+    let book = stored();
+    let existing = book
+        .entries()
+        .iter()
+        .position(|e| e.account() == entry.account());
+    // Two accounts with the same label could be mistaken for each other
+    if book
+        .entries()
+        .iter()
+        .any(|e| e.label() == entry.label() && e.account() != entry.account())
+    {
+        return Err(KadenaStatusWord::InvalidAddress);
+    }
+    if existing.is_none() && book.entries().len() == MAX_ENTRIES {
+        return Err(KadenaStatusWord::AddressBookFull);
+    }
+    confirm("Add Address", &entry)?;
+    let mut book = *stored();
+    let i = existing.unwrap_or_else(|| {
+        book.count = book.entries().len() as u8 + 1;
+        book.count as usize - 1
+    });
+    book.entries[i] = entry;
+    store(&book);
+    Ok(())
+}
+
+/// Deletes the entry of an account after the user confirms it.
+pub fn delete_address(data: &[u8]) -> Result<(), KadenaStatusWord> {
+    let (account, len) = parse_account(data).ok_or(KadenaStatusWord::InvalidAddress)?;
+    let account = &account[..len as usize];
+    let i = stored()
+        .entries()
+        .iter()
+        .position(|e| e.account() == account)
+        .ok_or(KadenaStatusWord::UnknownAddress)?;
+    let entry = stored().entries[i];
+    confirm("Delete Address", &entry)?;
+    let mut book = *stored();
+    let count = book.entries().len();
+    book.entries.copy_within(i + 1..count, i);
+    book.entries[count - 1] = Entry::EMPTY;
+    book.count = count as u8 - 1;
+    store(&book);
+    Ok(())
+}
+
+/// The number of entries, then the entry at the given index, if there is one.
+pub fn list_addresses(data: &[u8]) -> Result<ArrayVec<u8, LIST_REPLY_SIZE>, KadenaStatusWord> {
+    let index = match data {
+        [index] => *index as usize,
+        _ => return Err(KadenaStatusWord::InvalidAddress),
+    };
+    let entries = stored().entries();
+    let mut reply = ArrayVec::new();
+    reply.push(entries.len() as u8);
+    if let Some(entry) = entries.get(index) {
+        reply.push(entry.label().len() as u8);
+        reply.try_extend_from_slice(entry.label()).ok();
+        reply.push(entry.account().len() as u8);
+        reply.try_extend_from_slice(entry.account()).ok();
+    }
+    Ok(reply)
+}
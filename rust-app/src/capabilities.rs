@@ -0,0 +1,156 @@
+//! Capabilities the app knows about, shown with prompts of their own.
+//!
+//! Each of them has a `CapabilityRenderer`, registered in `RENDERERS` under the name of the
+//! capability and its number of args. A renderer checks the args it is given before showing
+//! them; capabilities without a renderer, or whose args are not those expected, are shown as
+//! unknown capabilities, with all their args. The renderers of a contract go in a module of their
+//! own, like `coin`, with their tests in `ts-tests`.
+//!
+//! Capabilities without a built-in renderer can also be described by the host, see `descriptor`.
+
+mod coin;
+mod descriptor;
+mod gas_station;
+
+pub use descriptor::add_descriptor;
+
+use crate::pact;
+use core::str::from_utf8;
+
+/// A capability of the transaction, with its args as rendered JSON.
+pub struct Capability<'a> {
+    pub name: &'a [u8],
+    args: &'a [u8],
+    // The end of each arg but the last in `args`, then zeros
+    ends: &'a [usize],
+    /// The transfers shown before this capability, to number them
+    pub transfers_before: u16,
+}
+
+impl<'a> Capability<'a> {
+    pub fn new(name: &'a [u8], args: &'a [u8], ends: &'a [usize], transfers_before: u16) -> Self {
+        Capability {
+            name,
+            args,
+            ends,
+            transfers_before,
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        if self.args.is_empty() {
+            return 0;
+        }
+        1 + self.ends.iter().take_while(|end| **end != 0).count()
+    }
+
+    pub fn arg(&self, i: usize) -> Option<&'a [u8]> {
+        let arity = self.arity();
+        if i >= arity {
+            return None;
+        }
+        let start = if i == 0 { 0 } else { *self.ends.get(i - 1)? };
+        let end = if i + 1 == arity {
+            self.args.len()
+        } else {
+            *self.ends.get(i)?
+        };
+        self.args.get(start..end)
+    }
+}
+
+// The account name in a string arg, as written in the JSON
+fn account(arg: Option<&[u8]>) -> Option<&[u8]> {
+    let text = pact::as_string(from_utf8(arg?).ok()?)?;
+    Some(text.as_bytes())
+}
+
+/// What a renderer made of a capability.
+pub enum Recognized {
+    /// Shown, and counted with the other transfers
+    Transfer,
+    Shown,
+    /// Not the expected args; shown as an unknown capability instead
+    No,
+}
+
+pub trait CapabilityRenderer: Sync {
+    /// Shows the capability; None if the user rejected it, or the transaction must be refused.
+    fn render(&self, cap: &Capability) -> Option<Recognized>;
+}
+
+struct Registration {
+    // A name starting with a dot is that of a capability in any module, like GAS_PAYER
+    name: &'static [u8],
+    arity: usize,
+    renderer: &'static dyn CapabilityRenderer,
+}
+
+impl Registration {
+    fn matches_name(&self, name: &[u8]) -> bool {
+        if self.name.starts_with(b".") {
+            name.len() > self.name.len() && name.ends_with(self.name)
+        } else {
+            name == self.name
+        }
+    }
+
+    fn matches(&self, name: &[u8], arity: usize) -> bool {
+        self.matches_name(name) && arity == self.arity
+    }
+}
+
+static RENDERERS: [Registration; 5] = [
+    Registration {
+        name: b"coin.GAS",
+        arity: 0,
+        renderer: &coin::Gas,
+    },
+    Registration {
+        name: b"coin.TRANSFER",
+        arity: 3,
+        renderer: &coin::Transfer,
+    },
+    Registration {
+        name: b"coin.TRANSFER_XCHAIN",
+        arity: 4,
+        renderer: &coin::TransferXChain,
+    },
+    Registration {
+        name: b"coin.ROTATE",
+        arity: 1,
+        renderer: &coin::Rotate,
+    },
+    Registration {
+        name: b".GAS_PAYER",
+        arity: 3,
+        renderer: &gas_station::GasPayer,
+    },
+];
+
+pub fn builtin_renderer(name: &[u8], arity: usize) -> Option<&'static dyn CapabilityRenderer> {
+    RENDERERS
+        .iter()
+        .find(|r| r.matches(name, arity))
+        .map(|r| r.renderer)
+}
+
+/// Whether a capability of this name has a built-in renderer, with whatever number of args.
+pub fn is_builtin(name: &[u8]) -> bool {
+    RENDERERS.iter().any(|r| r.matches_name(name))
+}
+
+/// Whether a capability is a transfer of some token, like `coin.TRANSFER` or
+/// `coin.TRANSFER_XCHAIN`; these are added up and checked against the settings.
+pub fn is_transfer(name: &[u8]) -> bool {
+    match name.iter().rposition(|b| *b == b'.') {
+        Some(i) => name[i + 1..].starts_with(b"TRANSFER"),
+        None => false,
+    }
+}
+
+/// The built-in renderer of a capability, or else that of a descriptor from the host.
+pub fn renderer(name: &[u8], arity: usize) -> Option<&'static dyn CapabilityRenderer> {
+    builtin_renderer(name, arity)
+        .or_else(|| descriptor::find(name, arity).map(|d| d as &'static dyn CapabilityRenderer))
+}
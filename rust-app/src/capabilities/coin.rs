@@ -0,0 +1,150 @@
+//! The capabilities of the `coin` contract, for KDA.
+
+use super::{account, Capability, CapabilityRenderer, Recognized};
+use crate::accounts::{own_account_index, OwnAccountNote};
+use crate::address_book::{self, check_recipient, Entry};
+use crate::implementation::{
+    add_transfer, mkarg, parse_chain_arg, reject_with, review_xchain_target, scroller,
+    warn_lookalikes,
+};
+use crate::interface::KadenaStatusWord;
+use crate::pact;
+use arrayvec::ArrayString;
+use core::fmt;
+use core::fmt::Write;
+use core::str::from_utf8;
+use ledger_prompts_ui::mk_prompt_write;
+
+fn transfer_title(cap: &Capability) -> Option<ArrayString<22>> {
+    let mut title = ArrayString::new();
+    write!(
+        mk_prompt_write(&mut title),
+        "Transfer {}",
+        cap.transfers_before + 1
+    )
+    .ok()?;
+    Some(title)
+}
+
+// Points out an account arg of this device's keys
+fn account_note(arg: Option<&[u8]>) -> OwnAccountNote {
+    OwnAccountNote(account(arg).and_then(own_account_index))
+}
+
+// A receiver in the address book is shown with its label, others as they are written
+struct Receiver<'a> {
+    arg: Option<&'a [u8]>,
+    entry: Option<Entry>,
+    note: OwnAccountNote,
+}
+
+impl<'a> Receiver<'a> {
+    fn new(arg: Option<&'a [u8]>) -> Receiver<'a> {
+        Receiver {
+            arg,
+            entry: account(arg).and_then(address_book::find),
+            note: account_note(arg),
+        }
+    }
+}
+
+impl fmt::Display for Receiver<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.entry {
+            Some(entry) => write!(f, "{}", entry),
+            None => {
+                let arg = mkarg(self.arg).map_err(|_| fmt::Error)?;
+                write!(f, "{}{}", arg, self.note)
+            }
+        }
+    }
+}
+
+// The sender and receiver must be account names, and the amount a number
+fn transfer_args_ok(cap: &Capability) -> bool {
+    let text = |i| cap.arg(i).and_then(|a| from_utf8(a).ok());
+    text(0).and_then(pact::as_string).is_some()
+        && text(1).and_then(pact::as_string).is_some()
+        && text(2).and_then(pact::amount).is_some()
+}
+
+pub struct Gas;
+
+impl CapabilityRenderer for Gas {
+    fn render(&self, _: &Capability) -> Option<Recognized> {
+        scroller("Paying Gas", |w| Ok(write!(w, " ")?))?;
+        Some(Recognized::Shown)
+    }
+}
+
+pub struct Transfer;
+
+impl CapabilityRenderer for Transfer {
+    fn render(&self, cap: &Capability) -> Option<Recognized> {
+        if !transfer_args_ok(cap) {
+            return Some(Recognized::No);
+        }
+        let (sender, receiver, amount) = (cap.arg(0), cap.arg(1), cap.arg(2));
+        let (sender_note, shown_receiver) = (account_note(sender), Receiver::new(receiver));
+        scroller(&transfer_title(cap)?, |w| {
+            Ok(write!(
+                w,
+                "{} from {}{} to {}",
+                mkarg(amount)?,
+                mkarg(sender)?,
+                sender_note,
+                shown_receiver
+            )?)
+        })?;
+        warn_lookalikes(&[sender, receiver])?;
+        check_recipient(account(receiver)?)?;
+        add_transfer(cap, 1, 2)?;
+        Some(Recognized::Transfer)
+    }
+}
+
+pub struct TransferXChain;
+
+impl CapabilityRenderer for TransferXChain {
+    fn render(&self, cap: &Capability) -> Option<Recognized> {
+        if !transfer_args_ok(cap) {
+            return Some(Recognized::No);
+        }
+        let (sender, receiver, amount) = (cap.arg(0), cap.arg(1), cap.arg(2));
+        let target = match cap.arg(3).and_then(parse_chain_arg) {
+            Some(target) => target,
+            None => return reject_with(KadenaStatusWord::InvalidChain),
+        };
+        let (sender_note, shown_receiver) = (account_note(sender), Receiver::new(receiver));
+        scroller(&transfer_title(cap)?, |w| {
+            Ok(write!(
+                w,
+                "Cross-chain {} from {}{} to {} to chain {}",
+                mkarg(amount)?,
+                mkarg(sender)?,
+                sender_note,
+                shown_receiver,
+                mkarg(cap.arg(3))?
+            )?)
+        })?;
+        warn_lookalikes(&[sender, receiver])?;
+        check_recipient(account(receiver)?)?;
+        review_xchain_target(target)?;
+        add_transfer(cap, 1, 2)?;
+        Some(Recognized::Transfer)
+    }
+}
+
+pub struct Rotate;
+
+impl CapabilityRenderer for Rotate {
+    fn render(&self, cap: &Capability) -> Option<Recognized> {
+        let account = cap.arg(0);
+        let note = account_note(account);
+        scroller("Rotate for account", |w| {
+            Ok(write!(w, "{}{}", mkarg(account)?, note)?)
+        })?;
+        warn_lookalikes(&[account])?;
+        Some(Recognized::Shown)
+    }
+}
@@ -0,0 +1,228 @@
+//! Capabilities described by the host, in descriptors signed by a key the app trusts.
+//!
+//! A descriptor gives the title of a capability, a label for each of its args, and which of them
+//! is an amount, with the decimals and label of its token, and which is the receiver if the
+//! capability is a transfer; see PROVIDE_CAP_DESCRIPTOR in `docs/apdu.md` for their encoding.
+//! Verified descriptors are kept until the app is closed, and render the capabilities which have
+//! no built-in renderer; the transfers they describe are added up with the others.
+
+use super::{account, Capability, CapabilityRenderer, Recognized};
+use crate::address_book::check_recipient;
+use crate::decimal::DECIMALS;
+use crate::implementation::{add_transfer, mkarg, parse_amount, scroller, warn_lookalikes};
+use crate::interface::KadenaStatusWord;
+use arrayvec::{ArrayString, ArrayVec};
+use core::fmt::Write;
+use core::str::from_utf8;
+use nanos_sdk::bindings::CX_SHA512;
+use nanos_sdk::ecc::{CurvesId, ECPublicKey};
+
+const VERSION: u8 = 1;
+const NAME_SIZE: usize = 32;
+const TITLE_SIZE: usize = 20;
+const LABEL_SIZE: usize = 16;
+const TOKEN_SIZE: usize = 10;
+const MAX_ARGS: usize = 5;
+const SIGNATURE_SIZE: usize = 64;
+// The amount index of descriptors without an amount, and the receiver index of those which are not
+// transfers
+const NO_ARG: u8 = 0xFF;
+
+#[cfg(target_os = "nanos")]
+const MAX_DESCRIPTORS: usize = 4;
+#[cfg(not(target_os = "nanos"))]
+const MAX_DESCRIPTORS: usize = 16;
+
+// Ed25519 public keys, uncompressed: 0x04, then both coordinates in big endian. The key of the
+// tests is derived from the seed "Kadena capability descriptors!!!", so anyone can sign with it.
+#[cfg(feature = "test_descriptor_key")]
+static TRUSTED_KEYS: [[u8; 65]; 1] = [[
+    0x04, 0x1a, 0x21, 0x90, 0xd8, 0x55, 0x7d, 0x39, 0x76, 0x62, 0x8f, 0x05, 0x1a, 0xbd, 0x2f, 0xf0,
+    0x47, 0x5e, 0xf7, 0xd6, 0x18, 0x97, 0xc6, 0x2f, 0x6c, 0x22, 0x7f, 0xbf, 0xc0, 0x16, 0xad, 0xfd,
+    0xe6, 0x04, 0xe6, 0x1d, 0xd3, 0x19, 0x3e, 0x31, 0xa9, 0x9e, 0x6b, 0xe6, 0xdd, 0xee, 0x05, 0x43,
+    0x8e, 0x07, 0x87, 0x3f, 0xbf, 0x50, 0x4d, 0x03, 0x87, 0xfa, 0x48, 0x81, 0xab, 0xec, 0xdd, 0x1a,
+    0xce,
+]];
+// No key has been published for signing descriptors yet, so release builds refuse them all with
+// SW_UNTRUSTED_DESCRIPTOR
+#[cfg(not(feature = "test_descriptor_key"))]
+static TRUSTED_KEYS: [[u8; 65]; 0] = [];
+
+pub struct Descriptor {
+    name: ArrayString<NAME_SIZE>,
+    title: ArrayString<TITLE_SIZE>,
+    labels: ArrayVec<ArrayString<LABEL_SIZE>, MAX_ARGS>,
+    amount: Option<Amount>,
+}
+
+struct Amount {
+    index: usize,
+    decimals: u32,
+    // Shown after the amount, like "KDA"
+    token: ArrayString<TOKEN_SIZE>,
+    // The index of the receiver arg, if the capability is a transfer of the amount
+    receiver: Option<usize>,
+}
+
+static mut DESCRIPTORS: ArrayVec<Descriptor, MAX_DESCRIPTORS> = ArrayVec::new_const();
+
+// Reads the fields of a descriptor one after the other
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Option<u8> {
+        let (b, rest) = self.0.split_first()?;
+        self.0 = rest;
+        Some(*b)
+    }
+
+    // A length, then that many characters; these are shown as they are, so only printable ASCII
+    fn text<const N: usize>(&mut self) -> Option<ArrayString<N>> {
+        let len = self.byte()? as usize;
+        let bytes = self.0.get(..len)?;
+        self.0 = &self.0[len..];
+        if len == 0 || !bytes.iter().all(|b| (b' '..=b'~').contains(b)) {
+            return None;
+        }
+        ArrayString::from(from_utf8(bytes).ok()?).ok()
+    }
+}
+
+fn parse(body: &[u8]) -> Option<Descriptor> {
+    let mut r = Reader(body);
+    if r.byte()? != VERSION {
+        return None;
+    }
+    let name = r.text()?;
+    let arity = r.byte()? as usize;
+    if arity > MAX_ARGS {
+        return None;
+    }
+    let title = r.text()?;
+    let mut labels = ArrayVec::new();
+    for _ in 0..arity {
+        labels.push(r.text()?);
+    }
+    let amount = match r.byte()? {
+        NO_ARG => None,
+        i => {
+            let index = i as usize;
+            let decimals = r.byte()? as u32;
+            let token = r.text()?;
+            let receiver = match r.byte()? {
+                NO_ARG => None,
+                j => Some(j as usize),
+            };
+            if index >= arity
+                || decimals > DECIMALS
+                || receiver.map_or(false, |j| j >= arity || j == index)
+            {
+                return None;
+            }
+            Some(Amount {
+                index,
+                decimals,
+                token,
+                receiver,
+            })
+        }
+    };
+    r.0.is_empty().then(|| Descriptor {
+        name,
+        title,
+        labels,
+        amount,
+    })
+}
+
+fn signed_by_trusted_key(body: &[u8], signature: &[u8]) -> bool {
+    TRUSTED_KEYS.iter().any(|key| {
+        let mut pubkey = ECPublicKey::<65, 'E'>::new(CurvesId::Ed25519);
+        pubkey.pubkey = *key;
+        pubkey.keylength = key.len();
+        pubkey.verify((signature, signature.len() as u32), body, CX_SHA512 as u8)
+    })
+}
+
+/// Checks a descriptor and keeps it, in place of any other for the same capability; once there
+/// are MAX_DESCRIPTORS of them, the oldest one is dropped.
+pub fn add_descriptor(data: &[u8]) -> Result<(), KadenaStatusWord> {
+    let body_len = data
+        .len()
+        .checked_sub(SIGNATURE_SIZE)
+        .ok_or(KadenaStatusWord::InvalidDescriptor)?;
+    let (body, signature) = data.split_at(body_len);
+    let descriptor = parse(body).ok_or(KadenaStatusWord::InvalidDescriptor)?;
+    let arity = descriptor.labels.len();
+    // The capabilities known to the app are always shown its own way
+    if super::builtin_renderer(descriptor.name.as_bytes(), arity).is_some() {
+        return Err(KadenaStatusWord::InvalidDescriptor);
+    }
+    if !signed_by_trusted_key(body, signature) {
+        return Err(KadenaStatusWord::UntrustedDescriptor);
+    }
+    let descriptors = unsafe { &mut DESCRIPTORS };
+    descriptors.retain(|d| !(d.name == descriptor.name && d.labels.len() == arity));
+    if descriptors.is_full() {
+        descriptors.remove(0);
+    }
+    descriptors.push(descriptor);
+    Ok(())
+}
+
+pub fn find(name: &[u8], arity: usize) -> Option<&'static Descriptor> {
+    unsafe { &DESCRIPTORS }
+        .iter()
+        .find(|d| d.name.as_bytes() == name && d.labels.len() == arity)
+}
+
+impl CapabilityRenderer for Descriptor {
+    fn render(&self, cap: &Capability) -> Option<Recognized> {
+        // An amount with more decimals than the token has is not one, and the receiver of a
+        // transfer must be an account name
+        let (amount, receiver) = match &self.amount {
+            Some(a) => {
+                let unit = 10u128.pow(DECIMALS - a.decimals);
+                let value = match cap.arg(a.index).and_then(parse_amount) {
+                    Some(value) if value.0 % unit == 0 => value,
+                    _ => return Some(Recognized::No),
+                };
+                let receiver = match a.receiver {
+                    Some(j) => match account(cap.arg(j)) {
+                        Some(receiver) => Some((j, receiver)),
+                        None => return Some(Recognized::No),
+                    },
+                    None => None,
+                };
+                (Some((a, value)), receiver)
+            }
+            None => (None, None),
+        };
+        // The name is always shown, so that a title cannot pass for another capability
+        scroller(&self.title, |w| {
+            write!(w, "name: {}", self.name)?;
+            for (i, label) in self.labels.iter().enumerate() {
+                match amount {
+                    Some((a, value)) if a.index == i => write!(
+                        w,
+                        ", {}: {} {}",
+                        label,
+                        value.with_decimals(a.decimals),
+                        a.token
+                    )?,
+                    _ => write!(w, ", {}: {}", label, mkarg(cap.arg(i))?)?,
+                }
+            }
+            Ok(())
+        })?;
+        match (amount, receiver) {
+            (Some((a, _)), Some((j, receiver))) => {
+                warn_lookalikes(&[cap.arg(j)])?;
+                check_recipient(receiver)?;
+                add_transfer(cap, j, a.index)?;
+                Some(Recognized::Transfer)
+            }
+            _ => Some(Recognized::Shown),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! The GAS_PAYER capability of gas stations, which pay for the gas of the commands they accept.
+//!
+//! Gas stations are contracts of their own, in any namespace, implementing the `gas-payer-v1`
+//! interface: `(defcap GAS_PAYER:bool (user:string limit:integer price:decimal))`. The station
+//! pays as the sender of the command, so it is shown with the meta, see `review_gas_station`; the
+//! user it pays for must be the sender, and the limit and price those of the meta.
+
+use super::{Capability, CapabilityRenderer, Recognized};
+use crate::implementation::{parse_amount, review_gas_station};
+use crate::pact;
+use core::str::from_utf8;
+
+pub struct GasPayer;
+
+impl CapabilityRenderer for GasPayer {
+    fn render(&self, cap: &Capability) -> Option<Recognized> {
+        let text = |i| cap.arg(i).and_then(|a| from_utf8(a).ok());
+        let args_ok = text(0).and_then(pact::as_string).is_some()
+            && text(1).and_then(pact::amount).is_some()
+            && text(2).and_then(pact::amount).is_some();
+        if !args_ok {
+            return Some(Recognized::No);
+        }
+        let (limit, price) = (
+            cap.arg(1).and_then(parse_amount),
+            cap.arg(2).and_then(parse_amount),
+        );
+        let user = text(0).and_then(pact::as_string).map(str::as_bytes);
+        review_gas_station(cap.name, user, limit, price)?;
+        Some(Recognized::Shown)
+    }
+}
@@ -0,0 +1,217 @@
+//! Exact decimal amounts, as used for KDA and other fungible tokens.
+//!
+//! Pact decimals are arbitrary precision, but the coin contract rounds amounts to 12 decimal
+//! places; numbers which cannot be written with 12 decimals are not parsed rather than rounded.
+
+use core::fmt;
+
+pub const DECIMALS: u32 = 12;
+const ONE: u128 = 10u128.pow(DECIMALS);
+
+/// A non-negative decimal number, in units of 10^-12.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub struct Decimal(pub u128);
+
+impl Decimal {
+    pub const fn from_integer(n: u64) -> Decimal {
+        Decimal(n as u128 * ONE)
+    }
+
+    /// Parses a JSON number, like `11`, `2.5` or `1.0e-5`.
+    pub fn parse(s: &[u8]) -> Option<Decimal> {
+        let (mantissa, exponent) = match s.iter().position(|b| matches!(b, b'e' | b'E')) {
+            Some(i) => (&s[..i], parse_exponent(&s[i + 1..])?),
+            None => (s, 0),
+        };
+        let (int_part, frac_part) = match mantissa.iter().position(|b| *b == b'.') {
+            Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+            None => (mantissa, &mantissa[mantissa.len()..]),
+        };
+        if int_part.is_empty() || (mantissa.len() > int_part.len() && frac_part.is_empty()) {
+            return None;
+        }
+        let mut digits: u128 = 0;
+        for d in int_part.iter().chain(frac_part) {
+            if !d.is_ascii_digit() {
+                return None;
+            }
+            digits = digits.checked_mul(10)?.checked_add((d - b'0') as u128)?;
+        }
+        // Zero is zero whatever its exponent, even one too large to apply
+        if digits == 0 {
+            return Some(Decimal(0));
+        }
+        // The value is digits * 10^shift units
+        let shift = DECIMALS as i32 + exponent - frac_part.len() as i32;
+        if shift >= 0 {
+            digits
+                .checked_mul(10u128.checked_pow(shift as u32)?)
+                .map(Decimal)
+        } else {
+            let divisor = 10u128.checked_pow((-shift) as u32).unwrap_or(u128::MAX);
+            // More than 12 decimals; only fine if those are zeros
+            (digits % divisor == 0).then(|| Decimal(digits / divisor))
+        }
+    }
+
+    pub fn checked_add(self, other: Decimal) -> Option<Decimal> {
+        self.0.checked_add(other.0).map(Decimal)
+    }
+
+    /// Multiplies by an integer, like a gas price by a gas limit.
+    pub fn checked_mul_int(self, n: u64) -> Option<Decimal> {
+        self.0.checked_mul(n as u128).map(Decimal)
+    }
+
+    /// Shown with exactly this many decimals, at most 12, like `1.50` for 2; further digits are
+    /// dropped.
+    pub fn with_decimals(self, decimals: u32) -> WithDecimals {
+        WithDecimals(self, decimals)
+    }
+}
+
+pub struct WithDecimals(Decimal, u32);
+
+fn parse_exponent(s: &[u8]) -> Option<i32> {
+    let (negative, digits) = match s {
+        [b'-', rest @ ..] => (true, rest),
+        [b'+', rest @ ..] => (false, rest),
+        _ => (false, s),
+    };
+    if digits.is_empty() || digits.len() > 3 {
+        return None;
+    }
+    let mut n: i32 = 0;
+    for d in digits {
+        if !d.is_ascii_digit() {
+            return None;
+        }
+        n = n * 10 + (d - b'0') as i32;
+    }
+    Some(if negative { -n } else { n })
+}
+
+/// Shown without trailing zeros, like `152.3`, or `11` for a whole number.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0 / ONE)?;
+        let mut frac = self.0 % ONE;
+        if frac == 0 {
+            return Ok(());
+        }
+        let mut width = DECIMALS as usize;
+        while frac % 10 == 0 {
+            frac /= 10;
+            width -= 1;
+        }
+        write!(f, ".{:0width$}", frac, width = width)
+    }
+}
+
+impl fmt::Display for WithDecimals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let WithDecimals(Decimal(units), decimals) = *self;
+        write!(f, "{}", units / ONE)?;
+        if decimals == 0 {
+            return Ok(());
+        }
+        let frac = units % ONE / 10u128.pow(DECIMALS - decimals);
+        write!(f, ".{:0width$}", frac, width = decimals as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Decimal;
+    use arrayvec::ArrayString;
+    use core::fmt::Write;
+    #[cfg(target_family = "bolos")]
+    use nanos_sdk::{assert_eq_err as assert_eq, TestType};
+    #[cfg(target_family = "bolos")]
+    use testmacro::test_item as test;
+
+    fn parse(s: &str) -> Option<Decimal> {
+        Decimal::parse(s.as_bytes())
+    }
+
+    fn show(s: &str) -> ArrayString<48> {
+        let mut shown = ArrayString::new();
+        write!(shown, "{}", parse(s).unwrap()).unwrap();
+        shown
+    }
+
+    #[test]
+    fn parses_integers_and_fractions() {
+        assert_eq!(parse("11"), Some(Decimal::from_integer(11)));
+        assert_eq!(parse("2.5"), Some(Decimal(2_500_000_000_000)));
+        assert_eq!(parse("0.000000000001"), Some(Decimal(1)));
+        assert_eq!(parse(".5"), None);
+        assert_eq!(parse("5."), None);
+        assert_eq!(parse("-5"), None);
+        assert_eq!(parse("1.2.3"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn parses_exponents() {
+        assert_eq!(parse("1.0e-5"), Some(Decimal(10_000_000)));
+        assert_eq!(parse("1E3"), Some(Decimal::from_integer(1000)));
+        assert_eq!(parse("1e+3"), Some(Decimal::from_integer(1000)));
+        assert_eq!(parse("15e-13"), None);
+        assert_eq!(parse("10e-13"), Some(Decimal(1)));
+        assert_eq!(parse("1e"), None);
+        assert_eq!(parse("1e-"), None);
+        assert_eq!(parse("1e1000"), None);
+        assert_eq!(parse("0e999"), Some(Decimal(0)));
+    }
+
+    #[test]
+    fn refuses_more_than_12_decimals_unless_zeros() {
+        assert_eq!(parse("1.0000000000001"), None);
+        assert_eq!(parse("1.0000000000000"), Some(Decimal::from_integer(1)));
+        assert_eq!(parse("0.1234567890120000"), Some(Decimal(123_456_789_012)));
+        assert_eq!(parse("1e-13"), None);
+        assert_eq!(parse("1e-999"), None);
+    }
+
+    #[test]
+    fn refuses_overflow() {
+        // u128::MAX is 340282366920938463463374607431768211455 units
+        assert_eq!(
+            parse("340282366920938463463374607.431768211455"),
+            Some(Decimal(u128::MAX))
+        );
+        assert_eq!(parse("340282366920938463463374607.431768211456"), None);
+        assert_eq!(parse("340282366920938463463374608"), None);
+        assert_eq!(parse("1e27"), None);
+        assert_eq!(Decimal(u128::MAX).checked_add(Decimal(1)), None);
+        assert_eq!(Decimal(u128::MAX).checked_mul_int(2), None);
+    }
+
+    #[test]
+    fn shows_without_trailing_zeros() {
+        assert_eq!(show("11.000").as_str(), "11");
+        assert_eq!(show("152.30").as_str(), "152.3");
+        assert_eq!(show("1.0e-5").as_str(), "0.00001");
+        assert_eq!(show("0.000000000001").as_str(), "0.000000000001");
+        assert_eq!(show("0").as_str(), "0");
+    }
+
+    #[test]
+    fn shows_with_decimals() {
+        let with_decimals = |s, decimals| {
+            let mut shown = ArrayString::<48>::new();
+            write!(shown, "{}", parse(s).unwrap().with_decimals(decimals)).unwrap();
+            shown
+        };
+        assert_eq!(with_decimals("1.5", 2).as_str(), "1.50");
+        assert_eq!(with_decimals("11", 3).as_str(), "11.000");
+        assert_eq!(with_decimals("0.05", 2).as_str(), "0.05");
+        assert_eq!(with_decimals("7", 0).as_str(), "7");
+        assert_eq!(with_decimals("1.25", 1).as_str(), "1.2");
+        assert_eq!(
+            with_decimals("0.000000000001", 12).as_str(),
+            "0.000000000001"
+        );
+    }
+}
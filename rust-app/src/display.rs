@@ -0,0 +1,47 @@
+//! Rendering of untrusted strings on the device screen.
+//!
+//! Strings from the transaction are shown as they appear in the JSON, escapes included, so the
+//! user sees exactly what the node will decode. Characters the screen cannot show, or which
+//! could change the layout of a prompt, are written as JSON `\uXXXX` escapes instead; those
+//! denote the same string, so nothing is lost.
+
+use core::fmt;
+
+/// Displays a string with every character outside of printable ASCII escaped.
+#[derive(Clone, Copy)]
+pub struct Sanitized<'a>(pub &'a str);
+
+impl fmt::Display for Sanitized<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut plain = 0;
+        for (i, c) in self.0.char_indices() {
+            if (' '..='~').contains(&c) {
+                continue;
+            }
+            f.write_str(&self.0[plain..i])?;
+            let mut units = [0; 2];
+            for unit in c.encode_utf16(&mut units) {
+                write!(f, "\\u{:04X}", unit)?;
+            }
+            plain = i + c.len_utf8();
+        }
+        f.write_str(&self.0[plain..])
+    }
+}
+
+/// Whether a JSON string contains characters outside of ASCII, raw or escaped, which could be
+/// mistaken for other ones; like a Cyrillic "а" in an account name made to look like another.
+pub fn has_lookalikes(s: &str) -> bool {
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if !b.is_ascii() {
+            return true;
+        }
+        // Also skips the escaped character, so that the "u" of "\\u" is not taken for an
+        // escape; escaped ASCII, like `\u0041`, is just as misleading as the real thing.
+        if b == b'\\' && bytes.next() == Some(b'u') {
+            return true;
+        }
+    }
+    false
+}
@@ -1,5 +1,12 @@
 #![allow(clippy::type_complexity)]
+use crate::accounts::{own_key_index, OwnAccountNote};
+use crate::address_book;
+use crate::capabilities::{self, Capability, Recognized};
+use crate::decimal::Decimal;
+use crate::display::*;
 use crate::interface::*;
+use crate::pact::{self, PactValue};
+use crate::settings::*;
 use crate::*;
 use arrayvec::ArrayString;
 use arrayvec::ArrayVec;
@@ -11,7 +18,8 @@ use ledger_crypto_helpers::eddsa::{
 };
 use ledger_crypto_helpers::hasher::{Blake2b, Hash, Hasher};
 use ledger_log::info;
-use ledger_parser_combinators::core_parsers::Alt;
+use ledger_parser_combinators::core_parsers::{Alt, Byte, U32};
+use ledger_parser_combinators::endianness::Endianness;
 use ledger_parser_combinators::interp_parser::{
     set_from_thunk, Action, DefaultInterp, DropInterp, InterpParser, ObserveLengthedBytes,
     SubInterp, OOB,
@@ -85,8 +93,62 @@ pub fn scroller_paginated<F: for<'b> Fn(&mut PromptWrite<'b, 16>) -> Result<(),
     ledger_prompts_ui::write_scroller(true, title, prompt_function)
 }
 
-fn mkstr(v: Option<&[u8]>) -> Result<&str, ScrollerError> {
-    Ok(from_utf8(v.ok_or(ScrollerError)?)?)
+// The reason the current request was rejected, if the host should get something more specific
+// than a generic error; the parsers can only signal that they rejected the input.
+static mut REJECT_REASON: Option<KadenaStatusWord> = None;
+
+pub fn reject_with<T>(reason: KadenaStatusWord) -> Option<T> {
+    unsafe {
+        REJECT_REASON = Some(reason);
+    }
+    None
+}
+
+fn reject_reason_pending() -> bool {
+    unsafe { REJECT_REASON.is_some() }
+}
+
+pub fn take_reject_reason() -> Option<KadenaStatusWord> {
+    unsafe { REJECT_REASON.take() }
+}
+
+// Only for a declined `final_accept_prompt`; a scroller which fails to format its prompt is
+// an unrenderable value, which `Lenient` may still drop.
+fn user_rejected() -> Option<()> {
+    reject_with(KadenaStatusWord::UserRejected)
+}
+
+// Strings from the transaction must only be shown through these, see `Sanitized`
+fn sanitized(v: &[u8]) -> Result<Sanitized, ScrollerError> {
+    Ok(Sanitized(from_utf8(v)?))
+}
+
+fn mkstr(v: Option<&[u8]>) -> Result<Sanitized, ScrollerError> {
+    sanitized(v.ok_or(ScrollerError)?)
+}
+
+// Capability args are shown as Pact values, see `PactValue`
+pub fn mkarg(v: Option<&[u8]>) -> Result<PactValue, ScrollerError> {
+    Ok(PactValue::parse(from_utf8(v.ok_or(ScrollerError)?)?))
+}
+
+// Hashes, nonces, signer keys, raw capabilities and derivation paths are only shown in expert mode
+fn expert_mode() -> bool {
+    Settings::new().is_enabled(SettingFlag::ExpertMode)
+}
+
+fn write_bip32_path<W: Write>(w: &mut W, path: &[u32]) -> core::fmt::Result {
+    for (i, step) in path.iter().enumerate() {
+        if i > 0 {
+            write!(w, "/")?;
+        }
+        if step & 0x8000_0000 != 0 {
+            write!(w, "{}'", step & 0x7fff_ffff)?;
+        } else {
+            write!(w, "{}", step)?;
+        }
+    }
+    Ok(())
 }
 
 pub type GetAddressImplT = impl InterpParser<Bip32Key, Returning = ArrayVec<u8, 128_usize>>;
@@ -98,7 +160,56 @@ pub const GET_ADDRESS_IMPL: GetAddressImplT = Action(
                 try_option(|| -> Option<()> {
                     scroller("Provide Public Key", |w| Ok(write!(w, "{}", pkh)?))?;
 
-                    final_accept_prompt(&[])?;
+                    final_accept_prompt(&[]).or_else(user_rejected)?;
+
+                    *destination = Some(ArrayVec::new());
+                    // key without y parity
+                    let key_x = ed25519_public_key_bytes(key);
+                    destination
+                        .as_mut()?
+                        .try_push(u8::try_from(key_x.len()).ok()?)
+                        .ok()?;
+                    destination.as_mut()?.try_extend_from_slice(key_x).ok()?;
+                    Some(())
+                }())
+            })
+            .ok()
+        },
+    ),
+);
+
+type VerifyAddressParserT = (SubDefT, (SubDefT, SubDefT));
+
+pub type VerifyAddressImplT =
+    impl InterpParser<VerifyAddressParameters, Returning = ArrayVec<u8, 128_usize>>;
+// Unlike GET_PUBKEY, the account is shown for the user to compare it with the one shown by the
+// wallet, and rejecting it means that they differ; it gets its own status word.
+pub const VERIFY_ADDRESS_IMPL: VerifyAddressImplT = Action(
+    (SUB_DEF, (SUB_DEF, SUB_DEF)),
+    mkfn(
+        |(path, optv1): &<VerifyAddressParserT as ParserCommon<VerifyAddressParameters>>::Returning,
+         destination: &mut Option<ArrayVec<u8, 128>>| {
+            let (chain, network) = optv1.as_ref()?;
+            let (chain, network) = (chain.as_ref()?, network.as_ref()?);
+            let chain = match chain.as_slice() {
+                [] => None,
+                c => match parse_chain_id(c) {
+                    Some(chain) => Some(chain),
+                    None => return reject_with(KadenaStatusWord::InvalidChain),
+                },
+            };
+            with_public_keys(path.as_ref()?, |key: &_, pkh: &PKH| {
+                try_option(|| -> Option<()> {
+                    scroller_paginated("Verify Address", |w| Ok(write!(w, "k:{}", pkh)?))?;
+                    if let Some(chain) = chain {
+                        scroller("On Chain", |w| Ok(write!(w, "{}", chain)?))?;
+                    }
+                    if !network.is_empty() {
+                        show_network(Some(network.as_slice()))?;
+                    }
+                    #[allow(clippy::needless_borrow)] // Needed for nanos
+                    final_accept_prompt(&[&"Approve Address?"])
+                        .or_else(|| reject_with(KadenaStatusWord::AddressRejected))?;
 
                     *destination = Some(ArrayVec::new());
                     // key without y parity
@@ -146,93 +257,23 @@ pub static SIGN_IMPL: SignImplT = Action(
     (
         Action(
             // Calculate the hash of the transaction
-            ObserveLengthedBytes(
-                Hasher::new,
-                Hasher::update,
-                Json(Action(Preaction( || -> Option<()> { scroller("Signing", |w| Ok(write!(w, "Transaction")?)) } , KadenaCmdInterp {
-                    field_nonce: DropInterp,
-                    field_meta: META_ACTION,
-                    field_payload: PayloadInterp {
-                        field_exec: CommandInterp {
-                            field_code: DropInterp,
-                            field_data: DropInterp
-                        }},
-                    field_signers: SubInterpM::<_, CapabilityCoverage>::new(Action(Preaction(
-                            || -> Option<()> {
-                                scroller("Requiring", |w| Ok(write!(w, "Capabilities")?))
-                            },
-                            SignerInterp {
-                        field_scheme: DropInterp,
-                        field_pub_key: MoveAction(JsonStringAccumulate::<64>, mkmvfn(|key : ArrayVec<u8, 64>, dest: &mut Option<ArrayVec<u8, 64>>| -> Option<()> {
-                            scroller("Of Key", |w| Ok(write!(w, "{}", from_utf8(key.as_slice())?)?))?;
-                            set_from_thunk(dest, || Some(key));
-                            Some(())
-                        })),
-                        field_addr: DropInterp,
-                        field_clist: Alt(DropInterp, CLIST_ACTION),
-                    }),
-                        mkfn(|signer: &Signer<_,Option<ArrayVec<u8, 64>>,_, Option<AltResult<(),(CapCountData, All)>>>, dest: &mut Option<CapabilityCoverage> | {
-                            *dest = Some(match signer.field_clist {
-                                Some(AltResult::Second((CapCountData::CapCount{total_caps,..}, All(a)))) if total_caps > 0 => if a {CapabilityCoverage::Full} else {CapabilityCoverage::HasFallback},
-                                _ => {
-                                    match from_utf8(signer.field_pub_key.as_ref()?.as_slice()) {
-                                        Ok(pub_key) => scroller("Unscoped Signer", |w| Ok(write!(w, "{}", pub_key)?)),
-                                        _ => Some(()),
-                                    };
-                                    CapabilityCoverage::NoCaps
-                                },
-                            });
-                            Some(())
-                        })),
-                        ),
-                    field_network_id: Action(Alt(JsonStringAccumulate::<32>, DropInterp), mkvfn(|mnet: &AltResult<ArrayVec<u8, 32>, ()>, dest: &mut Option<()>| {
-                        *dest = Some(());
-                        match mnet {
-                            AltResult::First(net) => {
-                                scroller("On Network", |w| Ok(write!(w, "{}", from_utf8(net.as_slice())?)?))
-                            }
-                            _ => { Some(())} // Ignore null
-                        }
-                    }))
-                }),
-                mkvfn(|cmd : &KadenaCmd<_,_,Option<CapabilityCoverage>,_,_>, _| {
-                    match cmd.field_signers.as_ref() {
-                        Some(CapabilityCoverage::Full) => { }
-                        Some(CapabilityCoverage::HasFallback) => {
-                            scroller("WARNING", |w| Ok(write!(w, "Transaction too large for Ledger to display.  PROCEED WITH GREAT CAUTION.  Do you want to continue?")?))?;
-                        }
-                        _ => {
-                            scroller("WARNING", |w| Ok(write!(w, "UNSAFE TRANSACTION. This transaction's code was not recognized and does not limit capabilities for all signers. Signing this transaction may make arbitrary actions on the chain including loss of all funds.")?))?;
-                        }
-                    }
-                    Some(())
-                })
-                )),
-            true),
+            ObserveLengthedBytes(Hasher::new, Hasher::update, SIGN_CMD_ACTION, true),
             // Ask the user if they accept the transaction body's hash
             mkfn(|(_, mut hasher): &(_, Blake2b), destination: &mut Option<Zeroizing<Hash<32>>>| {
                 let the_hash = hasher.finalize();
-                scroller("Transaction hash", |w| Ok(write!(w, "{}", the_hash.deref())?))?;
+                if expert_mode() {
+                    scroller("Transaction hash", |w| Ok(write!(w, "{}", the_hash.deref())?))?;
+                }
                 *destination=Some(the_hash);
                 Some(())
             }),
         ),
-        MoveAction(
-            SubInterp(DefaultInterp),
-            // And ask the user if this is the key the meant to sign with:
-            mkmvfn(|path: ArrayVec<u32, 10>, destination: &mut Option<ArrayVec<u32, 10>>| {
-                with_public_keys(&path, |_, pkh: &PKH| { try_option(|| -> Option<()> {
-                    scroller("Sign for Address", |w| Ok(write!(w, "{pkh}")?))?;
-                    Some(())
-                }())}).ok()?;
-                *destination = Some(path);
-                Some(())
-            }),
-        ),
+        SIGN_PATH_ACTION,
     ),
     mkfn(|(hash, path): &(Option<Zeroizing<Hash<32>>>, Option<ArrayVec<u32, 10>>), destination: &mut _| {
+        show_transfer_totals()?;
         #[allow(clippy::needless_borrow)] // Needed for nanos
-        final_accept_prompt(&[&"Sign Transaction?"])?;
+        final_accept_prompt(&[&"Sign Transaction?"]).or_else(user_rejected)?;
 
         // By the time we get here, we've approved and just need to do the signature.
         let sig = eddsa_sign(path.as_ref()?, &hash.as_ref()?.0[..]).ok()?;
@@ -243,29 +284,258 @@ pub static SIGN_IMPL: SignImplT = Action(
     }),
 );
 
+type SignCmdActionT = impl InterpParser<Json<KadenaCmdSchema>, Returning = ()>;
+const SIGN_CMD_ACTION: SignCmdActionT = Json(Action(
+    Preaction(
+        || -> Option<()> {
+            reset_tx_review();
+            reset_unscoped_signers();
+            reset_transfer_totals();
+            reset_station_senders();
+            scroller("Signing", |w| Ok(write!(w, "Transaction")?))
+        },
+        UniqueKeys(KadenaCmdInterp {
+            field_nonce: NONCE_ACTION,
+            field_meta: META_ACTION,
+            field_payload: PAYLOAD_ACTION,
+            field_signers: SIGNERS_ACTION,
+            field_network_id: NETWORK_ID_ACTION,
+            field_verifiers: VERIFIERS_ACTION,
+        }),
+    ),
+    mkvfn(|cmd: &KadenaCmd<_, _, Option<CapabilityCoverage>, _, _, Option<All>>, dest| {
+        *dest = Some(());
+        if cmd.field_network_id.is_none() {
+            show_network(None)?;
+        }
+        check_gas_station_shown()?;
+        show_coverage_warning(command_coverage(
+            cmd.field_signers.as_ref(),
+            cmd.field_verifiers.as_ref(),
+        ))
+    }),
+));
+
+// The interpreters for the fields of a command, shared by the parsers signing a JSON transaction
+
+type NonceActionT = impl JsonInterp<Alt<JsonString, JsonAny>, Returning = ()>;
+const NONCE_ACTION: NonceActionT = Action(
+    OrDropAny(JsonStringAccumulate::<64>),
+    mkvfn(|nonce: &Option<ArrayVec<u8, 64>>, dest| -> Option<()> {
+        *dest = Some(());
+        if !expert_mode() {
+            return Some(());
+        }
+        match nonce {
+            Some(n) => scroller("Nonce", |w| Ok(write!(w, "{}", sanitized(n.as_slice())?)?)),
+            None => scroller("Nonce", |w| Ok(write!(w, "too long to display")?)),
+        }
+    }),
+);
+
+type PayloadActionT = impl JsonInterp<PayloadSchema>;
+const PAYLOAD_ACTION: PayloadActionT = UniqueKeys(PayloadInterp {
+    field_exec: UniqueKeys(CommandInterp {
+        field_code: DropInterp,
+        field_data: DropInterp,
+    }),
+});
+
+type SignersActionT = impl JsonInterp<JsonArray<SignerSchema>, Returning = CapabilityCoverage>;
+const SIGNERS_ACTION: SignersActionT = SubInterpM::<_, CapabilityCoverage>::new(Action(Preaction(
+        || -> Option<()> {
+            set_in_verifier(false);
+            if expert_mode() {
+                scroller("Requiring", |w| Ok(write!(w, "Capabilities")?))
+            } else {
+                Some(())
+            }
+        },
+        UniqueKeys(SignerInterp {
+    field_scheme: DropInterp,
+    field_pub_key: MoveAction(JsonStringAccumulate::<64>, mkmvfn(|key : ArrayVec<u8, 64>, dest: &mut Option<ArrayVec<u8, 64>>| -> Option<()> {
+        if expert_mode() {
+            let note = OwnAccountNote(own_key_index(&key));
+            scroller("Of Key", |w| Ok(write!(w, "{}{}", sanitized(key.as_slice())?, note)?))?;
+        }
+        set_from_thunk(dest, || Some(key));
+        Some(())
+    })),
+    field_addr: DropInterp,
+    field_clist: Alt(DropInterp, CLIST_ACTION),
+})),
+    mkfn(|signer: &Signer<_,Option<ArrayVec<u8, 64>>,_, Option<AltResult<(),(CapCountData, All)>>>, dest: &mut Option<CapabilityCoverage> | {
+        *dest = Some(match signer.field_clist {
+            Some(AltResult::Second((CapCountData::CapCount{total_caps,..}, All(a)))) if total_caps > 0 => if a {CapabilityCoverage::Full} else {CapabilityCoverage::HasFallback},
+            _ => {
+                let pub_key = signer.field_pub_key.as_ref()?.as_slice();
+                note_unscoped_signer(pub_key);
+                match from_utf8(pub_key) {
+                    Ok(pub_key) => scroller("Unscoped Signer", |w| Ok(write!(w, "{}", Sanitized(pub_key))?)),
+                    _ => Some(()),
+                };
+                CapabilityCoverage::NoCaps
+            },
+        });
+        Some(())
+    })),
+    );
+
+// The networks shown by name; anything else gets a warning, or is refused in strict mode.
+const KNOWN_NETWORKS: [(&[u8], &str); 3] = [
+    (b"mainnet01", "Mainnet"),
+    (b"testnet04", "TESTNET"),
+    (b"development", "Development"),
+];
+
+// Shows the network of a command, None being a null or missing networkId; returns whether it
+// is a known network.
+fn show_network(network: Option<&[u8]>) -> Option<bool> {
+    let strict = Settings::new().is_enabled(SettingFlag::StrictNetworks);
+    match network {
+        Some(net) => match KNOWN_NETWORKS.iter().find(|(id, _)| *id == net) {
+            Some((_, name)) => {
+                scroller("On Network", |w| Ok(write!(w, "{}", name)?))?;
+                return Some(true);
+            }
+            None if strict => return reject_with(KadenaStatusWord::UnknownNetwork),
+            None => scroller("WARNING", |w| {
+                Ok(write!(w, "Unknown network {}", sanitized(net)?)?)
+            })?,
+        },
+        None if strict => return reject_with(KadenaStatusWord::UnknownNetwork),
+        None => scroller("WARNING", |w| Ok(write!(w, "Replayable on any network")?))?,
+    }
+    Some(false)
+}
+
+type NetworkIdActionT = impl JsonInterp<Alt<JsonString, JsonNull>, Returning = bool>;
+const NETWORK_ID_ACTION: NetworkIdActionT = Action(
+    Alt(JsonStringAccumulate::<32>, DropInterp),
+    mkfn(|mnet: &AltResult<ArrayVec<u8, 32>, ()>, dest: &mut Option<bool>| {
+        *dest = Some(match mnet {
+            AltResult::First(net) => show_network(Some(net.as_slice()))?,
+            AltResult::Second(()) => show_network(None)?,
+        });
+        Some(())
+    }),
+);
+
+type VerifiersActionT = impl JsonInterp<JsonArray<VerifierSchema>, Returning = All>;
+const VERIFIERS_ACTION: VerifiersActionT = SubInterpM::<_, All>::new(Action(
+    Preaction(
+        || -> Option<()> {
+            set_in_verifier(true);
+            Some(())
+        },
+        UniqueKeys(VerifierInterp {
+            field_name: Action(
+                JsonStringAccumulate::<64>,
+                mkvfn(|name: &ArrayVec<u8, 64>, dest| -> Option<()> {
+                    *dest = Some(());
+                    scroller("Verifier", |w| Ok(write!(w, "{}", sanitized(name.as_slice())?)?))
+                }),
+            ),
+            // Proofs are too long to show, and meaningless to the user anyway
+            field_proof: Action(
+                JsonStringHasher,
+                mkvfn(|proof_hash: &Zeroizing<Hash<32>>, dest| -> Option<()> {
+                    *dest = Some(());
+                    if !expert_mode() {
+                        return Some(());
+                    }
+                    scroller("Proof hash", |w| Ok(write!(w, "{}", proof_hash.deref())?))
+                }),
+            ),
+            field_clist: Alt(DropInterp, CLIST_ACTION),
+        }),
+    ),
+    mkfn(
+        |verifier: &Verifier<_, _, Option<AltResult<(), (CapCountData, All)>>>,
+         dest: &mut Option<All>| {
+            *dest = Some(match &verifier.field_clist {
+                Some(AltResult::Second((_, All(a)))) => All(*a),
+                _ => All(true),
+            });
+            Some(())
+        },
+    ),
+));
+
+// The coverage of the signers' capabilities, lowered if those of a verifier could not all be shown
+fn command_coverage(
+    signers: Option<&CapabilityCoverage>,
+    verifiers: Option<&All>,
+) -> CapabilityCoverage {
+    let mut coverage = match signers {
+        Some(CapabilityCoverage::Full) => CapabilityCoverage::Full,
+        Some(CapabilityCoverage::HasFallback) => CapabilityCoverage::HasFallback,
+        _ => CapabilityCoverage::NoCaps,
+    };
+    if let Some(All(false)) = verifiers {
+        coverage.add_and_set(&CapabilityCoverage::HasFallback);
+    }
+    coverage
+}
+
+fn show_coverage_warning(coverage: CapabilityCoverage) -> Option<()> {
+    match coverage {
+        CapabilityCoverage::Full => Some(()),
+        CapabilityCoverage::HasFallback => {
+            scroller("WARNING", |w| Ok(write!(w, "Transaction too large for Ledger to display.  PROCEED WITH GREAT CAUTION.  Do you want to continue?")?))
+        }
+        CapabilityCoverage::NoCaps => {
+            scroller("WARNING", |w| Ok(write!(w, "UNSAFE TRANSACTION. This transaction's code was not recognized and does not limit capabilities for all signers. Signing this transaction may make arbitrary actions on the chain including loss of all funds.")?))
+        }
+    }
+}
+
+type SignPathActionT = impl InterpParser<Bip32Key, Returning = ArrayVec<u32, 10>>;
+const SIGN_PATH_ACTION: SignPathActionT = MoveAction(
+    SubInterp(DefaultInterp),
+    // And ask the user if this is the key the meant to sign with:
+    mkmvfn(
+        |path: ArrayVec<u32, 10>, destination: &mut Option<ArrayVec<u32, 10>>| {
+            with_public_keys(&path, |_, pkh: &PKH| {
+                try_option(|| -> Option<()> {
+                    check_unscoped_signer(pkh)?;
+                    check_station_sender(pkh)?;
+                    scroller("Sign for Address", |w| Ok(write!(w, "{pkh}")?))?;
+                    if expert_mode() {
+                        scroller("Derivation Path", |w| Ok(write_bip32_path(w, &path)?))?;
+                    }
+                    Some(())
+                }())
+            })
+            .ok()?;
+            *destination = Some(path);
+            Some(())
+        },
+    ),
+);
+
 const META_ACTION: Action<
-    Alt<
-        MetaInterp<
-            Action<
+    UniqueKeys<
+        Alt<
+            MetaInterp<
                 JsonStringAccumulate<32_usize>,
-                fn(&ArrayVec<u8, 32_usize>, &mut Option<()>) -> Option<()>,
+                OrDropAny<JsonStringAccumulate<SENDER_SIZE>>,
+                JsonStringAccumulate<100_usize>,
+                JsonStringAccumulate<100_usize>,
+                JsonStringAccumulate<100_usize>,
+                DropInterp,
             >,
             DropInterp,
-            JsonStringAccumulate<100_usize>,
-            JsonStringAccumulate<100_usize>,
-            DropInterp,
-            DropInterp,
         >,
-        DropInterp,
     >,
     fn(
         &AltResult<
             Meta<
-                Option<()>,
-                Option<()>,
+                Option<ArrayVec<u8, 32_usize>>,
+                Option<Option<ArrayVec<u8, SENDER_SIZE>>>,
+                Option<ArrayVec<u8, 100_usize>>,
                 Option<ArrayVec<u8, 100_usize>>,
                 Option<ArrayVec<u8, 100_usize>>,
-                Option<()>,
                 Option<()>,
             >,
             (),
@@ -273,520 +543,1659 @@ const META_ACTION: Action<
         &mut Option<()>,
     ) -> Option<()>,
 > = Action(
-    Alt(
+    UniqueKeys(Alt(
         MetaInterp {
-            field_chain_id: Action(
-                JsonStringAccumulate::<32>,
-                mkvfn(|chain: &ArrayVec<u8, 32>, _| -> Option<()> {
-                    scroller("On Chain", |w| {
-                        Ok(write!(w, "{}", from_utf8(chain.as_slice())?)?)
-                    })
-                }),
-            ),
-            field_sender: DropInterp,
+            field_chain_id: JsonStringAccumulate::<32>,
+            field_sender: OrDropAny(JsonStringAccumulate::<SENDER_SIZE>),
             field_gas_limit: JsonStringAccumulate::<100>,
             field_gas_price: JsonStringAccumulate::<100>,
-            field_ttl: DropInterp,
+            field_ttl: JsonStringAccumulate::<100>,
             field_creation_time: DropInterp,
         },
         DropInterp,
-    ),
+    )),
+    // The prompts are only shown once the whole meta is parsed, as a rejection inside of the
+    // Alt would make it fall back to dropping the meta.
     mkvfn(|v, _| match v {
         AltResult::First(Meta {
+            ref field_chain_id,
+            ref field_sender,
             ref field_gas_limit,
             ref field_gas_price,
+            ref field_ttl,
             ..
-        }) => scroller("Using Gas", |w| {
-            Ok(write!(
-                w,
-                "at most {} at price {}",
-                from_utf8(field_gas_limit.as_ref().ok_or(ScrollerError)?.as_slice())?,
-                from_utf8(field_gas_price.as_ref().ok_or(ScrollerError)?.as_slice())?
-            )?)
-        }),
+        }) => {
+            let chain = match field_chain_id.as_ref().and_then(|c| parse_chain_id(c)) {
+                Some(chain) => chain,
+                None => return reject_with(KadenaStatusWord::InvalidChain),
+            };
+            scroller("On Chain", |w| Ok(write!(w, "{}", chain)?))?;
+            review_source_chain(chain)?;
+            scroller("Using Gas", |w| {
+                Ok(write!(
+                    w,
+                    "at most {} at price {}",
+                    mkstr(field_gas_limit.as_ref().map(|l| l.as_slice()))?,
+                    mkstr(field_gas_price.as_ref().map(|p| p.as_slice()))?
+                )?)
+            })?;
+            let meta = MetaValues {
+                gas_price: field_gas_price.as_ref().map(|p| p.as_slice()),
+                gas_limit: field_gas_limit.as_ref().map(|l| l.as_slice()),
+                ttl: field_ttl.as_ref().map(|t| t.as_slice()),
+            };
+            check_meta(&meta)?;
+            let sender = field_sender.as_ref().and_then(|s| s.as_ref());
+            review_sender(sender.map(|s| s.as_slice()), &meta)
+        }
         _ => scroller("CAUTION", |w| {
             Ok(write!(w, "'meta' field of transaction not recognized")?)
         }),
     }),
 );
 
-#[derive(Debug, Clone, Copy)]
-enum CapCountData {
-    IsTransfer,
-    IsUnknownCap,
-    CapCount {
-        total_caps: u16,
-        total_transfers: u16,
-        total_unknown: u16,
-    },
+// The signers without capabilities in the commands being signed, by a hash of their public key.
+// Once the path is known, signing for one of them is refused unless allowed in the settings.
+const MAX_UNSCOPED_SIGNERS: usize = 4;
+
+struct UnscopedSigners {
+    keys: ArrayVec<u32, MAX_UNSCOPED_SIGNERS>,
+    // Set if a key could not be recorded, or the signers could not be read; then no key is
+    // known to be safe to sign for.
+    overflow: bool,
 }
 
-impl Summable<CapCountData> for CapCountData {
-    fn add_and_set(&mut self, other: &CapCountData) {
-        if let CapCountData::CapCount {
-            total_caps,
-            total_transfers,
-            total_unknown,
-        } = self
-        {
-            *total_caps += 1;
-            match other {
-                CapCountData::IsTransfer => *total_transfers += 1,
-                CapCountData::IsUnknownCap => *total_unknown += 1,
-                _ => {}
-            }
-        }
+static mut UNSCOPED_SIGNERS: UnscopedSigners = UnscopedSigners {
+    keys: ArrayVec::new_const(),
+    overflow: false,
+};
+
+fn reset_unscoped_signers() {
+    unsafe {
+        UNSCOPED_SIGNERS.keys.clear();
+        UNSCOPED_SIGNERS.overflow = false;
     }
-    fn zero() -> Self {
-        CapCountData::CapCount {
-            total_caps: 0,
-            total_transfers: 0,
-            total_unknown: 0,
+}
+
+const FNV_OFFSET: u32 = 0x811c9dc5;
+const FNV_PRIME: u32 = 0x01000193;
+
+// FNV-1a hash of a public key in hex, ignoring case
+struct PubKeyHash(u32);
+
+impl Write for PubKeyHash {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for b in s.bytes() {
+            self.0 = (self.0 ^ b.to_ascii_lowercase() as u32).wrapping_mul(FNV_PRIME);
         }
+        Ok(())
     }
 }
 
-const CLIST_ACTION: SubInterpMFold<
-    Action<
-        KadenaCapabilityInterp<KadenaCapabilityArgsInterp, JsonStringAccumulate<32>>,
-        fn(
-            &KadenaCapability<
-                Option<<KadenaCapabilityArgsInterp as ParserCommon<JsonArray<JsonAny>>>::Returning>,
-                Option<ArrayVec<u8, 32>>,
-            >,
-            &mut Option<(CapCountData, bool)>,
-            (CapCountData, All),
-        ) -> Option<()>,
-    >,
-    (CapCountData, All),
-> = SubInterpMFold::new(Action(
-    KadenaCapabilityInterp {
-        field_args: KadenaCapabilityArgsInterp,
-        field_name: JsonStringAccumulate::<32>,
-    },
-    mkfnc(
-        |cap: &KadenaCapability<
-            Option<<KadenaCapabilityArgsInterp as ParserCommon<JsonArray<JsonAny>>>::Returning>,
-            Option<ArrayVec<u8, 32>>,
-        >,
-         destination: &mut Option<(CapCountData, bool)>,
-         v: (CapCountData, All)| {
-            let name = cap.field_name.as_ref()?.as_slice();
-            let name_utf8 = from_utf8(name).ok()?;
-            let mk_unknown_cap_title = || -> Option<_> {
-                let count = match v.0 {
-                    CapCountData::CapCount { total_unknown, .. } => total_unknown,
-                    _ => 0,
-                };
-                let mut buffer: ArrayString<22> = ArrayString::new();
-                write!(
-                    mk_prompt_write(&mut buffer),
-                    "Unknown Capability {}",
-                    count + 1
-                )
-                .ok()?;
-                Some(buffer)
-            };
-            let mk_transfer_title = || -> Option<_> {
-                let count = match v.0 {
-                    CapCountData::CapCount {
-                        total_transfers, ..
-                    } => total_transfers,
-                    _ => 0,
-                };
-                let mut buffer: ArrayString<22> = ArrayString::new();
-                write!(mk_prompt_write(&mut buffer), "Transfer {}", count + 1).ok()?;
-                Some(buffer)
-            };
+fn pub_key_hash(key: impl core::fmt::Display) -> Option<u32> {
+    let mut hash = PubKeyHash(FNV_OFFSET);
+    write!(hash, "{}", key).ok()?;
+    Some(hash.0)
+}
 
-            trace!("Prompting for capability");
-            *destination = Some((CapCountData::IsUnknownCap, true));
-            match cap.field_args.as_ref() {
-                Some((None, _)) => {
-                    if name == b"coin.GAS" {
-                        scroller("Paying Gas", |w| Ok(write!(w, " ")?))?;
-                        *destination = Some((Summable::zero(), true));
-                        trace!("Accepted gas");
-                    } else {
-                        scroller(&mk_unknown_cap_title()?, |w| {
-                            Ok(write!(w, "name: {}, no args", name_utf8)?)
-                        })?;
-                    }
-                }
-                Some((Some(Some(args)), arg_lengths)) => {
-                    if arg_lengths[3] != 0 {
-                        scroller(&mk_unknown_cap_title()?, |w| {
-                            Ok(write!(
-                                w,
-                                "name: {}, arg 1: {}, arg 2: {}, arg 3: {}, arg 4: {}, arg 5: {}",
-                                name_utf8,
-                                mkstr(args.as_slice().get(0..arg_lengths[0]))?,
-                                mkstr(args.as_slice().get(arg_lengths[0]..arg_lengths[1]))?,
-                                mkstr(args.as_slice().get(arg_lengths[1]..arg_lengths[2]))?,
-                                mkstr(args.as_slice().get(arg_lengths[2]..arg_lengths[3]))?,
-                                mkstr(args.as_slice().get(arg_lengths[3]..args.len()))?
-                            )?)
-                        })?;
-                    } else if arg_lengths[2] != 0 {
-                        if name == b"coin.TRANSFER_XCHAIN" {
-                            scroller(&mk_transfer_title()?, |w| {
-                                Ok(write!(
-                                    w,
-                                    "Cross-chain {} from {} to {} to chain {}",
-                                    mkstr(args.as_slice().get(arg_lengths[1]..arg_lengths[2]))?,
-                                    mkstr(args.as_slice().get(0..arg_lengths[0]))?,
-                                    mkstr(args.as_slice().get(arg_lengths[0]..arg_lengths[1]))?,
-                                    mkstr(args.as_slice().get(arg_lengths[2]..args.len()))?
-                                )?)
-                            })?;
-                            *destination = Some((CapCountData::IsTransfer, true));
-                        } else {
-                            scroller(&mk_unknown_cap_title()?, |w| {
-                                Ok(write!(
-                                    w,
-                                    "name: {}, arg 1: {}, arg 2: {}, arg 3: {}, arg 4: {}",
-                                    name_utf8,
-                                    mkstr(args.as_slice().get(0..arg_lengths[0]))?,
-                                    mkstr(args.as_slice().get(arg_lengths[0]..arg_lengths[1]))?,
-                                    mkstr(args.as_slice().get(arg_lengths[1]..arg_lengths[2]))?,
-                                    mkstr(args.as_slice().get(arg_lengths[2]..args.len()))?
-                                )?)
-                            })?;
-                        }
-                    } else if arg_lengths[1] != 0 {
-                        if name == b"coin.TRANSFER" {
-                            scroller(&mk_transfer_title()?, |w| {
-                                Ok(write!(
-                                    w,
-                                    "{} from {} to {}",
-                                    mkstr(args.as_slice().get(arg_lengths[1]..args.len()))?,
-                                    mkstr(args.as_slice().get(0..arg_lengths[0]))?,
-                                    mkstr(args.as_slice().get(arg_lengths[0]..arg_lengths[1]))?
-                                )?)
-                            })?;
-                            *destination = Some((CapCountData::IsTransfer, true));
-                        } else {
-                            scroller(&mk_unknown_cap_title()?, |w| {
-                                Ok(write!(
-                                    w,
-                                    "name: {}, arg 1: {}, arg 2: {}, arg 3: {}",
-                                    name_utf8,
-                                    mkstr(args.as_slice().get(0..arg_lengths[0]))?,
-                                    mkstr(args.as_slice().get(arg_lengths[0]..arg_lengths[1]))?,
-                                    mkstr(args.as_slice().get(arg_lengths[1]..args.len()))?
-                                )?)
-                            })?;
-                        }
-                    } else if arg_lengths[0] != 0 {
-                        scroller(&mk_unknown_cap_title()?, |w| {
-                            Ok(write!(
-                                w,
-                                "name: {}, arg 1: {}, arg 2: {}",
-                                name_utf8,
-                                mkstr(args.as_slice().get(0..arg_lengths[0]))?,
-                                mkstr(args.as_slice().get(arg_lengths[0]..args.len()))?
-                            )?)
-                        })?;
-                    } else if name == b"coin.ROTATE" {
-                        scroller("Rotate for account", |w| {
-                            Ok(write!(w, "{}", from_utf8(args.as_slice())?)?)
-                        })?;
-                        *destination = Some((Summable::zero(), true));
-                    } else {
-                        scroller(&mk_unknown_cap_title()?, |w| {
-                            Ok(write!(
-                                w,
-                                "name: {}, arg 1: {}",
-                                name_utf8,
-                                from_utf8(args.as_slice())?
-                            )?)
-                        })?;
-                    }
-                }
-                _ => {
-                    scroller(&mk_unknown_cap_title()?, |w| {
-                        Ok(write!(
-                            w,
-                            "name: {}, args cannot be displayed on Ledger",
-                            name_utf8
-                        )?)
-                    })?;
-                    set_from_thunk(destination, || Some((CapCountData::IsUnknownCap, false)));
-                    // Fallback case
-                }
-            }
-            Some(())
-        },
-    ),
-));
+fn note_unscoped_signer(pub_key: &[u8]) {
+    let signers = unsafe { &mut UNSCOPED_SIGNERS };
+    // An escaped key would not hash like the same key written plainly
+    let hash = match from_utf8(pub_key) {
+        Ok(key) if !key.contains('\\') => pub_key_hash(key),
+        _ => None,
+    };
+    match hash {
+        Some(hash) if signers.keys.try_push(hash).is_ok() => {}
+        _ => signers.overflow = true,
+    }
+}
 
-pub type SignHashImplT = impl InterpParser<SignHashParameters, Returning = ArrayVec<u8, 128_usize>>;
+// For a command whose signers were dropped or missing, any of which could be unscoped, with
+// transfers which cannot be added up or checked
+fn note_unknown_signers() -> Option<()> {
+    unsafe {
+        UNSCOPED_SIGNERS.overflow = true;
+        TRANSFER_TOTALS.unknown_transfers = true;
+    }
+    address_book::check_unknown_recipient()
+}
 
-pub static SIGN_HASH_IMPL: SignHashImplT = Action(
-    Preaction(
-        || -> Option<()> {
-            scroller("WARNING", |w| {
-                Ok(write!(w, "Blind Signing a Transaction Hash is a very unusual operation. Do not continue unless you know what you are doing")?)
-            })
-        },
-        (
-            Action(
-                SubInterp(DefaultInterp),
-                // Ask the user if they accept the transaction body's hash
-                mkfn(|hash_val: &[u8; 32], destination: &mut Option<[u8; 32]>| {
-                    let the_hash = Hash(*hash_val);
-                    scroller("Transaction hash", |w| Ok(write!(w, "{}", the_hash)?))?;
-                    *destination = Some(the_hash.0);
-                    Some(())
-                }),
-            ),
-            MoveAction(
-                SubInterp(DefaultInterp),
-                // And ask the user if this is the key the meant to sign with:
-                mkmvfn(
-                    |path: ArrayVec<u32, 10>, destination: &mut Option<ArrayVec<u32, 10>>| {
-                        with_public_keys(&path, |_, pkh: &PKH| {
-                            try_option(|| -> Option<()> {
-                                scroller("Sign for Address", |w| Ok(write!(w, "{}", pkh)?))?;
-                                Some(())
-                            }())
-                        })
-                        .ok()?;
-                        *destination = Some(path);
-                        Some(())
-                    },
-                ),
-            ),
-        ),
-    ),
-    mkfn(
-        |(hash, path): &(Option<[u8; 32]>, Option<ArrayVec<u32, 10>>), destination: &mut _| {
-            #[allow(clippy::needless_borrow)] // Needed for nanos
-            final_accept_prompt(&[&"Sign Transaction Hash?"])?;
+fn check_unscoped_signer(pkh: &PKH) -> Option<()> {
+    if Settings::new().is_enabled(SettingFlag::AllowUnscoped) {
+        return Some(());
+    }
+    let signers = unsafe { &UNSCOPED_SIGNERS };
+    if !signers.overflow && !signers.keys.contains(&pub_key_hash(pkh)?) {
+        return Some(());
+    }
+    // The reason is shown even if the user rejects this screen
+    let _ = scroller("Refused", |w| {
+        Ok(write!(w, "Signing key has no capabilities. Allow Unscoped Sigs in Settings to sign")?)
+    });
+    reject_with(KadenaStatusWord::UnscopedSigner)
+}
 
-            // By the time we get here, we've approved and just need to do the signature.
-            let sig = eddsa_sign(path.as_ref()?, &hash.as_ref()?[..]).ok()?;
-            let mut rv = ArrayVec::<u8, 128>::new();
-            rv.try_extend_from_slice(&sig.0[..]).ok()?;
-            *destination = Some(rv);
-            Some(())
-        },
-    ),
-);
+// The gas and TTL of a command, as written in the JSON
+struct MetaValues<'a> {
+    gas_price: Option<&'a [u8]>,
+    gas_limit: Option<&'a [u8]>,
+    ttl: Option<&'a [u8]>,
+}
 
-pub struct KadenaCapabilityArgsInterp;
+// Each rule shows its own warning if the values are outside of the limits chosen in the
+// settings. Values which cannot be parsed, like a number too large, get the warning as well.
+type MetaRule = fn(&MetaValues, &Settings) -> Option<()>;
 
-// The Caps list is parsed and the args are stored in a single common ArrayVec of this size.
-// (This may be as large as the stack allows)
-#[cfg(target_os = "nanos")]
-const ARG_ARRAY_SIZE: usize = 184;
-#[cfg(not(target_os = "nanos"))]
-const ARG_ARRAY_SIZE: usize = 2048;
-const MAX_ARG_COUNT: usize = 5;
+const META_RULES: [MetaRule; 3] = [check_gas_price, check_gas_limit, check_ttl];
 
-// Since we use a single ArrayVec to store the rendered json of all the args.
-// This list keeps track of the indices in the array for each arg, and even the args count
+fn check_meta(meta: &MetaValues) -> Option<()> {
+    let settings = Settings::new();
+    for rule in META_RULES {
+        rule(meta, &settings)?;
+    }
+    Some(())
+}
 
-// If there are three args; then indices[0] will contain the end of first arg, indices[1] will be end of second, and indices[2] will be 0
-// In other words, first arg will be: array[0..indices[0]], second: array[indices[0]..indices[1]], third: array[indices[1]..array.len()]
-type ArgListIndicesT = [usize; MAX_ARG_COUNT - 1];
+fn exceeds(value: &[u8], max: Decimal) -> bool {
+    Decimal::parse(value).map_or(true, |v| v > max)
+}
 
-// The Alt parser will first try to parse JsonAny and render it upto the available space in array
-// on hitting end of array it will fallback to the OrDropAny
-type CapArgT = Alt<JsonAny, JsonAny>;
-type CapArgInterpT = OrDropAny<JsonStringAccumulate<ARG_ARRAY_SIZE>>;
+fn check_gas_price(meta: &MetaValues, settings: &Settings) -> Option<()> {
+    let max = settings.max_gas_price();
+    match meta.gas_price {
+        Some(price) if exceeds(price, max) => scroller("WARNING", |w| {
+            Ok(write!(w, "Gas price of {} KDA is above {}", sanitized(price)?, max)?)
+        }),
+        _ => Some(()),
+    }
+}
 
-#[derive(Debug)]
-pub enum KadenaCapabilityArgsInterpState {
-    Start,
-    Begin,
-    Argument(<CapArgInterpT as ParserCommon<CapArgT>>::State),
-    ValueSep,
-    FallbackValue(<DropInterp as ParserCommon<JsonAny>>::State),
-    FallbackValueSep,
+fn check_gas_limit(meta: &MetaValues, settings: &Settings) -> Option<()> {
+    let max = settings.max_gas_limit();
+    match meta.gas_limit {
+        Some(limit) if exceeds(limit, Decimal::from_integer(max)) => scroller("WARNING", |w| {
+            Ok(write!(w, "Gas limit of {} is above {}", sanitized(limit)?, max)?)
+        }),
+        _ => Some(()),
+    }
 }
 
-impl ParserCommon<JsonArray<JsonAny>> for KadenaCapabilityArgsInterp {
-    type State = (
-        KadenaCapabilityArgsInterpState,
-        Option<<DropInterp as ParserCommon<JsonAny>>::Returning>,
-        usize,
-    );
-    type Returning = (
-        Option<<CapArgInterpT as ParserCommon<CapArgT>>::Returning>,
-        ArgListIndicesT,
-    );
-    fn init(&self) -> Self::State {
-        (KadenaCapabilityArgsInterpState::Start, None, 0)
+fn check_ttl(meta: &MetaValues, settings: &Settings) -> Option<()> {
+    let max = settings.max_ttl();
+    match meta.ttl {
+        Some(ttl) if exceeds(ttl, Decimal::from_integer(max)) => scroller("WARNING", |w| {
+            Ok(write!(w, "Valid for {} seconds, more than {}", sanitized(ttl)?, max)?)
+        }),
+        _ => Some(()),
     }
 }
-impl JsonInterp<JsonArray<JsonAny>> for KadenaCapabilityArgsInterp {
-    #[inline(never)]
-    fn parse<'a, 'b>(
-        &self,
-        (ref mut state, ref mut scratch, ref mut arg_count): &'b mut Self::State,
-        token: JsonToken<'a>,
-        destination: &mut Option<Self::Returning>,
-    ) -> Result<(), Option<OOB>> {
-        let str_interp = OrDropAny(JsonStringAccumulate::<ARG_ARRAY_SIZE>);
-        loop {
-            use KadenaCapabilityArgsInterpState::*;
-            match state {
-                Start if token == JsonToken::BeginArray => {
-                    set_from_thunk(destination, || Some((None, [0, 0, 0, 0])));
-                    set_from_thunk(state, || Begin);
-                }
-                Begin if token == JsonToken::EndArray => {
-                    return Ok(());
-                }
-                Begin => {
-                    set_from_thunk(state, || {
-                        Argument(<CapArgInterpT as ParserCommon<CapArgT>>::init(&str_interp))
-                    });
-                    *arg_count = 1;
-                    continue;
-                }
-                Argument(ref mut s) => {
-                    <CapArgInterpT as JsonInterp<CapArgT>>::parse(
-                        &str_interp,
-                        s,
-                        token,
-                        &mut destination.as_mut().ok_or(Some(OOB::Reject))?.0,
-                    )?;
-                    set_from_thunk(state, || ValueSep);
-                }
-                ValueSep if token == JsonToken::ValueSeparator => {
-                    match &destination.as_mut().ok_or(Some(OOB::Reject))?.0 {
-                        Some(Some(sub_dest)) if *arg_count < MAX_ARG_COUNT => {
-                            destination.as_mut().ok_or(Some(OOB::Reject))?.1[*arg_count - 1] =
-                                sub_dest.len();
-                            set_from_thunk(state, || {
-                                Argument(<CapArgInterpT as ParserCommon<CapArgT>>::init(
-                                    &str_interp,
-                                ))
-                            });
-                            *arg_count += 1;
-                        }
-                        _ => {
-                            set_from_thunk(destination, || None);
-                            set_from_thunk(state, || {
-                                FallbackValue(<DropInterp as ParserCommon<JsonAny>>::init(
-                                    &DropInterp,
-                                ))
-                            });
-                        }
-                    }
-                }
-                ValueSep if token == JsonToken::EndArray => return Ok(()),
-                FallbackValue(ref mut s) => {
-                    <DropInterp as JsonInterp<JsonAny>>::parse(&DropInterp, s, token, scratch)?;
-                    set_from_thunk(state, || FallbackValueSep);
-                }
-                FallbackValueSep if token == JsonToken::ValueSeparator => {
-                    set_from_thunk(state, || {
-                        FallbackValue(<DropInterp as ParserCommon<JsonAny>>::init(&DropInterp))
-                    });
-                }
-                FallbackValueSep if token == JsonToken::EndArray => {
-                    return Ok(());
-                }
-                _ => return Err(Some(OOB::Reject)),
-            }
-            break Err(None);
+
+// Chain ids are "0" to "19" on mainnet and testnet, written without leading zeros
+const CHAIN_COUNT: u8 = 20;
+
+fn parse_chain_id(s: &[u8]) -> Option<u8> {
+    match s {
+        [b'0'] | [b'1'..=b'9'] | [b'1'..=b'9', b'0'..=b'9'] => {
+            let chain = s.iter().fold(0, |n, d| n * 10 + (d - b'0'));
+            (chain < CHAIN_COUNT).then_some(chain)
         }
+        _ => None,
     }
 }
 
-// ----------------------------------------------------------------------------------
+// Chain ids in capability args are JSON strings, quotes included
+pub fn parse_chain_arg(arg: &[u8]) -> Option<u8> {
+    parse_chain_id(arg.strip_prefix(b"\"")?.strip_suffix(b"\"")?)
+}
 
-// tx_type
-// 0 -> Transfer
-// 1 -> Transfer create
-// 2 -> Transfer cross-chain
+/// What the review of the current command has seen so far, for the checks involving several
+/// fields; those can come in any order in the JSON.
+struct TxReview {
+    chain: Option<u8>,
+    // Bit n is set if there is a cross-chain transfer to chain n
+    xchain_targets: u32,
+    meta_seen: bool,
+    // The sender of the command, which pays for its gas; None if it could not be read
+    sender: Option<ArrayVec<u8, SENDER_SIZE>>,
+    // The gas limit and price of the meta; None if they could not be read
+    gas_limit: Option<Decimal>,
+    gas_price: Option<Decimal>,
+    // The first GAS_PAYER capability; others are compared to it
+    gas_station: Option<GasStation>,
+    // Set while the capabilities of a verifier are parsed, which are not added to the totals
+    in_verifier: bool,
+    // Digests of the transfers added to the totals, as several signers may have the same one
+    counted_transfers: ArrayVec<[u8; 32], MAX_COUNTED_TRANSFERS>,
+}
 
-#[allow(clippy::too_many_arguments)]
-#[inline(never)]
-fn handle_tx_param_1(
-    pkh_str: &ArrayString<64>,
-    hasher: &mut Blake2b,
-    tx_type: u8,
-    recipient: &ArrayVec<u8, PARAM_RECIPIENT_SIZE>,
-    recipient_chain: &ArrayVec<u8, PARAM_RECIPIENT_CHAIN_SIZE>,
-    amount: &ArrayVec<u8, PARAM_AMOUNT_SIZE>,
-    network: &ArrayVec<u8, PARAM_NETWORK_SIZE>,
-    namespace: &ArrayVec<u8, PARAM_NAMESPACE_SIZE>,
-    mod_name: &ArrayVec<u8, PARAM_MOD_NAME_SIZE>,
-) -> Option<()> {
-    let amount_str = from_utf8(amount).ok()?;
-    let recipient_str = from_utf8(recipient).ok()?;
-    let recipient_chain_str = from_utf8(recipient_chain).ok()?;
-    let network_str = from_utf8(network).ok()?;
-    let namespace_str = from_utf8(namespace).ok()?;
-    let mod_name_str = from_utf8(mod_name).ok()?;
-    if !namespace_str.is_empty() && mod_name_str.is_empty() {
-        return None;
+// Transfers beyond this are added up even if they were already, which can only raise the totals
+#[cfg(target_os = "nanos")]
+const MAX_COUNTED_TRANSFERS: usize = 4;
+#[cfg(not(target_os = "nanos"))]
+const MAX_COUNTED_TRANSFERS: usize = 16;
+
+impl TxReview {
+    const fn new() -> TxReview {
+        TxReview {
+            chain: None,
+            xchain_targets: 0,
+            meta_seen: false,
+            sender: None,
+            gas_limit: None,
+            gas_price: None,
+            gas_station: None,
+            in_verifier: false,
+            counted_transfers: ArrayVec::new_const(),
+        }
     }
+}
 
-    // recipient_str should be hex
-    if recipient_str.len() != 64 {
-        return None;
+static mut TX_REVIEW: TxReview = TxReview::new();
+
+fn reset_tx_review() {
+    unsafe {
+        TX_REVIEW = TxReview::new();
     }
-    for (_, c) in recipient_str.char_indices() {
-        if !matches!(c, '0'..='9' | 'A'..='F' | 'a'..='f') {
-            return None;
-        }
+}
+
+fn set_in_verifier(in_verifier: bool) {
+    unsafe { TX_REVIEW.in_verifier = in_verifier }
+}
+
+fn warn_same_chain(chain: u8) -> Option<()> {
+    scroller("WARNING", |w| {
+        Ok(write!(w, "Cross-chain transfer to chain {}, the chain it is sent from", chain)?)
+    })
+}
+
+fn review_source_chain(chain: u8) -> Option<()> {
+    let review = unsafe { &mut TX_REVIEW };
+    review.chain = Some(chain);
+    if review.xchain_targets & (1 << chain) != 0 {
+        warn_same_chain(chain)?;
     }
-    check_positive_integer(recipient_chain_str)?;
-    check_decimal(amount_str)?;
+    Some(())
+}
 
-    let coin_or_namespace = |hasher: &mut Blake2b| -> Option<()> {
-        if namespace_str.is_empty() {
-            write!(hasher, "coin").ok()?;
-        } else {
-            write!(hasher, "{}.{}", namespace_str, mod_name_str).ok()?;
+pub fn review_xchain_target(chain: u8) -> Option<()> {
+    let review = unsafe { &mut TX_REVIEW };
+    review.xchain_targets |= 1 << chain;
+    if review.chain == Some(chain) {
+        warn_same_chain(chain)?;
+    }
+    Some(())
+}
+
+// Long enough for "k:" accounts; longer senders are not shown
+const SENDER_SIZE: usize = 66;
+
+#[derive(PartialEq)]
+struct GasStation {
+    // The module of the capability, that of the gas station
+    module: ArrayVec<u8, 32>,
+    // Its user arg, which is the sender of the command; None if too long to keep
+    user: Option<ArrayVec<u8, SENDER_SIZE>>,
+    // Its limit and price args, which are those of the meta in the capability the station grants
+    limit: Option<Decimal>,
+    price: Option<Decimal>,
+}
+
+// The args of a GAS_PAYER capability, like "free.gas-station for user free-gas, at most 600 at
+// price 0.00001", for the capabilities which cannot be checked against the meta.
+fn write_gas_station(
+    w: &mut PromptWrite<'_, 16>,
+    station: &GasStation,
+) -> Result<(), ScrollerError> {
+    write!(w, "{} for user ", sanitized(&station.module)?)?;
+    match &station.user {
+        Some(user) => write!(w, "{}", sanitized(user)?)?,
+        None => write!(w, "too long to display")?,
+    }
+    match (station.limit, station.price) {
+        (Some(limit), Some(price)) => Ok(write!(w, ", at most {} at price {}", limit, price)?),
+        _ => Ok(write!(w, ", with a limit or price which cannot be shown")?),
+    }
+}
+
+// A gas station pays for the gas of the commands with its GAS_PAYER capability, as their sender;
+// this is shown once both the capability and the meta are parsed.
+fn show_gas_station(review: &TxReview) -> Option<()> {
+    let station = review.gas_station.as_ref()?;
+    let sender = review.sender.as_deref();
+    note_station_sender(sender);
+    match sender {
+        Some(sender) => scroller("Paying Gas", |w| {
+            Ok(write!(
+                w,
+                "Gas paid by station {} of {}",
+                sanitized(sender)?,
+                sanitized(&station.module)?
+            )?)
+        }),
+        None => scroller("WARNING", |w| {
+            Ok(write!(
+                w,
+                "Gas paid by a station of {}, but its account, the sender, cannot be shown",
+                sanitized(&station.module)?
+            )?)
+        }),
+    }?;
+    if let Some(sender) = sender {
+        if station.user.as_deref() != Some(sender) {
+            scroller("WARNING", |w| {
+                write!(w, "User of the GAS_PAYER capability is not the sender: ")?;
+                write_gas_station(w, station)
+            })?;
         }
-        Some(())
-    };
+    }
+    let gas_matches = station.limit.is_some()
+        && station.limit == review.gas_limit
+        && station.price.is_some()
+        && station.price == review.gas_price;
+    if gas_matches {
+        return Some(());
+    }
+    scroller("WARNING", |w| {
+        Ok(write!(
+            w,
+            "Gas limit or price of the GAS_PAYER capability is not that of the command"
+        )?)
+    })
+}
 
-    // curly braces are escaped like '{{', '}}'
-    // The JSON struct begins here, and ends in handle_tx_params_2
-    write!(hasher, "{{").ok()?;
-    write!(hasher, "\"networkId\":\"{}\"", network_str).ok()?;
-    match tx_type {
-        0 => {
-            write!(
-                hasher,
-                ",\"payload\":{{\"exec\":{{\"data\":{{}},\"code\":\"("
-            )
-            .ok()?;
-            coin_or_namespace(hasher)?;
-            write!(hasher, ".transfer").ok()?;
-            write!(hasher, " \\\"k:{}\\\"", pkh_str).ok()?;
-            write!(hasher, " \\\"k:{}\\\"", recipient_str).ok()?;
-            write!(hasher, " {})\"}}}}", amount_str).ok()?;
-            write!(hasher, ",\"signers\":[{{\"pubKey\":").ok()?;
-            write!(hasher, "\"{}\"", pkh_str).ok()?;
-            write!(hasher, ",\"clist\":[{{\"args\":[").ok()?;
-            write!(hasher, "\"k:{}\",", pkh_str).ok()?;
-            write!(hasher, "\"k:{}\",", recipient_str).ok()?;
-            write!(hasher, "{}]", amount_str).ok()?;
-            write!(hasher, ",\"name\":\"").ok()?;
-            coin_or_namespace(hasher)?;
-            write!(
-                hasher,
-                ".TRANSFER\"}},{{\"args\":[],\"name\":\"coin.GAS\"}}]}}]"
-            )
-            .ok()?;
+/// Notes the GAS_PAYER capability of a gas station, with its user, limit and price args. The
+/// same capability may be granted by several signers; any other one is shown on its own.
+pub fn review_gas_station(
+    cap_name: &[u8],
+    user: Option<&[u8]>,
+    limit: Option<Decimal>,
+    price: Option<Decimal>,
+) -> Option<()> {
+    let review = unsafe { &mut TX_REVIEW };
+    let module = match cap_name.iter().rposition(|b| *b == b'.') {
+        Some(i) => &cap_name[..i],
+        None => cap_name,
+    };
+    let station = GasStation {
+        module: ArrayVec::try_from(module).ok()?,
+        user: user.and_then(|u| ArrayVec::try_from(u).ok()),
+        limit,
+        price,
+    };
+    match &review.gas_station {
+        Some(first) if *first == station => return Some(()),
+        Some(_) => {
+            return scroller("WARNING", |w| {
+                write!(w, "Another GAS_PAYER capability, not that of the first: ")?;
+                write_gas_station(w, &station)
+            })
         }
-        1 => {
-            write!(hasher, ",\"payload\":{{\"exec\":{{\"data\":{{").ok()?;
-            write!(hasher, "\"ks\":{{\"pred\":\"keys-all\",\"keys\":[").ok()?;
-            write!(hasher, "\"{}\"]}}}}", recipient_str).ok()?;
-            write!(hasher, ",\"code\":\"(").ok()?;
-            coin_or_namespace(hasher)?;
-            write!(hasher, ".transfer-create").ok()?;
-            write!(hasher, " \\\"k:{}\\\"", pkh_str).ok()?;
-            write!(hasher, " \\\"k:{}\\\"", recipient_str).ok()?;
+        None => {}
+    }
+    review.gas_station = Some(station);
+    if review.meta_seen {
+        show_gas_station(review)?;
+    }
+    Some(())
+}
+
+fn review_sender(sender: Option<&[u8]>, meta: &MetaValues) -> Option<()> {
+    let review = unsafe { &mut TX_REVIEW };
+    review.meta_seen = true;
+    review.sender = sender.and_then(|s| ArrayVec::try_from(s).ok());
+    review.gas_limit = meta.gas_limit.and_then(Decimal::parse);
+    review.gas_price = meta.gas_price.and_then(Decimal::parse);
+    if review.gas_station.is_some() {
+        show_gas_station(review)?;
+    }
+    Some(())
+}
+
+// Without the meta, the GAS_PAYER capability cannot be shown with the sender it pays as
+fn check_gas_station_shown() -> Option<()> {
+    let review = unsafe { &TX_REVIEW };
+    match &review.gas_station {
+        Some(station) if !review.meta_seen => scroller("WARNING", |w| {
+            write!(w, "Gas paid by a station, but the meta cannot be shown: ")?;
+            write_gas_station(w, station)
+        }),
+        _ => Some(()),
+    }
+}
+
+// The senders of the commands of the request which use a gas station, by a hash like that of
+// public keys; a command can claim a gas station while the signer's own account pays for its gas.
+static mut STATION_SENDERS: ArrayVec<u32, MAX_BATCH_SIZE> = ArrayVec::new_const();
+
+fn reset_station_senders() {
+    unsafe { STATION_SENDERS.clear() }
+}
+
+fn note_station_sender(sender: Option<&[u8]>) {
+    if let Some(hash) = sender.and_then(|s| pub_key_hash(sanitized(s).ok()?)) {
+        let _ = unsafe { STATION_SENDERS.try_push(hash) };
+    }
+}
+
+fn check_station_sender(pkh: &PKH) -> Option<()> {
+    let own_account = pub_key_hash(format_args!("k:{}", pkh))?;
+    if !unsafe { STATION_SENDERS.contains(&own_account) } {
+        return Some(());
+    }
+    scroller("WARNING", |w| {
+        Ok(write!(w, "Gas is paid by your own account, not by the gas station")?)
+    })
+}
+
+// The transfers of the request, added up by token; like the unscoped signers these cover all
+// the commands of a batch.
+const MAX_TOKENS: usize = 2;
+const MAX_RECIPIENTS: usize = 8;
+
+struct TokenTotal {
+    // The module of the TRANSFER capability, like "coin"
+    module: ArrayVec<u8, 32>,
+    // None once an amount could not be read, or the sum overflowed
+    amount: Option<Decimal>,
+    // Hashes of the distinct receiver accounts
+    recipients: ArrayVec<u32, MAX_RECIPIENTS>,
+    more_recipients: bool,
+    // Set if the receiver of a transfer could not be read
+    unknown_recipients: bool,
+}
+
+struct TransferTotals {
+    tokens: ArrayVec<TokenTotal, MAX_TOKENS>,
+    more_tokens: bool,
+    // Set if the capabilities of a command could not be read, so that any of them could be
+    // transfers
+    unknown_transfers: bool,
+}
+
+static mut TRANSFER_TOTALS: TransferTotals = TransferTotals {
+    tokens: ArrayVec::new_const(),
+    more_tokens: false,
+    unknown_transfers: false,
+};
+
+fn reset_transfer_totals() {
+    unsafe {
+        TRANSFER_TOTALS.tokens.clear();
+        TRANSFER_TOTALS.more_tokens = false;
+        TRANSFER_TOTALS.unknown_transfers = false;
+    }
+}
+
+pub fn parse_amount(arg: &[u8]) -> Option<Decimal> {
+    Decimal::parse(pact::amount(from_utf8(arg).ok()?)?.as_bytes())
+}
+
+// A transfer is identified by its name and args, each after its length
+fn transfer_digest(cap: &Capability) -> [u8; 32] {
+    let mut hasher: Blake2b = Hasher::new();
+    for part in core::iter::once(Some(cap.name)).chain((0..cap.arity()).map(|i| cap.arg(i))) {
+        let part = part.unwrap_or_default();
+        hasher.update(&(part.len() as u32).to_le_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().0
+}
+
+// The total of the token of a TRANSFER capability; None if it cannot be added up, which is then
+// noted in the totals.
+fn token_total(cap_name: &[u8]) -> Option<&'static mut TokenTotal> {
+    let module = match cap_name.iter().rposition(|b| *b == b'.') {
+        Some(i) => &cap_name[..i],
+        None => cap_name,
+    };
+    let totals = unsafe { &mut TRANSFER_TOTALS };
+    let i = match totals
+        .tokens
+        .iter()
+        .position(|t| t.module.as_slice() == module)
+    {
+        Some(i) => i,
+        None => {
+            let mut name = ArrayVec::new();
+            if name.try_extend_from_slice(module).is_err() || totals.tokens.is_full() {
+                totals.more_tokens = true;
+                return None;
+            }
+            totals.tokens.push(TokenTotal {
+                module: name,
+                amount: Some(Decimal::default()),
+                recipients: ArrayVec::new(),
+                more_recipients: false,
+                unknown_recipients: false,
+            });
+            totals.tokens.len() - 1
+        }
+    };
+    totals.tokens.get_mut(i)
+}
+
+/// Adds a transfer shown by a renderer to the totals, unless it is that of a verifier or was
+/// already added for another signer of the command. Its receiver and amount are the args at these
+/// indices, like the second and third ones of `coin.TRANSFER`.
+pub fn add_transfer(cap: &Capability, receiver: usize, amount: usize) -> Option<()> {
+    let review = unsafe { &mut TX_REVIEW };
+    if review.in_verifier {
+        return Some(());
+    }
+    let digest = transfer_digest(cap);
+    if review.counted_transfers.contains(&digest) {
+        return Some(());
+    }
+    let _ = review.counted_transfers.try_push(digest);
+    let (recipient, amount) = (cap.arg(receiver)?, cap.arg(amount)?);
+    let token = match token_total(cap.name) {
+        Some(token) => token,
+        None => return Some(()),
+    };
+    token.amount = token
+        .amount
+        .and_then(|total| total.checked_add(parse_amount(amount)?));
+    let mut hash = FNV_OFFSET;
+    for b in recipient {
+        hash = (hash ^ *b as u32).wrapping_mul(FNV_PRIME);
+    }
+    if !token.recipients.contains(&hash) && token.recipients.try_push(hash).is_err() {
+        token.more_recipients = true;
+    }
+    Some(())
+}
+
+/// Adds a transfer which is not shown as one, like a `coin.TRANSFER` whose args were too long or
+/// not those expected, to the totals as an unknown amount to unknown recipients. Its receiver
+/// cannot be checked either, see `address_book::check_unknown_recipient`.
+pub fn add_unknown_transfer(cap_name: &[u8]) -> Option<()> {
+    address_book::check_unknown_recipient()?;
+    if unsafe { TX_REVIEW.in_verifier } {
+        return Some(());
+    }
+    if let Some(token) = token_total(cap_name) {
+        token.amount = None;
+        token.unknown_recipients = true;
+    }
+    Some(())
+}
+
+// Shown before the final approval, if there are any transfers
+fn show_transfer_totals() -> Option<()> {
+    let totals = unsafe { &TRANSFER_TOTALS };
+    for token in totals.tokens.iter() {
+        scroller("Total", |w| {
+            match token.amount {
+                Some(amount) => write!(w, "{} ", amount)?,
+                None => write!(w, "Unknown amount of ")?,
+            }
+            match token.module.as_slice() {
+                b"coin" => write!(w, "KDA")?,
+                module => write!(w, "{}", sanitized(module)?)?,
+            }
+            Ok(match token.recipients.len() {
+                _ if token.more_recipients => {
+                    write!(w, " to more than {} recipients", MAX_RECIPIENTS)?
+                }
+                _ if token.unknown_recipients => write!(w, " to unknown recipients")?,
+                1 => write!(w, " to 1 recipient")?,
+                n => write!(w, " to {} recipients", n)?,
+            })
+        })?;
+    }
+    if totals.more_tokens {
+        scroller("WARNING", |w| {
+            Ok(write!(w, "Transfers of more than {} tokens, not all added up", MAX_TOKENS)?)
+        })?;
+    }
+    // KDA left out of the totals could be any amount
+    match totals.tokens.iter().find(|t| t.module.as_slice() == b"coin") {
+        _ if totals.unknown_transfers => check_spending_limit(None),
+        Some(kda) => check_spending_limit(kda.amount),
+        None if totals.more_tokens => check_spending_limit(None),
+        None => Some(()),
+    }
+}
+
+// Transfers of more KDA than the spending limit, or of an amount which cannot be added up, need
+// another confirmation; with Refuse Over Limit they are refused instead.
+fn check_spending_limit(total: Option<Decimal>) -> Option<()> {
+    let settings = Settings::new();
+    let limit = match settings.spending_limit() {
+        Some(limit) => limit,
+        None => return Some(()),
+    };
+    if total.map_or(false, |total| total <= limit) {
+        return Some(());
+    }
+    if settings.is_enabled(SettingFlag::RefuseOverLimit) {
+        // The reason is shown even if the user rejects this screen
+        let _ = scroller("Refused", |w| {
+            Ok(write!(
+                w,
+                "Transfers above the spending limit of {limit} KDA"
+            )?)
+        });
+        return reject_with(KadenaStatusWord::OverSpendingLimit);
+    }
+    scroller("Over Limit", |w| match total {
+        Some(total) => Ok(write!(
+            w,
+            "{total} KDA, above the spending limit of {limit}"
+        )?),
+        None => Ok(write!(
+            w,
+            "Unknown KDA amount, the spending limit is {limit}"
+        )?),
+    })?;
+    final_accept_prompt(&[]).or_else(user_rejected)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CapCountData {
+    IsTransfer,
+    IsUnknownCap,
+    CapCount {
+        total_caps: u16,
+        total_transfers: u16,
+        total_unknown: u16,
+    },
+}
+
+impl Summable<CapCountData> for CapCountData {
+    fn add_and_set(&mut self, other: &CapCountData) {
+        if let CapCountData::CapCount {
+            total_caps,
+            total_transfers,
+            total_unknown,
+        } = self
+        {
+            *total_caps += 1;
+            match other {
+                CapCountData::IsTransfer => *total_transfers += 1,
+                CapCountData::IsUnknownCap => *total_unknown += 1,
+                _ => {}
+            }
+        }
+    }
+    fn zero() -> Self {
+        CapCountData::CapCount {
+            total_caps: 0,
+            total_transfers: 0,
+            total_unknown: 0,
+        }
+    }
+}
+
+// Shown after an account name with characters which could make it pass for another account
+pub fn warn_lookalikes(accounts: &[Option<&[u8]>]) -> Option<()> {
+    let suspicious = accounts
+        .iter()
+        .any(|a| a.and_then(|a| from_utf8(a).ok()).map_or(false, has_lookalikes));
+    if !suspicious {
+        return Some(());
+    }
+    scroller("WARNING", |w| {
+        Ok(write!(w, "Account name has non-ASCII characters, which can look like other ones")?)
+    })
+}
+
+// In expert mode a recognized capability is also shown as it appears in the transaction
+fn show_raw_capability(name: Sanitized, cap: &Capability) -> Option<()> {
+    if !expert_mode() {
+        return Some(());
+    }
+    scroller("Raw Capability", |w| {
+        write!(w, "{{\"args\":[")?;
+        for i in 0..cap.arity() {
+            if i != 0 {
+                write!(w, ",")?;
+            }
+            write!(w, "{}", mkstr(cap.arg(i))?)?;
+        }
+        Ok(write!(w, "],\"name\":\"{}\"}}", name)?)
+    })
+}
+
+// Capabilities without a renderer, see `capabilities`, are shown with all their args
+fn show_unknown_capability(title: &str, name: Sanitized, cap: &Capability) -> Option<()> {
+    scroller(title, |w| {
+        write!(w, "name: {}", name)?;
+        if cap.arity() == 0 {
+            return Ok(write!(w, ", no args")?);
+        }
+        for i in 0..cap.arity() {
+            write!(w, ", arg {}: {}", i + 1, mkarg(cap.arg(i))?)?;
+        }
+        Ok(())
+    })
+}
+
+const CLIST_ACTION: SubInterpMFold<
+    Action<
+        UniqueKeys<KadenaCapabilityInterp<KadenaCapabilityArgsInterp, JsonStringAccumulate<32>>>,
+        fn(
+            &KadenaCapability<
+                Option<<KadenaCapabilityArgsInterp as ParserCommon<JsonArray<JsonAny>>>::Returning>,
+                Option<ArrayVec<u8, 32>>,
+            >,
+            &mut Option<(CapCountData, bool)>,
+            (CapCountData, All),
+        ) -> Option<()>,
+    >,
+    (CapCountData, All),
+> = SubInterpMFold::new(Action(
+    UniqueKeys(KadenaCapabilityInterp {
+        field_args: KadenaCapabilityArgsInterp,
+        field_name: JsonStringAccumulate::<32>,
+    }),
+    mkfnc(
+        |cap: &KadenaCapability<
+            Option<<KadenaCapabilityArgsInterp as ParserCommon<JsonArray<JsonAny>>>::Returning>,
+            Option<ArrayVec<u8, 32>>,
+        >,
+         destination: &mut Option<(CapCountData, bool)>,
+         v: (CapCountData, All)| {
+            let name = cap.field_name.as_ref()?.as_slice();
+            let name_utf8 = Sanitized(from_utf8(name).ok()?);
+            let mk_unknown_cap_title = || -> Option<_> {
+                let count = match v.0 {
+                    CapCountData::CapCount { total_unknown, .. } => total_unknown,
+                    _ => 0,
+                };
+                let mut buffer: ArrayString<22> = ArrayString::new();
+                write!(
+                    mk_prompt_write(&mut buffer),
+                    "Unknown Capability {}",
+                    count + 1
+                )
+                .ok()?;
+                Some(buffer)
+            };
+
+            trace!("Prompting for capability");
+            *destination = Some((CapCountData::IsUnknownCap, true));
+            match cap.field_args.as_ref() {
+                Some(CapArgs::Shown(count)) => {
+                    scroller(&mk_unknown_cap_title()?, |w| match count {
+                        1 => Ok(write!(w, "name: {}, with the arg above", name_utf8)?),
+                        n => Ok(write!(w, "name: {}, with the {} args above", name_utf8, n)?),
+                    })?;
+                    // The app does not know what these args mean, but has a prompt of its own for
+                    // this capability; without it, the transaction cannot be fully reviewed.
+                    if capabilities::is_transfer(name) {
+                        add_unknown_transfer(name)?;
+                    }
+                    if capabilities::is_transfer(name) || capabilities::is_builtin(name) {
+                        *destination = Some((CapCountData::IsUnknownCap, false));
+                    }
+                }
+                Some(CapArgs::Buffered(args, arg_lengths)) => {
+                    let transfers_before = match v.0 {
+                        CapCountData::CapCount {
+                            total_transfers, ..
+                        } => total_transfers,
+                        _ => 0,
+                    };
+                    let capability = Capability::new(name, args, arg_lengths, transfers_before);
+                    let recognized = match capabilities::renderer(name, capability.arity()) {
+                        Some(renderer) => renderer.render(&capability)?,
+                        None => Recognized::No,
+                    };
+                    // Only the renderers of transfers add them up, like those of descriptors
+                    // which give the receiver
+                    let unknown_transfer = !matches!(recognized, Recognized::Transfer)
+                        && capabilities::is_transfer(name);
+                    match recognized {
+                        Recognized::No => {
+                            let title = mk_unknown_cap_title()?;
+                            show_unknown_capability(&title, name_utf8, &capability)?;
+                        }
+                        Recognized::Transfer => {
+                            show_raw_capability(name_utf8, &capability)?;
+                            *destination = Some((CapCountData::IsTransfer, true));
+                        }
+                        Recognized::Shown => {
+                            show_raw_capability(name_utf8, &capability)?;
+                            *destination = Some((Summable::zero(), true));
+                        }
+                    }
+                    if unknown_transfer {
+                        add_unknown_transfer(name)?;
+                    }
+                }
+                _ => {
+                    scroller(&mk_unknown_cap_title()?, |w| {
+                        Ok(write!(
+                            w,
+                            "name: {}, args cannot be displayed on Ledger",
+                            name_utf8
+                        )?)
+                    })?;
+                    set_from_thunk(destination, || Some((CapCountData::IsUnknownCap, false)));
+                    if capabilities::is_transfer(name) {
+                        add_unknown_transfer(name)?;
+                    }
+                    // Fallback case
+                }
+            }
+            Some(())
+        },
+    ),
+));
+
+pub type SignHashImplT = impl InterpParser<SignHashParameters, Returning = ArrayVec<u8, 128_usize>>;
+
+pub static SIGN_HASH_IMPL: SignHashImplT = Action(
+    Preaction(
+        || -> Option<()> {
+            scroller("WARNING", |w| {
+                Ok(write!(w, "Blind Signing a Transaction Hash is a very unusual operation. Do not continue unless you know what you are doing")?)
+            })
+        },
+        (
+            Action(
+                SubInterp(DefaultInterp),
+                // Ask the user if they accept the transaction body's hash
+                mkfn(|hash_val: &[u8; 32], destination: &mut Option<[u8; 32]>| {
+                    let the_hash = Hash(*hash_val);
+                    scroller("Transaction hash", |w| Ok(write!(w, "{}", the_hash)?))?;
+                    *destination = Some(the_hash.0);
+                    Some(())
+                }),
+            ),
+            MoveAction(
+                SubInterp(DefaultInterp),
+                // And ask the user if this is the key the meant to sign with:
+                mkmvfn(
+                    |path: ArrayVec<u32, 10>, destination: &mut Option<ArrayVec<u32, 10>>| {
+                        with_public_keys(&path, |_, pkh: &PKH| {
+                            try_option(|| -> Option<()> {
+                                scroller("Sign for Address", |w| Ok(write!(w, "{}", pkh)?))?;
+                                Some(())
+                            }())
+                        })
+                        .ok()?;
+                        *destination = Some(path);
+                        Some(())
+                    },
+                ),
+            ),
+        ),
+    ),
+    mkfn(
+        |(hash, path): &(Option<[u8; 32]>, Option<ArrayVec<u32, 10>>), destination: &mut _| {
+            #[allow(clippy::needless_borrow)] // Needed for nanos
+            final_accept_prompt(&[&"Sign Transaction Hash?"]).or_else(user_rejected)?;
+
+            // By the time we get here, we've approved and just need to do the signature.
+            let sig = eddsa_sign(path.as_ref()?, &hash.as_ref()?[..]).ok()?;
+            Settings::new().blind_signature_made();
+            let mut rv = ArrayVec::<u8, 128>::new();
+            rv.try_extend_from_slice(&sig.0[..]).ok()?;
+            *destination = Some(rv);
+            Some(())
+        },
+    ),
+);
+
+// Messages are signed in a digest no transaction can have: the hash of a transaction is that
+// of its JSON, which starts with "{". See SIGN_MESSAGE in docs/apdu.md.
+const MESSAGE_PREFIX: &[u8] = b"\x19Kadena Signed Message:\n";
+
+fn message_digest(message: &[u8]) -> Option<Zeroizing<Hash<32>>> {
+    let mut hasher: Blake2b = Hasher::new();
+    hasher.update(MESSAGE_PREFIX);
+    write!(hasher, "{}\n", message.len()).ok()?;
+    hasher.update(message);
+    Some(hasher.finalize())
+}
+
+pub type SignMessageImplT =
+    impl InterpParser<SignMessageParameters, Returning = ArrayVec<u8, 128_usize>>;
+
+pub static SIGN_MESSAGE_IMPL: SignMessageImplT = Action(
+    (
+        Action(
+            SubInterp(DefaultInterp),
+            // Show the whole message; characters outside of ASCII are shown escaped
+            mkfn(
+                |message: &ArrayVec<u8, MAX_MESSAGE_SIZE>,
+                 destination: &mut Option<Zeroizing<Hash<32>>>| {
+                    let text = from_utf8(message).ok()?;
+                    if text.is_empty() {
+                        return None;
+                    }
+                    scroller("Signing", |w| Ok(write!(w, "Message")?))?;
+                    scroller_paginated("Message", |w| Ok(write!(w, "{}", Sanitized(text))?))?;
+                    *destination = Some(message_digest(message)?);
+                    Some(())
+                },
+            ),
+        ),
+        MoveAction(
+            SubInterp(DefaultInterp),
+            mkmvfn(
+                |path: ArrayVec<u32, 10>, destination: &mut Option<ArrayVec<u32, 10>>| {
+                    with_public_keys(&path, |_, pkh: &PKH| {
+                        try_option(|| -> Option<()> {
+                            scroller("Sign for Address", |w| Ok(write!(w, "{}", pkh)?))?;
+                            Some(())
+                        }())
+                    })
+                    .ok()?;
+                    *destination = Some(path);
+                    Some(())
+                },
+            ),
+        ),
+    ),
+    mkfn(
+        |(digest, path): &(Option<Zeroizing<Hash<32>>>, Option<ArrayVec<u32, 10>>),
+         destination: &mut _| {
+            final_accept_prompt(&[]).or_else(user_rejected)?;
+
+            let sig = eddsa_sign(path.as_ref()?, &digest.as_ref()?.0[..]).ok()?;
+            let mut rv = ArrayVec::<u8, 128>::new();
+            rv.try_extend_from_slice(&sig.0[..]).ok()?;
+            *destination = Some(rv);
+            Some(())
+        },
+    ),
+);
+
+pub type SignHashWithTxImplT =
+    impl InterpParser<SignParameters, Returning = ArrayVec<u8, 128_usize>>;
+
+// Like SIGN_IMPL, but the parts of the transaction which can not be parsed are dropped and
+// counted instead of rejecting the whole transaction.
+pub static SIGN_HASH_WITH_TX_IMPL: SignHashWithTxImplT = Action(
+    (
+        Action(
+            ObserveLengthedBytes(
+                Hasher::new,
+                Hasher::update,
+                Json(Action(
+                    Preaction(
+                        || -> Option<()> {
+                            reset_dropped_fields();
+                            reset_tx_review();
+                            reset_unscoped_signers();
+                            reset_transfer_totals();
+                            reset_station_senders();
+                            scroller("Signing", |w| Ok(write!(w, "Transaction Hash")?))
+                        },
+                        Lenient(UniqueKeys(KadenaCmdInterp {
+                            field_nonce: Lenient(NONCE_ACTION),
+                            field_meta: Lenient(META_ACTION),
+                            field_payload: Lenient(PAYLOAD_ACTION),
+                            field_signers: Lenient(SIGNERS_ACTION),
+                            field_network_id: Lenient(NETWORK_ID_ACTION),
+                            field_verifiers: Lenient(VERIFIERS_ACTION),
+                        })),
+                    ),
+                    mkfn(
+                        |cmd: &Option<
+                            KadenaCmd<
+                                _,
+                                _,
+                                Option<Option<CapabilityCoverage>>,
+                                _,
+                                Option<Option<bool>>,
+                                Option<Option<All>>,
+                            >,
+                        >,
+                         dest: &mut Option<bool>| {
+                            match cmd {
+                                Some(cmd) => {
+                                    if cmd.field_network_id.is_none() {
+                                        show_network(None)?;
+                                    }
+                                    if !matches!(cmd.field_signers, Some(Some(_))) {
+                                        note_unknown_signers()?;
+                                    }
+                                    check_gas_station_shown()?;
+                                    show_coverage_warning(command_coverage(
+                                        cmd.field_signers.as_ref().and_then(Option::as_ref),
+                                        cmd.field_verifiers.as_ref().and_then(Option::as_ref),
+                                    ))?;
+                                    match dropped_fields() {
+                                        0 => {}
+                                        1 => scroller("WARNING", |w| {
+                                            Ok(write!(w, "1 field not shown")?)
+                                        })?,
+                                        n => scroller("WARNING", |w| {
+                                            Ok(write!(w, "{} fields not shown", n)?)
+                                        })?,
+                                    }
+                                    *dest = Some(true);
+                                }
+                                // Nothing of the transaction could be shown; this is plain
+                                // blind signing.
+                                None => {
+                                    if !Settings::new().blind_signing_allowed() {
+                                        return reject_with(KadenaStatusWord::BlindSigningRequired);
+                                    }
+                                    scroller("WARNING", |w| {
+                                        Ok(write!(w, "Transaction not recognized. Do not continue unless you know what you are doing")?)
+                                    })?;
+                                    *dest = Some(false);
+                                }
+                            }
+                            Some(())
+                        },
+                    ),
+                )),
+                true,
+            ),
+            mkfn(
+                |(recognized, mut hasher): &(Option<bool>, Blake2b),
+                 destination: &mut Option<(Zeroizing<Hash<32>>, bool)>| {
+                    // A prompt declined while parsing must not be mistaken for an unparsed
+                    // transaction.
+                    if reject_reason_pending() {
+                        return None;
+                    }
+                    let blind = !(*recognized)?;
+                    let the_hash = hasher.finalize();
+                    scroller("Transaction hash", |w| Ok(write!(w, "{}", the_hash.deref())?))?;
+                    *destination = Some((the_hash, blind));
+                    Some(())
+                },
+            ),
+        ),
+        SIGN_PATH_ACTION,
+    ),
+    mkfn(
+        |(hash, path): &(Option<(Zeroizing<Hash<32>>, bool)>, Option<ArrayVec<u32, 10>>),
+         destination: &mut _| {
+            show_transfer_totals()?;
+            #[allow(clippy::needless_borrow)] // Needed for nanos
+            final_accept_prompt(&[&"Sign Transaction Hash?"]).or_else(user_rejected)?;
+
+            let (hash, blind) = hash.as_ref()?;
+            let sig = eddsa_sign(path.as_ref()?, &hash.0[..]).ok()?;
+            if *blind {
+                Settings::new().blind_signature_made();
+            }
+            let mut rv = ArrayVec::<u8, 128>::new();
+            rv.try_extend_from_slice(&sig.0[..]).ok()?;
+            *destination = Some(rv);
+            Some(())
+        },
+    ),
+);
+
+pub struct KadenaCapabilityArgsInterp;
+
+// The args of a capability are rendered as JSON into a single ArrayVec of this size, for the
+// capabilities which are recognized and shown with their own prompts.
+// (This may be as large as the stack allows)
+#[cfg(target_os = "nanos")]
+const ARG_ARRAY_SIZE: usize = 184;
+#[cfg(not(target_os = "nanos"))]
+const ARG_ARRAY_SIZE: usize = 2048;
+const MAX_ARG_COUNT: usize = 5;
+
+// Args which do not fit, or beyond MAX_ARG_COUNT, are shown as they are parsed instead, in pages
+// of this size; the ArrayVec then only holds the current page. This is the same on all devices.
+const ARG_PAGE_SIZE: usize = 128;
+
+// Since we use a single ArrayVec to store the rendered json of all the args.
+// This list keeps track of the indices in the array for each arg, and even the args count
+
+// If there are three args; then indices[0] will contain the end of first arg, indices[1] will be end of second, and indices[2] will be 0
+// In other words, first arg will be: array[0..indices[0]], second: array[indices[0]..indices[1]], third: array[indices[1]..array.len()]
+type ArgListIndicesT = [usize; MAX_ARG_COUNT - 1];
+
+pub enum CapArgs {
+    /// The rendered args, see `ArgListIndicesT`; empty if there are none.
+    Buffered(ArrayVec<u8, ARG_ARRAY_SIZE>, ArgListIndicesT),
+    /// The number of args, which did not fit and have already been shown one by one.
+    Shown(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KadenaCapabilityArgsPhase {
+    Start,
+    BeforeArg,
+    InArg,
+    AfterArg,
+}
+
+#[derive(Debug)]
+pub struct KadenaCapabilityArgsInterpState {
+    phase: KadenaCapabilityArgsPhase,
+    // Nesting of the current arg in arrays and objects
+    depth: usize,
+    // The args started so far
+    count: usize,
+    // Where the current arg starts in the ArrayVec, until streaming
+    arg_start: usize,
+    streaming: bool,
+    // The page of the current arg being filled, once streaming
+    part: usize,
+}
+
+impl ParserCommon<JsonArray<JsonAny>> for KadenaCapabilityArgsInterp {
+    type State = KadenaCapabilityArgsInterpState;
+    type Returning = CapArgs;
+    fn init(&self) -> Self::State {
+        KadenaCapabilityArgsInterpState {
+            phase: KadenaCapabilityArgsPhase::Start,
+            depth: 0,
+            count: 0,
+            arg_start: 0,
+            streaming: false,
+            part: 1,
+        }
+    }
+}
+
+// The end of the first page of some rendered JSON, without splitting a character
+fn arg_page_end(text: &[u8]) -> usize {
+    let mut end = core::cmp::min(ARG_PAGE_SIZE, text.len());
+    while end < text.len() && text[end] & 0xC0 == 0x80 {
+        end -= 1;
+    }
+    end
+}
+
+// Parts are only numbered if the arg does not fit in one page
+fn show_arg_page(arg: usize, part: usize, last: bool, page: &[u8]) -> Option<()> {
+    let mut title: ArrayString<22> = ArrayString::new();
+    if part == 1 && last {
+        write!(mk_prompt_write(&mut title), "Arg {}", arg).ok()?;
+    } else {
+        write!(mk_prompt_write(&mut title), "Arg {}, part {}", arg, part).ok()?;
+    }
+    scroller(&title, |w| Ok(write!(w, "{}", sanitized(page)?)?))
+}
+
+// Shows the whole pages of a rendered arg, and returns the length of the rest
+fn show_arg_pages(arg: usize, part: &mut usize, mut text: &[u8], complete: bool) -> Option<usize> {
+    while text.len() > ARG_PAGE_SIZE || (complete && !text.is_empty()) {
+        let end = arg_page_end(text);
+        show_arg_page(arg, *part, complete && end == text.len(), &text[..end])?;
+        *part += 1;
+        text = &text[end..];
+    }
+    Some(text.len())
+}
+
+impl KadenaCapabilityArgsInterpState {
+    // Shows the args rendered so far, and keeps the rest of the current arg as its first page
+    fn start_streaming(
+        &mut self,
+        args: &mut ArrayVec<u8, ARG_ARRAY_SIZE>,
+        ends: &ArgListIndicesT,
+    ) -> Option<()> {
+        let completed = self.count - 1;
+        let mut start = 0;
+        for i in 0..completed {
+            let end = if i + 1 == completed { self.arg_start } else { ends[i] };
+            show_arg_pages(i + 1, &mut 1, args.get(start..end)?, true)?;
+            start = end;
+        }
+        self.part = 1;
+        let rest = show_arg_pages(self.count, &mut self.part, args.get(self.arg_start..)?, false)?;
+        args.drain(..args.len() - rest);
+        self.streaming = true;
+        Some(())
+    }
+
+    fn write(&mut self, destination: &mut Option<CapArgs>, mut text: &str) -> Option<()> {
+        let (args, ends) = match destination {
+            Some(CapArgs::Buffered(args, ends)) => (args, ends),
+            _ => return None,
+        };
+        while !text.is_empty() {
+            let limit = if self.streaming { ARG_PAGE_SIZE } else { ARG_ARRAY_SIZE };
+            let mut n = core::cmp::min(limit - args.len(), text.len());
+            while !text.is_char_boundary(n) {
+                n -= 1;
+            }
+            if n == 0 {
+                if self.streaming {
+                    show_arg_page(self.count, self.part, false, args)?;
+                    self.part += 1;
+                    args.clear();
+                } else {
+                    self.start_streaming(args, ends)?;
+                }
+                continue;
+            }
+            args.try_extend_from_slice(text[..n].as_bytes()).ok()?;
+            text = &text[n..];
+        }
+        Some(())
+    }
+
+    fn write_token(&mut self, destination: &mut Option<CapArgs>, token: &JsonToken) -> Option<()> {
+        match token {
+            JsonToken::String(s) => {
+                self.write(destination, "\"")?;
+                self.write(destination, s)?;
+                self.write(destination, "\"")
+            }
+            JsonToken::Number(n) => self.write(destination, n),
+            JsonToken::True => self.write(destination, "true"),
+            JsonToken::False => self.write(destination, "false"),
+            JsonToken::Null => self.write(destination, "null"),
+            JsonToken::BeginArray => self.write(destination, "["),
+            JsonToken::EndArray => self.write(destination, "]"),
+            JsonToken::BeginObject => self.write(destination, "{"),
+            JsonToken::EndObject => self.write(destination, "}"),
+            JsonToken::NameSeparator => self.write(destination, ":"),
+            JsonToken::ValueSeparator => self.write(destination, ","),
+        }
+    }
+
+    fn begin_arg(&mut self, destination: &mut Option<CapArgs>) -> Option<()> {
+        self.count += 1;
+        self.depth = 0;
+        if self.streaming {
+            return Some(());
+        }
+        let (args, ends) = match destination {
+            Some(CapArgs::Buffered(args, ends)) => (args, ends),
+            _ => return None,
+        };
+        if self.count > 1 && self.count - 2 < ends.len() {
+            ends[self.count - 2] = args.len();
+        }
+        self.arg_start = args.len();
+        if self.count > MAX_ARG_COUNT {
+            self.start_streaming(args, ends)?;
+        }
+        Some(())
+    }
+
+    fn end_arg(&mut self, destination: &mut Option<CapArgs>) -> Option<()> {
+        if let (true, Some(CapArgs::Buffered(args, _))) = (self.streaming, destination) {
+            show_arg_page(self.count, self.part, true, args)?;
+            self.part = 1;
+            args.clear();
+        }
+        Some(())
+    }
+
+    fn finish(&self, destination: &mut Option<CapArgs>) {
+        if self.streaming {
+            *destination = Some(CapArgs::Shown(self.count));
+        }
+    }
+}
+
+impl JsonInterp<JsonArray<JsonAny>> for KadenaCapabilityArgsInterp {
+    #[inline(never)]
+    fn parse<'a, 'b>(
+        &self,
+        state: &'b mut Self::State,
+        token: JsonToken<'a>,
+        destination: &mut Option<Self::Returning>,
+    ) -> Result<(), Option<OOB>> {
+        use KadenaCapabilityArgsPhase::*;
+        match state.phase {
+            Start if token == JsonToken::BeginArray => {
+                *destination = Some(CapArgs::Buffered(ArrayVec::new(), [0; MAX_ARG_COUNT - 1]));
+                state.phase = BeforeArg;
+                return Err(None);
+            }
+            BeforeArg if token == JsonToken::EndArray && state.count == 0 => {
+                return Ok(());
+            }
+            AfterArg if token == JsonToken::EndArray => {
+                state.finish(destination);
+                return Ok(());
+            }
+            AfterArg if token == JsonToken::ValueSeparator => {
+                state.phase = BeforeArg;
+                return Err(None);
+            }
+            BeforeArg => {
+                state.begin_arg(destination).ok_or(Some(OOB::Reject))?;
+                state.phase = InArg;
+            }
+            InArg => {}
+            _ => return Err(Some(OOB::Reject)),
+        }
+        match token {
+            JsonToken::BeginArray | JsonToken::BeginObject => state.depth += 1,
+            JsonToken::EndArray | JsonToken::EndObject => {
+                state.depth = state.depth.checked_sub(1).ok_or(Some(OOB::Reject))?
+            }
+            JsonToken::NameSeparator | JsonToken::ValueSeparator if state.depth == 0 => {
+                return Err(Some(OOB::Reject))
+            }
+            _ => {}
+        }
+        state.write_token(destination, &token).ok_or(Some(OOB::Reject))?;
+        if state.depth == 0 {
+            state.end_arg(destination).ok_or(Some(OOB::Reject))?;
+            state.phase = AfterArg;
+        }
+        Err(None)
+    }
+}
+
+// Hashes a JSON string without keeping it, for strings too long to accumulate
+pub struct JsonStringHasher;
+
+impl ParserCommon<JsonString> for JsonStringHasher {
+    type State = (<DropInterp as ParserCommon<JsonString>>::State, Blake2b);
+    type Returning = Zeroizing<Hash<32>>;
+    fn init(&self) -> Self::State {
+        (
+            <DropInterp as ParserCommon<JsonString>>::init(&DropInterp),
+            Hasher::new(),
+        )
+    }
+}
+
+impl JsonInterp<JsonString> for JsonStringHasher {
+    #[inline(never)]
+    fn parse<'a, 'b>(
+        &self,
+        (ref mut drop_state, ref mut hasher): &'b mut Self::State,
+        token: JsonToken<'a>,
+        destination: &mut Option<Self::Returning>,
+    ) -> Result<(), Option<OOB>> {
+        if let JsonToken::String(s) = token {
+            hasher.update(s.as_bytes());
+        }
+        <DropInterp as JsonInterp<JsonString>>::parse(&DropInterp, drop_state, token, &mut None)?;
+        *destination = Some(hasher.finalize());
+        Ok(())
+    }
+}
+
+// Number of values dropped by Lenient in the current transaction
+static mut DROPPED_FIELDS: u16 = 0;
+
+fn reset_dropped_fields() {
+    unsafe {
+        DROPPED_FIELDS = 0;
+    }
+}
+
+fn dropped_fields() -> u16 {
+    unsafe { DROPPED_FIELDS }
+}
+
+/// Interprets a JSON value with the wrapped interpreter, but if that rejects the value, the rest
+/// of the value is skipped and counted in `dropped_fields` instead of rejecting the whole input.
+///
+/// Rejections with a reason for the host, like the user declining a prompt, are passed on.
+pub struct Lenient<I>(pub I);
+
+impl<T, I: JsonInterp<T>> ParserCommon<T> for Lenient<I> {
+    // The state of the wrapped interpreter, or None once the value is being skipped, and the
+    // nesting depth of the value so far.
+    type State = (Option<<I as ParserCommon<T>>::State>, usize);
+    // None if the value was skipped.
+    type Returning = Option<<I as ParserCommon<T>>::Returning>;
+    fn init(&self) -> Self::State {
+        (Some(<I as ParserCommon<T>>::init(&self.0)), 0)
+    }
+}
+
+impl<T, I: JsonInterp<T>> JsonInterp<T> for Lenient<I> {
+    #[inline(never)]
+    fn parse<'a, 'b>(
+        &self,
+        (ref mut inner, ref mut depth): &'b mut Self::State,
+        token: JsonToken<'a>,
+        destination: &mut Option<Self::Returning>,
+    ) -> Result<(), Option<OOB>> {
+        match token {
+            JsonToken::BeginArray | JsonToken::BeginObject => *depth += 1,
+            JsonToken::EndArray | JsonToken::EndObject => *depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        if let Some(ref mut s) = inner {
+            let sub_destination = destination.get_or_insert(None);
+            match <I as JsonInterp<T>>::parse(&self.0, s, token, sub_destination) {
+                Err(Some(OOB::Reject)) if !reject_reason_pending() => {
+                    info!("Dropping unrecognized value");
+                    set_from_thunk(inner, || None);
+                    *destination = Some(None);
+                    unsafe {
+                        DROPPED_FIELDS = DROPPED_FIELDS.saturating_add(1);
+                    }
+                }
+                rv => return rv,
+            }
+        }
+        // Skipping; the value ends with the token which closes its outermost array or object.
+        if *depth == 0 {
+            Ok(())
+        } else {
+            Err(None)
+        }
+    }
+}
+
+// The keys of the structs checked by UniqueKeys. The interpreters read no other keys, so a
+// repeated unknown key cannot change what is shown.
+const STRUCT_KEYS: [&str; 22] = [
+    "nonce",
+    "meta",
+    "signers",
+    "payload",
+    "networkId",
+    "verifiers",
+    "chainId",
+    "sender",
+    "gasLimit",
+    "gasPrice",
+    "ttl",
+    "creationTime",
+    "args",
+    "name",
+    "scheme",
+    "pubKey",
+    "addr",
+    "clist",
+    "data",
+    "code",
+    "exec",
+    "proof",
+];
+// The length of the longest of STRUCT_KEYS
+const MAX_KEY_SIZE: usize = 12;
+
+/// Interprets a JSON object with the wrapped interpreter, rejecting it with
+/// `KadenaStatusWord::DuplicateKey` if a key appears twice.
+///
+/// The struct interpreters take the last value of a repeated key, while other JSON decoders may
+/// take the first one, so the user could be shown a different command than the one executed.
+/// Escaped keys are rejected outright, as they could spell an existing key differently.
+pub struct UniqueKeys<I>(pub I);
+
+pub struct KeyTracker {
+    // Nesting depth within the object; its keys are at depth 1.
+    depth: usize,
+    expecting_key: bool,
+    // The key read so far, which can come in several tokens; None once it is longer than any
+    // of STRUCT_KEYS.
+    key: Option<ArrayVec<u8, MAX_KEY_SIZE>>,
+    // Bit i is set once STRUCT_KEYS[i] has been seen
+    seen: u32,
+}
+
+impl KeyTracker {
+    fn new() -> KeyTracker {
+        KeyTracker {
+            depth: 0,
+            expecting_key: false,
+            key: Some(ArrayVec::new()),
+            seen: 0,
+        }
+    }
+
+    fn observe(&mut self, token: &JsonToken) -> Option<()> {
+        match token {
+            JsonToken::BeginObject => {
+                self.depth += 1;
+                if self.depth == 1 {
+                    self.expecting_key = true;
+                }
+            }
+            JsonToken::BeginArray => self.depth += 1,
+            JsonToken::EndArray | JsonToken::EndObject => self.depth = self.depth.saturating_sub(1),
+            JsonToken::String(s) if self.depth == 1 && self.expecting_key => {
+                if s.contains('\\') {
+                    return None;
+                }
+                if let Some(key) = &mut self.key {
+                    if key.try_extend_from_slice(s.as_bytes()).is_err() {
+                        self.key = None;
+                    }
+                }
+            }
+            JsonToken::NameSeparator if self.depth == 1 => {
+                let key = core::mem::replace(&mut self.key, Some(ArrayVec::new()));
+                let known = key.and_then(|k| {
+                    STRUCT_KEYS
+                        .iter()
+                        .position(|n| n.as_bytes() == k.as_slice())
+                });
+                if let Some(i) = known {
+                    if self.seen & (1 << i) != 0 {
+                        return reject_with(KadenaStatusWord::DuplicateKey);
+                    }
+                    self.seen |= 1 << i;
+                }
+                self.expecting_key = false;
+            }
+            JsonToken::ValueSeparator if self.depth == 1 => self.expecting_key = true,
+            _ => {}
+        }
+        Some(())
+    }
+}
+
+impl<T, I: JsonInterp<T>> ParserCommon<T> for UniqueKeys<I> {
+    type State = (<I as ParserCommon<T>>::State, KeyTracker);
+    type Returning = <I as ParserCommon<T>>::Returning;
+    fn init(&self) -> Self::State {
+        (<I as ParserCommon<T>>::init(&self.0), KeyTracker::new())
+    }
+}
+
+impl<T, I: JsonInterp<T>> JsonInterp<T> for UniqueKeys<I> {
+    #[inline(never)]
+    fn parse<'a, 'b>(
+        &self,
+        (ref mut inner, ref mut tracker): &'b mut Self::State,
+        token: JsonToken<'a>,
+        destination: &mut Option<Self::Returning>,
+    ) -> Result<(), Option<OOB>> {
+        tracker.observe(&token).ok_or(Some(OOB::Reject))?;
+        <I as JsonInterp<T>>::parse(&self.0, inner, token, destination)
+    }
+}
+
+// ----------------------------------------------------------------------------------
+
+// tx_type
+// 0 -> Transfer
+// 1 -> Transfer create
+// 2 -> Transfer cross-chain
+
+#[allow(clippy::too_many_arguments)]
+#[inline(never)]
+fn handle_tx_param_1(
+    pkh_str: &ArrayString<64>,
+    hasher: &mut Blake2b,
+    tx_type: u8,
+    recipient: &ArrayVec<u8, PARAM_RECIPIENT_SIZE>,
+    recipient_chain: &ArrayVec<u8, PARAM_RECIPIENT_CHAIN_SIZE>,
+    amount: &ArrayVec<u8, PARAM_AMOUNT_SIZE>,
+    network: &ArrayVec<u8, PARAM_NETWORK_SIZE>,
+    namespace: &ArrayVec<u8, PARAM_NAMESPACE_SIZE>,
+    mod_name: &ArrayVec<u8, PARAM_MOD_NAME_SIZE>,
+) -> Option<()> {
+    let amount_str = from_utf8(amount).ok()?;
+    let recipient_str = from_utf8(recipient).ok()?;
+    let recipient_chain_str = from_utf8(recipient_chain).ok()?;
+    let network_str = from_utf8(network).ok()?;
+    let namespace_str = from_utf8(namespace).ok()?;
+    let mod_name_str = from_utf8(mod_name).ok()?;
+    if !namespace_str.is_empty() && mod_name_str.is_empty() {
+        return None;
+    }
+
+    // recipient_str should be hex
+    if recipient_str.len() != 64 {
+        return None;
+    }
+    for (_, c) in recipient_str.char_indices() {
+        if !matches!(c, '0'..='9' | 'A'..='F' | 'a'..='f') {
+            return None;
+        }
+    }
+    let target_chain = if tx_type == 2 {
+        match parse_chain_id(recipient_chain) {
+            Some(chain) => Some(chain),
+            None => return reject_with(KadenaStatusWord::InvalidChain),
+        }
+    } else {
+        check_positive_integer(recipient_chain_str)?;
+        None
+    };
+    check_decimal(amount_str)?;
+
+    let coin_or_namespace = |hasher: &mut Blake2b| -> Option<()> {
+        if namespace_str.is_empty() {
+            write!(hasher, "coin").ok()?;
+        } else {
+            write!(hasher, "{}.{}", namespace_str, mod_name_str).ok()?;
+        }
+        Some(())
+    };
+
+    // curly braces are escaped like '{{', '}}'
+    // The JSON struct begins here, and ends in handle_tx_params_2
+    write!(hasher, "{{").ok()?;
+    write!(hasher, "\"networkId\":\"{}\"", network_str).ok()?;
+    match tx_type {
+        0 => {
+            write!(
+                hasher,
+                ",\"payload\":{{\"exec\":{{\"data\":{{}},\"code\":\"("
+            )
+            .ok()?;
+            coin_or_namespace(hasher)?;
+            write!(hasher, ".transfer").ok()?;
+            write!(hasher, " \\\"k:{}\\\"", pkh_str).ok()?;
+            write!(hasher, " \\\"k:{}\\\"", recipient_str).ok()?;
+            write!(hasher, " {})\"}}}}", amount_str).ok()?;
+            write!(hasher, ",\"signers\":[{{\"pubKey\":").ok()?;
+            write!(hasher, "\"{}\"", pkh_str).ok()?;
+            write!(hasher, ",\"clist\":[{{\"args\":[").ok()?;
+            write!(hasher, "\"k:{}\",", pkh_str).ok()?;
+            write!(hasher, "\"k:{}\",", recipient_str).ok()?;
+            write!(hasher, "{}]", amount_str).ok()?;
+            write!(hasher, ",\"name\":\"").ok()?;
+            coin_or_namespace(hasher)?;
+            write!(
+                hasher,
+                ".TRANSFER\"}},{{\"args\":[],\"name\":\"coin.GAS\"}}]}}]"
+            )
+            .ok()?;
+        }
+        1 => {
+            write!(hasher, ",\"payload\":{{\"exec\":{{\"data\":{{").ok()?;
+            write!(hasher, "\"ks\":{{\"pred\":\"keys-all\",\"keys\":[").ok()?;
+            write!(hasher, "\"{}\"]}}}}", recipient_str).ok()?;
+            write!(hasher, ",\"code\":\"(").ok()?;
+            coin_or_namespace(hasher)?;
+            write!(hasher, ".transfer-create").ok()?;
+            write!(hasher, " \\\"k:{}\\\"", pkh_str).ok()?;
+            write!(hasher, " \\\"k:{}\\\"", recipient_str).ok()?;
             write!(hasher, " (read-keyset \\\"ks\\\")").ok()?;
             write!(hasher, " {})\"}}}}", amount_str).ok()?;
             write!(hasher, ",\"signers\":[{{\"pubKey\":").ok()?;
@@ -803,385 +2212,1038 @@ fn handle_tx_param_1(
             )
             .ok()?;
         }
-        2 => {
-            write!(hasher, ",\"payload\":{{\"exec\":{{\"data\":{{").ok()?;
-            write!(hasher, "\"ks\":{{\"pred\":\"keys-all\",\"keys\":[").ok()?;
-            write!(hasher, "\"{}\"]}}}}", recipient_str).ok()?;
-            write!(hasher, ",\"code\":\"(").ok()?;
-            coin_or_namespace(hasher)?;
-            write!(hasher, ".transfer-crosschain").ok()?;
-            write!(hasher, " \\\"k:{}\\\"", pkh_str).ok()?;
-            write!(hasher, " \\\"k:{}\\\"", recipient_str).ok()?;
-            write!(hasher, " (read-keyset \\\"ks\\\")").ok()?;
-            write!(hasher, " \\\"{}\\\"", recipient_chain_str).ok()?;
-            write!(hasher, " {})\"}}}}", amount_str).ok()?;
-            write!(hasher, ",\"signers\":[{{\"pubKey\":").ok()?;
-            write!(hasher, "\"{}\"", pkh_str).ok()?;
-            write!(hasher, ",\"clist\":[{{\"args\":[").ok()?;
-            write!(hasher, "\"k:{}\",", pkh_str).ok()?;
-            write!(hasher, "\"k:{}\",", recipient_str).ok()?;
-            write!(hasher, "{},", amount_str).ok()?;
-            write!(hasher, "\"{}\"]", recipient_chain_str).ok()?;
-            write!(hasher, ",\"name\":\"").ok()?;
-            coin_or_namespace(hasher)?;
-            write!(
-                hasher,
-                ".TRANSFER_XCHAIN\"}},{{\"args\":[],\"name\":\"coin.GAS\"}}]}}]"
-            )
-            .ok()?;
+        2 => {
+            write!(hasher, ",\"payload\":{{\"exec\":{{\"data\":{{").ok()?;
+            write!(hasher, "\"ks\":{{\"pred\":\"keys-all\",\"keys\":[").ok()?;
+            write!(hasher, "\"{}\"]}}}}", recipient_str).ok()?;
+            write!(hasher, ",\"code\":\"(").ok()?;
+            coin_or_namespace(hasher)?;
+            write!(hasher, ".transfer-crosschain").ok()?;
+            write!(hasher, " \\\"k:{}\\\"", pkh_str).ok()?;
+            write!(hasher, " \\\"k:{}\\\"", recipient_str).ok()?;
+            write!(hasher, " (read-keyset \\\"ks\\\")").ok()?;
+            write!(hasher, " \\\"{}\\\"", recipient_chain_str).ok()?;
+            write!(hasher, " {})\"}}}}", amount_str).ok()?;
+            write!(hasher, ",\"signers\":[{{\"pubKey\":").ok()?;
+            write!(hasher, "\"{}\"", pkh_str).ok()?;
+            write!(hasher, ",\"clist\":[{{\"args\":[").ok()?;
+            write!(hasher, "\"k:{}\",", pkh_str).ok()?;
+            write!(hasher, "\"k:{}\",", recipient_str).ok()?;
+            write!(hasher, "{},", amount_str).ok()?;
+            write!(hasher, "\"{}\"]", recipient_chain_str).ok()?;
+            write!(hasher, ",\"name\":\"").ok()?;
+            coin_or_namespace(hasher)?;
+            write!(
+                hasher,
+                ".TRANSFER_XCHAIN\"}},{{\"args\":[],\"name\":\"coin.GAS\"}}]}}]"
+            )
+            .ok()?;
+        }
+        _ => {}
+    }
+
+    if namespace_str.is_empty() {
+        scroller("Transfer", |w| Ok(write!(w, "KDA")?))?;
+    } else {
+        scroller("Transfer", |w| {
+            Ok(write!(w, "{}.{}", Sanitized(namespace_str), Sanitized(mod_name_str))?)
+        })?;
+    }
+
+    let sender_note = OwnAccountNote(own_key_index(pkh_str.as_bytes()));
+    let recipient_note = OwnAccountNote(own_key_index(recipient.as_slice()));
+    let mut recipient_account = ArrayString::<{ address_book::ACCOUNT_SIZE }>::new();
+    write!(recipient_account, "k:{}", recipient_str).ok()?;
+    let recipient_entry = address_book::find(recipient_account.as_bytes());
+    scroller_paginated("From", |w| Ok(write!(w, "k:{}{}", pkh_str, sender_note)?))?;
+    match recipient_entry {
+        Some(entry) => scroller_paginated("To", |w| Ok(write!(w, "{}", entry)?))?,
+        None => scroller_paginated("To", |w| {
+            Ok(write!(w, "k:{}{}", recipient_str, recipient_note)?)
+        })?,
+    }
+    address_book::check_recipient(recipient_account.as_bytes())?;
+    if let Some(chain) = target_chain {
+        scroller("To Chain", |w| Ok(write!(w, "{}", chain)?))?;
+        review_xchain_target(chain)?;
+    }
+    scroller("Amount", |w| Ok(write!(w, "{}", amount_str)?))?;
+    if namespace_str.is_empty() {
+        check_spending_limit(Decimal::parse(amount.as_slice()))?;
+    }
+    Some(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_tx_params_2(
+    pkh_str: &ArrayString<64>,
+    hasher: &mut Blake2b,
+    gas_price: &ArrayVec<u8, PARAM_GAS_PRICE_SIZE>,
+    gas_limit: &ArrayVec<u8, PARAM_GAS_LIMIT_SIZE>,
+    creation_time: &ArrayVec<u8, PARAM_CREATION_TIME_SIZE>,
+    chain_id: &ArrayVec<u8, PARAM_CHAIN_SIZE>,
+    nonce: &ArrayVec<u8, PARAM_NOONCE_SIZE>,
+    ttl: &ArrayVec<u8, PARAM_TTL_SIZE>,
+) -> Option<()> {
+    let gas_price_str = from_utf8(gas_price).ok()?;
+    let gas_limit_str = from_utf8(gas_limit).ok()?;
+    let chain_id_str = from_utf8(chain_id).ok()?;
+    let ttl_str = from_utf8(ttl).ok()?;
+    let creation_time_str = from_utf8(creation_time).ok()?;
+    {
+        // gas_price_str should be positive integer, decimal or exponential value
+        if gas_price_str.is_empty() {
+            return None;
+        }
+        let mut decimal = false;
+        let mut exp = false;
+        let mut should_be_minus = false;
+        for (_, c) in gas_price_str.char_indices() {
+            if should_be_minus {
+                if c == '-' {
+                    should_be_minus = false;
+                    continue;
+                } else {
+                    return None;
+                }
+            }
+            if !matches!(c, '0'..='9') {
+                if c == '.' && !decimal {
+                    decimal = true;
+                    continue;
+                }
+                if c == 'e' && !exp && decimal {
+                    exp = true;
+                    should_be_minus = true;
+                    continue;
+                }
+                return None;
+            }
+        }
+    }
+    check_positive_integer(gas_limit_str)?;
+    let chain = match parse_chain_id(chain_id) {
+        Some(chain) => chain,
+        None => return reject_with(KadenaStatusWord::InvalidChain),
+    };
+    check_positive_integer(creation_time_str)?;
+    check_decimal(ttl_str)?;
+    write!(hasher, ",\"meta\":{{").ok()?;
+    write!(hasher, "\"creationTime\":{}", creation_time_str).ok()?;
+    write!(hasher, ",\"ttl\":{}", ttl_str).ok()?;
+    write!(hasher, ",\"gasLimit\":{}", gas_limit_str).ok()?;
+    write!(hasher, ",\"chainId\":\"{}\"", chain_id_str).ok()?;
+    write!(hasher, ",\"gasPrice\":{}", gas_price_str).ok()?;
+    write!(hasher, ",\"sender\":\"k:{}\"", pkh_str).ok()?;
+    write!(hasher, "}}").ok()?;
+    write!(hasher, ",\"nonce\":\"{}\"", from_utf8(nonce).ok()?).ok()?;
+    // The JSON struct ends here
+    write!(hasher, "}}").ok()?;
+
+    scroller("Paying Gas (1/2)", |w| {
+        Ok(write!(w, "At most {}", from_utf8(gas_limit)?,)?)
+    })?;
+    scroller("Paying Gas (2/2)", |w| {
+        Ok(write!(w, "Price {}", from_utf8(gas_price)?)?)
+    })?;
+    check_meta(&MetaValues {
+        gas_price: Some(gas_price.as_slice()),
+        gas_limit: Some(gas_limit.as_slice()),
+        ttl: Some(ttl.as_slice()),
+    })?;
+    review_source_chain(chain)
+}
+
+fn check_decimal(s: &str) -> Option<()> {
+    if s.is_empty() {
+        return None;
+    }
+    let mut decimal = false;
+    for (_, c) in s.char_indices() {
+        if !matches!(c, '0'..='9') {
+            if c == '.' && !decimal {
+                decimal = true;
+                continue;
+            }
+            return None;
+        }
+    }
+    Some(())
+}
+
+fn check_positive_integer(s: &str) -> Option<()> {
+    if s.is_empty() {
+        return None;
+    }
+    for (_, c) in s.char_indices() {
+        if !matches!(c, '0'..='9') {
+            return None;
+        }
+    }
+    Some(())
+}
+
+// Define some useful type aliases
+pub type OptionByteVec<const N: usize> = Option<ArrayVec<u8, N>>;
+type SubDefT = SubInterp<DefaultInterp>;
+const SUB_DEF: SubDefT = SubInterp(DefaultInterp);
+
+// This is kept in State to avoid passing it in-between the sub-parsers
+// via parameters / DynBind
+type HasherAndPrivKey = (Blake2b, ECPrivateKey<32, 'E'>);
+
+pub type PathParserT = impl InterpParser<Bip32Key, Returning = HasherAndPrivKey>;
+
+const PATH_PARSER: PathParserT = MoveAction(
+    SUB_DEF,
+    mkmvfn(
+        |path: <SubDefT as ParserCommon<Bip32Key>>::Returning,
+         destination: &mut Option<HasherAndPrivKey>| {
+            set_from_thunk(destination, || {
+                Some((Hasher::new(), Ed25519::from_bip32(&path)))
+            });
+            Some(())
+        },
+    ),
+);
+
+type TxParams1ParserT = (
+    DefaultInterp,
+    (SubDefT, (SubDefT, (SubDefT, (SubDefT, (SubDefT, SubDefT))))),
+);
+const TX_PARAMS1_PARSER: TxParams1ParserT = (
+    DefaultInterp,
+    (SUB_DEF, (SUB_DEF, (SUB_DEF, (SUB_DEF, (SUB_DEF, SUB_DEF))))),
+);
+
+pub type RecipientAmountT =
+    impl InterpParser<MakeTransferTxParameters1, Returning = HasherAndPrivKey>;
+
+const RECIPIENT_AMOUNT_PARSER: RecipientAmountT
+  = MoveAction(
+      TX_PARAMS1_PARSER
+    , mkmvfn(|(tx_type, optv1): <TxParams1ParserT as ParserCommon<MakeTransferTxParameters1>>::Returning
+             , destination:&mut Option<HasherAndPrivKey>| {
+        let (recipient, optv2) = optv1?;
+        let (recipient_chain, optv3) = optv2?;
+        let (network, optv4) = optv3?;
+        let (amount, optv5) = optv4?;
+        let (namespace, mod_name) = optv5?;
+        match destination {
+            Some((ref mut hasher, privkey)) => {
+                let mut pkh_str: ArrayString<64> = ArrayString::new();
+                {
+                    with_public_keys_int(privkey, |_: &_, pkh: &PKH| { try_option(|| -> Option<()> {
+                        write!(mk_prompt_write(&mut pkh_str), "{}", pkh).ok()
+                    }())}).ok()?;
+                }
+                handle_tx_param_1(&pkh_str, hasher, tx_type?, recipient.as_ref()?, recipient_chain.as_ref()?, amount.as_ref()?, network.as_ref()?, namespace.as_ref()?, mod_name.as_ref()?)?;
+
+            }
+            _ => { panic!("should have been set") }
+        }
+        Some(())
+    }),
+    );
+
+type TxParams2ParserT = (SubDefT, (SubDefT, (SubDefT, (SubDefT, (SubDefT, SubDefT)))));
+const TX_PARAMS2_PARSER: TxParams2ParserT =
+    (SUB_DEF, (SUB_DEF, (SUB_DEF, (SUB_DEF, (SUB_DEF, SUB_DEF)))));
+
+pub type MetaNonceT = impl InterpParser<MakeTransferTxParameters2, Returning = HasherAndPrivKey>;
+
+const META_NONCE_PARSER: MetaNonceT =
+    MoveAction(
+        TX_PARAMS2_PARSER,
+        mkmvfn(
+            |(gas_price, optv1): <TxParams2ParserT as ParserCommon<
+                MakeTransferTxParameters2,
+            >>::Returning,
+             destination: &mut Option<HasherAndPrivKey>| {
+                let (gas_limit, optv2) = optv1?;
+                let (creation_time, optv3) = optv2?;
+                let (chain_id, optv4) = optv3?;
+                let (nonce, ttl) = optv4?;
+                match destination {
+                    Some((ref mut hasher, privkey)) => {
+                        let mut pkh_str: ArrayString<64> = ArrayString::new();
+                        {
+                            with_public_keys_int(privkey, |_: &_, pkh: &PKH| {
+                                try_option(|| -> Option<()> {
+                                    write!(mk_prompt_write(&mut pkh_str), "{}", pkh).ok()
+                                }())
+                            })
+                            .ok()?;
+                        }
+                        handle_tx_params_2(
+                            &pkh_str,
+                            hasher,
+                            &gas_price?,
+                            &gas_limit?,
+                            &creation_time?,
+                            &chain_id?,
+                            &nonce?,
+                            &ttl?,
+                        )?;
+                    }
+                    _ => {
+                        panic!("destination should have been set")
+                    }
+                }
+                Some(())
+            },
+        ),
+    );
+
+pub type MakeTransferTxImplT =
+    impl InterpParser<MakeTransferTxParameters, Returning = ArrayVec<u8, 128_usize>>;
+
+pub struct MakeTx;
+pub static MAKE_TRANSFER_TX_IMPL: MakeTransferTxImplT = MakeTx;
+
+pub enum MakeTxSubState {
+    Init,
+    Path(<PathParserT as ParserCommon<Bip32Key>>::State),
+    RecipientAmount(<RecipientAmountT as ParserCommon<MakeTransferTxParameters1>>::State),
+    MetaNonce(<MetaNonceT as ParserCommon<MakeTransferTxParameters2>>::State),
+    Done,
+}
+
+impl ParserCommon<MakeTransferTxParameters> for MakeTx {
+    type State = (Option<HasherAndPrivKey>, MakeTxSubState);
+    type Returning = ArrayVec<u8, 128_usize>;
+    fn init(&self) -> Self::State {
+        (None, MakeTxSubState::Init)
+    }
+}
+
+impl InterpParser<MakeTransferTxParameters> for MakeTx {
+    #[inline(never)]
+    fn parse<'a, 'b>(
+        &self,
+        (ref mut hasher_and_privkey, ref mut state): &'b mut Self::State,
+        chunk: &'a [u8],
+        destination: &mut Option<Self::Returning>,
+    ) -> ParseResult<'a> {
+        let mut cursor = chunk;
+        loop {
+            match state {
+                MakeTxSubState::Init => {
+                    info!(
+                        "State sizes \nMakeTx: {}\n",
+                        core::mem::size_of::<MakeTxSubState>()
+                    );
+                    init_with_default(destination);
+                    reset_tx_review();
+                    set_from_thunk(state, || {
+                        MakeTxSubState::Path(<PathParserT as ParserCommon<Bip32Key>>::init(
+                            &PATH_PARSER,
+                        ))
+                    })
+                }
+                MakeTxSubState::Path(ref mut sub) => {
+                    cursor = <PathParserT as InterpParser<Bip32Key>>::parse(
+                        &PATH_PARSER,
+                        sub,
+                        cursor,
+                        hasher_and_privkey,
+                    )?;
+                    set_from_thunk(state, || {
+                        MakeTxSubState::RecipientAmount(<RecipientAmountT as ParserCommon<
+                            MakeTransferTxParameters1,
+                        >>::init(
+                            &RECIPIENT_AMOUNT_PARSER
+                        ))
+                    })
+                }
+                MakeTxSubState::RecipientAmount(ref mut sub) => {
+                    cursor = <RecipientAmountT as InterpParser<MakeTransferTxParameters1>>::parse(
+                        &RECIPIENT_AMOUNT_PARSER,
+                        sub,
+                        cursor,
+                        hasher_and_privkey,
+                    )?;
+                    set_from_thunk(state, || {
+                        MakeTxSubState::MetaNonce(<MetaNonceT as ParserCommon<
+                            MakeTransferTxParameters2,
+                        >>::init(
+                            &META_NONCE_PARSER
+                        ))
+                    })
+                }
+                MakeTxSubState::MetaNonce(ref mut sub) => {
+                    cursor = <MetaNonceT as InterpParser<MakeTransferTxParameters2>>::parse(
+                        &META_NONCE_PARSER,
+                        sub,
+                        cursor,
+                        hasher_and_privkey,
+                    )?;
+                    set_from_thunk(state, || MakeTxSubState::Done);
+                }
+                MakeTxSubState::Done => {
+                    match hasher_and_privkey {
+                        Some((ref mut hasher, privkey)) => {
+                            #[allow(clippy::needless_borrow)] // Needed for nanos
+                            final_accept_prompt(&[&"Sign Transaction?"])
+                                .or_else(user_rejected)
+                                .ok_or((Some(OOB::Reject), cursor))?;
+                            *destination = Some(ArrayVec::new());
+
+                            let mut add_sig = || -> Option<()> {
+                                let hash = hasher.finalize();
+                                let sig = eddsa_sign_int(privkey, &hash.0).ok()?;
+                                destination
+                                    .as_mut()?
+                                    .try_extend_from_slice(&sig.0[..])
+                                    .ok()?;
+                                Some(())
+                            };
+                            add_sig().ok_or((Some(OOB::Reject), cursor))?;
+
+                            with_public_keys_int(privkey, |key: &_, _: &PKH| {
+                                try_option(|| -> Option<()> {
+                                    let key_x = ed25519_public_key_bytes(key);
+                                    destination.as_mut()?.try_extend_from_slice(key_x).ok()
+                                }())
+                            })
+                            .or(Err((Some(OOB::Reject), cursor)))?;
+                            break Ok(cursor);
+                        }
+                        _ => {
+                            panic!("should have been set")
+                        }
+                    }
+                }
+            }
         }
-        _ => {}
     }
+}
 
-    if namespace_str.is_empty() {
-        scroller("Transfer", |w| Ok(write!(w, "KDA")?))?;
-    } else {
-        scroller("Transfer", |w| {
-            Ok(write!(w, "{}.{}", namespace_str, mod_name_str)?)
-        })?;
-    }
+// ----------------------------------------------------------------------------------
 
-    scroller_paginated("From", |w| Ok(write!(w, "k:{}", pkh_str)?))?;
-    scroller_paginated("To", |w| Ok(write!(w, "k:{}", recipient_str)?))?;
-    if tx_type == 2 {
-        scroller("To Chain", |w| Ok(write!(w, "{}", recipient_chain_str)?))?;
-    }
-    scroller("Amount", |w| Ok(write!(w, "{}", amount_str)?))?;
-    Some(())
+// Reviews one command of a batch like SIGN_IMPL does, returning its hash and whether it had
+// no warnings.
+type BatchCommandActionT =
+    impl InterpParser<JsonCommand, Returning = (Zeroizing<Hash<32>>, bool)>;
+const BATCH_COMMAND_ACTION: BatchCommandActionT = Action(
+    ObserveLengthedBytes(
+        Hasher::new,
+        Hasher::update,
+        Json(Action(
+            UniqueKeys(KadenaCmdInterp {
+                field_nonce: NONCE_ACTION,
+                field_meta: META_ACTION,
+                field_payload: PAYLOAD_ACTION,
+                field_signers: SIGNERS_ACTION,
+                field_network_id: NETWORK_ID_ACTION,
+                field_verifiers: VERIFIERS_ACTION,
+            }),
+            mkfn(
+                |cmd: &KadenaCmd<_, _, Option<CapabilityCoverage>, _, Option<bool>, Option<All>>,
+                 dest: &mut Option<bool>| {
+                    let known_network = match cmd.field_network_id {
+                        Some(known) => known,
+                        None => show_network(None)?,
+                    };
+                    check_gas_station_shown()?;
+                    let coverage = command_coverage(
+                        cmd.field_signers.as_ref(),
+                        cmd.field_verifiers.as_ref(),
+                    );
+                    *dest = Some(known_network && coverage == CapabilityCoverage::Full);
+                    show_coverage_warning(coverage)
+                },
+            ),
+        )),
+        true,
+    ),
+    mkfn(
+        |(no_warnings, mut hasher): &(Option<bool>, Blake2b),
+         destination: &mut Option<(Zeroizing<Hash<32>>, bool)>| {
+            let no_warnings = (*no_warnings)?;
+            let the_hash = hasher.finalize();
+            if expert_mode() {
+                scroller("Transaction hash", |w| Ok(write!(w, "{}", the_hash.deref())?))?;
+            }
+            *destination = Some((the_hash, no_warnings));
+            Some(())
+        },
+    ),
+);
+
+pub const BATCH_SIGNATURES_SIZE: usize = 64 * BATCH_SIGNATURES_PER_REPLY;
+
+// The signatures of the last batch which did not fit in its response, kept for
+// GET_BATCH_SIGNATURES until the next request of another instruction.
+const MAX_KEPT_SIGNATURES: usize = MAX_BATCH_SIZE - BATCH_SIGNATURES_PER_REPLY;
+static mut BATCH_SIGNATURES: ArrayVec<[u8; 64], MAX_KEPT_SIGNATURES> = ArrayVec::new_const();
+
+pub fn clear_batch_signatures() {
+    unsafe { BATCH_SIGNATURES.clear() }
 }
 
-#[allow(clippy::too_many_arguments)]
-fn handle_tx_params_2(
-    pkh_str: &ArrayString<64>,
-    hasher: &mut Blake2b,
-    gas_price: &ArrayVec<u8, PARAM_GAS_PRICE_SIZE>,
-    gas_limit: &ArrayVec<u8, PARAM_GAS_LIMIT_SIZE>,
-    creation_time: &ArrayVec<u8, PARAM_CREATION_TIME_SIZE>,
-    chain_id: &ArrayVec<u8, PARAM_CHAIN_SIZE>,
-    nonce: &ArrayVec<u8, PARAM_NOONCE_SIZE>,
-    ttl: &ArrayVec<u8, PARAM_TTL_SIZE>,
-) -> Option<()> {
-    let gas_price_str = from_utf8(gas_price).ok()?;
-    let gas_limit_str = from_utf8(gas_limit).ok()?;
-    let chain_id_str = from_utf8(chain_id).ok()?;
-    let ttl_str = from_utf8(ttl).ok()?;
-    let creation_time_str = from_utf8(creation_time).ok()?;
-    {
-        // gas_price_str should be positive integer, decimal or exponential value
-        if gas_price_str.is_empty() {
-            return None;
-        }
-        let mut decimal = false;
-        let mut exp = false;
-        let mut should_be_minus = false;
-        for (_, c) in gas_price_str.char_indices() {
-            if should_be_minus {
-                if c == '-' {
-                    should_be_minus = false;
-                    continue;
-                } else {
-                    return None;
-                }
-            }
-            if !matches!(c, '0'..='9') {
-                if c == '.' && !decimal {
-                    decimal = true;
-                    continue;
-                }
-                if c == 'e' && !exp && decimal {
-                    exp = true;
-                    should_be_minus = true;
-                    continue;
-                }
-                return None;
-            }
-        }
+/// The signatures of the last batch from the index given by the single byte of `data`, as many as
+/// fit in a response; only those after the ones returned with the batch are kept.
+pub fn get_batch_signatures(
+    data: &[u8],
+) -> Result<ArrayVec<u8, BATCH_SIGNATURES_SIZE>, KadenaStatusWord> {
+    let kept = unsafe { &BATCH_SIGNATURES };
+    let signatures = match data {
+        [start] => (*start as usize)
+            .checked_sub(BATCH_SIGNATURES_PER_REPLY)
+            .and_then(|i| kept.get(i..))
+            .filter(|s| !s.is_empty()),
+        _ => None,
     }
-    check_positive_integer(gas_limit_str)?;
-    check_positive_integer(chain_id_str)?;
-    check_positive_integer(creation_time_str)?;
-    check_decimal(ttl_str)?;
-    write!(hasher, ",\"meta\":{{").ok()?;
-    write!(hasher, "\"creationTime\":{}", creation_time_str).ok()?;
-    write!(hasher, ",\"ttl\":{}", ttl_str).ok()?;
-    write!(hasher, ",\"gasLimit\":{}", gas_limit_str).ok()?;
-    write!(hasher, ",\"chainId\":\"{}\"", chain_id_str).ok()?;
-    write!(hasher, ",\"gasPrice\":{}", gas_price_str).ok()?;
-    write!(hasher, ",\"sender\":\"k:{}\"", pkh_str).ok()?;
-    write!(hasher, "}}").ok()?;
-    write!(hasher, ",\"nonce\":\"{}\"", from_utf8(nonce).ok()?).ok()?;
-    // The JSON struct ends here
-    write!(hasher, "}}").ok()?;
+    .ok_or(KadenaStatusWord::InvalidBatchSize)?;
+    let mut rv = ArrayVec::new();
+    for sig in signatures.iter().take(BATCH_SIGNATURES_PER_REPLY) {
+        rv.extend(sig.iter().copied());
+    }
+    Ok(rv)
+}
 
-    scroller("Paying Gas (1/2)", |w| {
-        Ok(write!(w, "At most {}", from_utf8(gas_limit)?,)?)
-    })?;
-    scroller("Paying Gas (2/2)", |w| {
-        Ok(write!(w, "Price {}", from_utf8(gas_price)?)?)
-    })?;
-    Some(())
+pub type SignBatchImplT =
+    impl InterpParser<SignBatchParameters, Returning = ArrayVec<u8, BATCH_SIGNATURES_SIZE>>;
+
+pub struct SignBatch;
+pub static SIGN_BATCH_IMPL: SignBatchImplT = SignBatch;
+
+pub enum SignBatchSubState {
+    Count(<DefaultInterp as ParserCommon<Byte>>::State),
+    Command(<BatchCommandActionT as ParserCommon<JsonCommand>>::State),
+    Path(<SignPathActionT as ParserCommon<Bip32Key>>::State),
+    Done,
 }
 
-fn check_decimal(s: &str) -> Option<()> {
-    if s.is_empty() {
-        return None;
+#[derive(Default)]
+pub struct SignBatchData {
+    count: Option<u8>,
+    command: Option<(Zeroizing<Hash<32>>, bool)>,
+    // Hashes of the commands reviewed so far
+    hashes: ArrayVec<Zeroizing<Hash<32>>, MAX_BATCH_SIZE>,
+    with_warnings: u8,
+    path: Option<ArrayVec<u32, 10>>,
+}
+
+impl SignBatchData {
+    fn count(&self) -> usize {
+        self.count.map_or(0, usize::from)
     }
-    let mut decimal = false;
-    for (_, c) in s.char_indices() {
-        if !matches!(c, '0'..='9') {
-            if c == '.' && !decimal {
-                decimal = true;
-                continue;
-            }
-            return None;
+
+    fn start_command(&self) -> Option<SignBatchSubState> {
+        reset_tx_review();
+        scroller("Signing", |w| {
+            Ok(write!(w, "Command {} of {}", self.hashes.len() + 1, self.count())?)
+        })?;
+        Some(SignBatchSubState::Command(
+            <BatchCommandActionT as ParserCommon<JsonCommand>>::init(&BATCH_COMMAND_ACTION),
+        ))
+    }
+
+    fn show_summary(&self) -> Option<()> {
+        match self.with_warnings {
+            0 => scroller("Summary", |w| Ok(write!(w, "{} commands", self.count())?)),
+            n => scroller("Summary", |w| {
+                Ok(write!(w, "{} commands, {} with warnings", self.count(), n)?)
+            }),
         }
     }
-    Some(())
 }
 
-fn check_positive_integer(s: &str) -> Option<()> {
-    if s.is_empty() {
-        return None;
+impl ParserCommon<SignBatchParameters> for SignBatch {
+    type State = (SignBatchData, SignBatchSubState);
+    type Returning = ArrayVec<u8, BATCH_SIGNATURES_SIZE>;
+    fn init(&self) -> Self::State {
+        (
+            SignBatchData::default(),
+            SignBatchSubState::Count(<DefaultInterp as ParserCommon<Byte>>::init(&DefaultInterp)),
+        )
     }
-    for (_, c) in s.char_indices() {
-        if !matches!(c, '0'..='9') {
-            return None;
+}
+
+impl InterpParser<SignBatchParameters> for SignBatch {
+    #[inline(never)]
+    fn parse<'a, 'b>(
+        &self,
+        (ref mut data, ref mut state): &'b mut Self::State,
+        chunk: &'a [u8],
+        destination: &mut Option<Self::Returning>,
+    ) -> ParseResult<'a> {
+        let mut cursor = chunk;
+        loop {
+            match state {
+                SignBatchSubState::Count(ref mut sub) => {
+                    cursor = <DefaultInterp as InterpParser<Byte>>::parse(
+                        &DefaultInterp,
+                        sub,
+                        cursor,
+                        &mut data.count,
+                    )?;
+                    if !(1..=MAX_BATCH_SIZE).contains(&data.count()) {
+                        reject_with::<()>(KadenaStatusWord::InvalidBatchSize);
+                        return Err((Some(OOB::Reject), cursor));
+                    }
+                    reset_unscoped_signers();
+                    reset_transfer_totals();
+                    reset_station_senders();
+                    let next = data.start_command().ok_or((Some(OOB::Reject), cursor))?;
+                    set_from_thunk(state, || next);
+                }
+                SignBatchSubState::Command(ref mut sub) => {
+                    cursor = <BatchCommandActionT as InterpParser<JsonCommand>>::parse(
+                        &BATCH_COMMAND_ACTION,
+                        sub,
+                        cursor,
+                        &mut data.command,
+                    )?;
+                    let (hash, no_warnings) =
+                        data.command.take().ok_or((Some(OOB::Reject), cursor))?;
+                    data.hashes.try_push(hash).or(Err((Some(OOB::Reject), cursor)))?;
+                    if !no_warnings {
+                        data.with_warnings += 1;
+                    }
+                    if data.hashes.len() < data.count() {
+                        let next = data.start_command().ok_or((Some(OOB::Reject), cursor))?;
+                        set_from_thunk(state, || next);
+                    } else {
+                        data.show_summary().ok_or((Some(OOB::Reject), cursor))?;
+                        set_from_thunk(state, || {
+                            SignBatchSubState::Path(<SignPathActionT as ParserCommon<
+                                Bip32Key,
+                            >>::init(
+                                &SIGN_PATH_ACTION
+                            ))
+                        });
+                    }
+                }
+                SignBatchSubState::Path(ref mut sub) => {
+                    cursor = <SignPathActionT as InterpParser<Bip32Key>>::parse(
+                        &SIGN_PATH_ACTION,
+                        sub,
+                        cursor,
+                        &mut data.path,
+                    )?;
+                    set_from_thunk(state, || SignBatchSubState::Done);
+                }
+                SignBatchSubState::Done => {
+                    show_transfer_totals().ok_or((Some(OOB::Reject), cursor))?;
+                    #[allow(clippy::needless_borrow)] // Needed for nanos
+                    final_accept_prompt(&[&"Sign Transactions?"])
+                        .or_else(user_rejected)
+                        .ok_or((Some(OOB::Reject), cursor))?;
+
+                    // By the time we get here, we've approved and just need to do the signatures.
+                    // Those which do not fit in the response are kept for GET_BATCH_SIGNATURES.
+                    let mut sign_all = || -> Option<()> {
+                        let path = data.path.as_ref()?;
+                        let mut rv = ArrayVec::new();
+                        let kept = unsafe { &mut BATCH_SIGNATURES };
+                        kept.clear();
+                        for (i, hash) in data.hashes.iter().enumerate() {
+                            let sig = eddsa_sign(path, &hash.0[..]).ok()?;
+                            if i < BATCH_SIGNATURES_PER_REPLY {
+                                rv.try_extend_from_slice(&sig.0[..]).ok()?;
+                            } else {
+                                kept.try_push(<[u8; 64]>::try_from(&sig.0[..]).ok()?).ok()?;
+                            }
+                        }
+                        *destination = Some(rv);
+                        Some(())
+                    };
+                    sign_all().ok_or((Some(OOB::Reject), cursor))?;
+                    break Ok(cursor);
+                }
+            }
         }
     }
-    Some(())
 }
 
-// Define some useful type aliases
-pub type OptionByteVec<const N: usize> = Option<ArrayVec<u8, N>>;
-type SubDefT = SubInterp<DefaultInterp>;
-const SUB_DEF: SubDefT = SubInterp(DefaultInterp);
+// ----------------------------------------------------------------------------------
 
-// This is kept in State to avoid passing it in-between the sub-parsers
-// via parameters / DynBind
-type HasherAndPrivKey = (Blake2b, ECPrivateKey<32, 'E'>);
+pub type SignWrappedImplT =
+    impl InterpParser<SignWrappedParameters, Returning = ArrayVec<u8, 128_usize>>;
 
-pub type PathParserT = impl InterpParser<Bip32Key, Returning = HasherAndPrivKey>;
+pub static SIGN_WRAPPED_IMPL: SignWrappedImplT = Action(
+    (WrappedCmd, SIGN_PATH_ACTION),
+    mkfn(
+        |(hash, path): &(Option<Zeroizing<Hash<32>>>, Option<ArrayVec<u32, 10>>),
+         destination: &mut _| {
+            show_transfer_totals()?;
+            #[allow(clippy::needless_borrow)] // Needed for nanos
+            final_accept_prompt(&[&"Sign Transaction?"]).or_else(user_rejected)?;
 
-const PATH_PARSER: PathParserT = MoveAction(
-    SUB_DEF,
-    mkmvfn(
-        |path: <SubDefT as ParserCommon<Bip32Key>>::Returning,
-         destination: &mut Option<HasherAndPrivKey>| {
-            set_from_thunk(destination, || {
-                Some((Hasher::new(), Ed25519::from_bip32(&path)))
-            });
+            // By the time we get here, we've approved and just need to do the signature.
+            let sig = eddsa_sign(path.as_ref()?, &hash.as_ref()?.0[..]).ok()?;
+            let mut rv = ArrayVec::<u8, 128>::new();
+            rv.try_extend_from_slice(&sig.0[..]).ok()?;
+            *destination = Some(rv);
             Some(())
         },
     ),
 );
 
-type TxParams1ParserT = (
-    DefaultInterp,
-    (SubDefT, (SubDefT, (SubDefT, (SubDefT, (SubDefT, SubDefT))))),
-);
-const TX_PARAMS1_PARSER: TxParams1ParserT = (
-    DefaultInterp,
-    (SUB_DEF, (SUB_DEF, (SUB_DEF, (SUB_DEF, (SUB_DEF, SUB_DEF))))),
-);
+// Parses the wrapper of a command without a JSON parser of its own: the `cmd` string is unescaped
+// as it streams in, and the unescaped bytes are hashed and parsed with SIGN_CMD_ACTION. The value
+// of `hash`, if any, must match the hash of the command; other fields, like `sigs`, are skipped.
+pub struct WrappedCmd;
 
-pub type RecipientAmountT =
-    impl InterpParser<MakeTransferTxParameters1, Returning = HasherAndPrivKey>;
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum WrapperKey {
+    Hash,
+    Cmd,
+    Other,
+}
 
-const RECIPIENT_AMOUNT_PARSER: RecipientAmountT
-  = MoveAction(
-      TX_PARAMS1_PARSER
-    , mkmvfn(|(tx_type, optv1): <TxParams1ParserT as ParserCommon<MakeTransferTxParameters1>>::Returning
-             , destination:&mut Option<HasherAndPrivKey>| {
-        let (recipient, optv2) = optv1?;
-        let (recipient_chain, optv3) = optv2?;
-        let (network, optv4) = optv3?;
-        let (amount, optv5) = optv4?;
-        let (namespace, mod_name) = optv5?;
-        match destination {
-            Some((ref mut hasher, privkey)) => {
-                let mut pkh_str: ArrayString<64> = ArrayString::new();
-                {
-                    with_public_keys_int(privkey, |_: &_, pkh: &PKH| { try_option(|| -> Option<()> {
-                        write!(mk_prompt_write(&mut pkh_str), "{}", pkh).ok()
-                    }())}).ok()?;
+#[derive(Debug)]
+enum WrapperScan {
+    Start,
+    BeforeKey,
+    Key,
+    AfterKey,
+    BeforeValue,
+    HashString,
+    CmdString,
+    // The value of another key; `depth` counts the arrays and objects it has open
+    OtherValue {
+        depth: u8,
+        in_string: bool,
+        escaped: bool,
+    },
+    AfterValue,
+    End,
+}
+
+// Position in an escape sequence of the `cmd` string
+#[derive(Debug)]
+enum Unescape {
+    Char,
+    Escape,
+    Unicode { digits: u8, value: u32 },
+}
+
+// Unescaped bytes are passed to the command parser in blocks of this size
+const UNESCAPED_BLOCK_SIZE: usize = 64;
+type UnescapedBlock = ArrayVec<u8, UNESCAPED_BLOCK_SIZE>;
+
+pub struct WrappedCmdState {
+    size_state: <DefaultInterp as ParserCommon<U32<{ Endianness::Little }>>>::State,
+    // Bytes of the wrapper still to come, once the length is known
+    remaining: Option<u32>,
+    scan: WrapperScan,
+    key: ArrayVec<u8, 4>,
+    key_too_long: bool,
+    current: WrapperKey,
+    hash_field: Option<ArrayVec<u8, 64>>,
+    cmd_seen: bool,
+    unescape: Unescape,
+    high_surrogate: Option<u32>,
+    hasher: Blake2b,
+    cmd_state: <SignCmdActionT as ParserCommon<Json<KadenaCmdSchema>>>::State,
+    cmd_result: Option<()>,
+    cmd_done: bool,
+}
+
+fn is_json_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+impl WrappedCmdState {
+    fn scan_byte(&mut self, b: u8, block: &mut UnescapedBlock) -> Option<()> {
+        use WrapperScan::*;
+        loop {
+            match self.scan {
+                Start | BeforeKey | AfterKey | BeforeValue | AfterValue | End
+                    if is_json_whitespace(b) => {}
+                Start if b == b'{' => self.scan = BeforeKey,
+                BeforeKey if b == b'"' => {
+                    self.key.clear();
+                    self.key_too_long = false;
+                    self.scan = Key;
                 }
-                handle_tx_param_1(&pkh_str, hasher, tx_type?, recipient.as_ref()?, recipient_chain.as_ref()?, amount.as_ref()?, network.as_ref()?, namespace.as_ref()?, mod_name.as_ref()?)?;
+                BeforeKey if b == b'}' => self.scan = End,
+                Key if b == b'"' => {
+                    self.current = match &self.key[..] {
+                        _ if self.key_too_long => WrapperKey::Other,
+                        b"hash" => WrapperKey::Hash,
+                        b"cmd" => WrapperKey::Cmd,
+                        _ => WrapperKey::Other,
+                    };
+                    self.scan = AfterKey;
+                }
+                // None of the keys of the wrapper need escaping
+                Key if b == b'\\' => return None,
+                Key => {
+                    if self.key.try_push(b).is_err() {
+                        self.key_too_long = true;
+                    }
+                }
+                AfterKey if b == b':' => self.scan = BeforeValue,
+                BeforeValue => match self.current {
+                    WrapperKey::Hash if b == b'"' && self.hash_field.is_none() => {
+                        self.hash_field = Some(ArrayVec::new());
+                        self.scan = HashString;
+                    }
+                    WrapperKey::Cmd if b == b'"' && !self.cmd_seen => {
+                        self.cmd_seen = true;
+                        self.scan = CmdString;
+                    }
+                    WrapperKey::Hash | WrapperKey::Cmd if b == b'"' => {
+                        return reject_with(KadenaStatusWord::DuplicateKey);
+                    }
+                    WrapperKey::Other => {
+                        self.scan = OtherValue {
+                            depth: 0,
+                            in_string: false,
+                            escaped: false,
+                        };
+                        continue;
+                    }
+                    _ => return None,
+                },
+                HashString if b == b'"' => self.scan = AfterValue,
+                HashString if b == b'\\' => return None,
+                HashString => self.hash_field.as_mut()?.try_push(b).ok()?,
+                CmdString => {
+                    if self.unescape_byte(b, block)? {
+                        self.flush_cmd(block)?;
+                        if !self.cmd_done {
+                            return None;
+                        }
+                        self.scan = AfterValue;
+                    }
+                }
+                OtherValue {
+                    ref mut depth,
+                    ref mut in_string,
+                    ref mut escaped,
+                } => {
+                    if *in_string {
+                        if *escaped {
+                            *escaped = false;
+                        } else if b == b'\\' {
+                            *escaped = true;
+                        } else if b == b'"' {
+                            *in_string = false;
+                            if *depth == 0 {
+                                self.scan = AfterValue;
+                            }
+                        }
+                    } else {
+                        match b {
+                            b'"' => *in_string = true,
+                            b'[' | b'{' => *depth = depth.checked_add(1)?,
+                            b']' | b'}' if *depth > 0 => {
+                                *depth -= 1;
+                                if *depth == 0 {
+                                    self.scan = AfterValue;
+                                }
+                            }
+                            // The end of a number or literal
+                            b',' | b']' | b'}' if *depth == 0 => {
+                                self.scan = AfterValue;
+                                continue;
+                            }
+                            _ if *depth == 0 && is_json_whitespace(b) => self.scan = AfterValue,
+                            _ => {}
+                        }
+                    }
+                }
+                AfterValue if b == b',' => self.scan = BeforeKey,
+                AfterValue if b == b'}' => self.scan = End,
+                _ => return None,
+            }
+            return Some(());
+        }
+    }
+
+    // Returns whether the byte ended the string
+    fn unescape_byte(&mut self, b: u8, block: &mut UnescapedBlock) -> Option<bool> {
+        match self.unescape {
+            Unescape::Char => match b {
+                b'"' if self.high_surrogate.is_none() => return Some(true),
+                b'"' => return None,
+                b'\\' => self.unescape = Unescape::Escape,
+                _ => self.push_cmd_byte(b, block)?,
+            },
+            Unescape::Escape => {
+                let c = match b {
+                    b'"' | b'\\' | b'/' => b,
+                    b'b' => 0x08,
+                    b'f' => 0x0c,
+                    b'n' => b'\n',
+                    b'r' => b'\r',
+                    b't' => b'\t',
+                    b'u' => {
+                        self.unescape = Unescape::Unicode {
+                            digits: 0,
+                            value: 0,
+                        };
+                        return Some(false);
+                    }
+                    _ => return None,
+                };
+                self.unescape = Unescape::Char;
+                self.push_cmd_byte(c, block)?;
+            }
+            Unescape::Unicode {
+                ref mut digits,
+                ref mut value,
+            } => {
+                *value = *value * 16 + char::from(b).to_digit(16)?;
+                *digits += 1;
+                if *digits == 4 {
+                    let unit = *value;
+                    self.unescape = Unescape::Char;
+                    self.push_code_unit(unit, block)?;
+                }
+            }
+        }
+        Some(false)
+    }
 
+    // \u escapes are UTF-16 code units; characters outside the BMP take a surrogate pair
+    fn push_code_unit(&mut self, unit: u32, block: &mut UnescapedBlock) -> Option<()> {
+        match (self.high_surrogate.take(), unit) {
+            (None, 0xD800..=0xDBFF) => {
+                self.high_surrogate = Some(unit);
+                Some(())
             }
-            _ => { panic!("should have been set") }
+            (Some(high), 0xDC00..=0xDFFF) => {
+                self.push_char(0x10000 + ((high - 0xD800) << 10) + (unit - 0xDC00), block)
+            }
+            (None, _) => self.push_char(unit, block),
+            (Some(_), _) => None,
         }
-        Some(())
-    }),
-    );
+    }
 
-type TxParams2ParserT = (SubDefT, (SubDefT, (SubDefT, (SubDefT, (SubDefT, SubDefT)))));
-const TX_PARAMS2_PARSER: TxParams2ParserT =
-    (SUB_DEF, (SUB_DEF, (SUB_DEF, (SUB_DEF, (SUB_DEF, SUB_DEF)))));
+    fn push_char(&mut self, code_point: u32, block: &mut UnescapedBlock) -> Option<()> {
+        let mut utf8 = [0; 4];
+        for b in char::from_u32(code_point)?.encode_utf8(&mut utf8).bytes() {
+            self.push_cmd_byte(b, block)?;
+        }
+        Some(())
+    }
 
-pub type MetaNonceT = impl InterpParser<MakeTransferTxParameters2, Returning = HasherAndPrivKey>;
+    fn push_cmd_byte(&mut self, b: u8, block: &mut UnescapedBlock) -> Option<()> {
+        if self.high_surrogate.is_some() {
+            return None;
+        }
+        if block.is_full() {
+            self.flush_cmd(block)?;
+        }
+        block.try_push(b).ok()
+    }
 
-const META_NONCE_PARSER: MetaNonceT =
-    MoveAction(
-        TX_PARAMS2_PARSER,
-        mkmvfn(
-            |(gas_price, optv1): <TxParams2ParserT as ParserCommon<
-                MakeTransferTxParameters2,
-            >>::Returning,
-             destination: &mut Option<HasherAndPrivKey>| {
-                let (gas_limit, optv2) = optv1?;
-                let (creation_time, optv3) = optv2?;
-                let (chain_id, optv4) = optv3?;
-                let (nonce, ttl) = optv4?;
-                match destination {
-                    Some((ref mut hasher, privkey)) => {
-                        let mut pkh_str: ArrayString<64> = ArrayString::new();
-                        {
-                            with_public_keys_int(privkey, |_: &_, pkh: &PKH| {
-                                try_option(|| -> Option<()> {
-                                    write!(mk_prompt_write(&mut pkh_str), "{}", pkh).ok()
-                                }())
-                            })
-                            .ok()?;
-                        }
-                        handle_tx_params_2(
-                            &pkh_str,
-                            hasher,
-                            &gas_price?,
-                            &gas_limit?,
-                            &creation_time?,
-                            &chain_id?,
-                            &nonce?,
-                            &ttl?,
-                        )?;
-                    }
-                    _ => {
-                        panic!("destination should have been set")
+    #[inline(never)]
+    fn flush_cmd(&mut self, block: &mut UnescapedBlock) -> Option<()> {
+        self.hasher.update(block);
+        if self.cmd_done {
+            if !block.iter().all(|b| is_json_whitespace(*b)) {
+                return None;
+            }
+        } else {
+            match <SignCmdActionT as InterpParser<Json<KadenaCmdSchema>>>::parse(
+                &SIGN_CMD_ACTION,
+                &mut self.cmd_state,
+                block,
+                &mut self.cmd_result,
+            ) {
+                Ok(rest) => {
+                    self.cmd_done = true;
+                    if !rest.iter().all(|b| is_json_whitespace(*b)) {
+                        return None;
                     }
                 }
-                Some(())
-            },
-        ),
-    );
-
-pub type MakeTransferTxImplT =
-    impl InterpParser<MakeTransferTxParameters, Returning = ArrayVec<u8, 128_usize>>;
-
-pub struct MakeTx;
-pub static MAKE_TRANSFER_TX_IMPL: MakeTransferTxImplT = MakeTx;
+                Err((None, [])) => {}
+                Err(_) => return None,
+            }
+        }
+        block.clear();
+        Some(())
+    }
 
-pub enum MakeTxSubState {
-    Init,
-    Path(<PathParserT as ParserCommon<Bip32Key>>::State),
-    RecipientAmount(<RecipientAmountT as ParserCommon<MakeTransferTxParameters1>>::State),
-    MetaNonce(<MetaNonceT as ParserCommon<MakeTransferTxParameters2>>::State),
-    Done,
+    fn finish(&mut self) -> Option<Zeroizing<Hash<32>>> {
+        if !matches!(self.scan, WrapperScan::End) || !self.cmd_done {
+            return None;
+        }
+        let the_hash = self.hasher.finalize();
+        if let Some(expected) = &self.hash_field {
+            let mut computed = ArrayString::<64>::new();
+            write!(computed, "{}", the_hash.deref()).ok()?;
+            if computed.as_bytes() != expected.as_slice() {
+                return reject_with(KadenaStatusWord::HashMismatch);
+            }
+        }
+        if expert_mode() {
+            scroller("Transaction hash", |w| Ok(write!(w, "{}", the_hash.deref())?))?;
+        }
+        Some(the_hash)
+    }
 }
 
-impl ParserCommon<MakeTransferTxParameters> for MakeTx {
-    type State = (Option<HasherAndPrivKey>, MakeTxSubState);
-    type Returning = ArrayVec<u8, 128_usize>;
+impl ParserCommon<WrappedJsonCommand> for WrappedCmd {
+    type State = WrappedCmdState;
+    type Returning = Zeroizing<Hash<32>>;
     fn init(&self) -> Self::State {
-        (None, MakeTxSubState::Init)
+        WrappedCmdState {
+            size_state: <DefaultInterp as ParserCommon<U32<{ Endianness::Little }>>>::init(
+                &DefaultInterp,
+            ),
+            remaining: None,
+            scan: WrapperScan::Start,
+            key: ArrayVec::new(),
+            key_too_long: false,
+            current: WrapperKey::Other,
+            hash_field: None,
+            cmd_seen: false,
+            unescape: Unescape::Char,
+            high_surrogate: None,
+            hasher: Hasher::new(),
+            cmd_state: <SignCmdActionT as ParserCommon<Json<KadenaCmdSchema>>>::init(
+                &SIGN_CMD_ACTION,
+            ),
+            cmd_result: None,
+            cmd_done: false,
+        }
     }
 }
 
-impl InterpParser<MakeTransferTxParameters> for MakeTx {
+impl InterpParser<WrappedJsonCommand> for WrappedCmd {
     #[inline(never)]
     fn parse<'a, 'b>(
         &self,
-        (ref mut hasher_and_privkey, ref mut state): &'b mut Self::State,
+        state: &'b mut Self::State,
         chunk: &'a [u8],
         destination: &mut Option<Self::Returning>,
     ) -> ParseResult<'a> {
         let mut cursor = chunk;
-        loop {
-            match state {
-                MakeTxSubState::Init => {
-                    info!(
-                        "State sizes \nMakeTx: {}\n",
-                        core::mem::size_of::<MakeTxSubState>()
-                    );
-                    init_with_default(destination);
-                    set_from_thunk(state, || {
-                        MakeTxSubState::Path(<PathParserT as ParserCommon<Bip32Key>>::init(
-                            &PATH_PARSER,
-                        ))
-                    })
-                }
-                MakeTxSubState::Path(ref mut sub) => {
-                    cursor = <PathParserT as InterpParser<Bip32Key>>::parse(
-                        &PATH_PARSER,
-                        sub,
-                        cursor,
-                        hasher_and_privkey,
-                    )?;
-                    set_from_thunk(state, || {
-                        MakeTxSubState::RecipientAmount(<RecipientAmountT as ParserCommon<
-                            MakeTransferTxParameters1,
-                        >>::init(
-                            &RECIPIENT_AMOUNT_PARSER
-                        ))
-                    })
-                }
-                MakeTxSubState::RecipientAmount(ref mut sub) => {
-                    cursor = <RecipientAmountT as InterpParser<MakeTransferTxParameters1>>::parse(
-                        &RECIPIENT_AMOUNT_PARSER,
-                        sub,
-                        cursor,
-                        hasher_and_privkey,
-                    )?;
-                    set_from_thunk(state, || {
-                        MakeTxSubState::MetaNonce(<MetaNonceT as ParserCommon<
-                            MakeTransferTxParameters2,
-                        >>::init(
-                            &META_NONCE_PARSER
-                        ))
-                    })
-                }
-                MakeTxSubState::MetaNonce(ref mut sub) => {
-                    cursor = <MetaNonceT as InterpParser<MakeTransferTxParameters2>>::parse(
-                        &META_NONCE_PARSER,
-                        sub,
-                        cursor,
-                        hasher_and_privkey,
-                    )?;
-                    set_from_thunk(state, || MakeTxSubState::Done);
-                }
-                MakeTxSubState::Done => {
-                    match hasher_and_privkey {
-                        Some((ref mut hasher, privkey)) => {
-                            #[allow(clippy::needless_borrow)] // Needed for nanos
-                            final_accept_prompt(&[&"Sign Transaction?"])
-                                .ok_or((Some(OOB::Reject), cursor))?;
-                            *destination = Some(ArrayVec::new());
-
-                            let mut add_sig = || -> Option<()> {
-                                let hash = hasher.finalize();
-                                let sig = eddsa_sign_int(privkey, &hash.0).ok()?;
-                                destination
-                                    .as_mut()?
-                                    .try_extend_from_slice(&sig.0[..])
-                                    .ok()?;
-                                Some(())
-                            };
-                            add_sig().ok_or((Some(OOB::Reject), cursor))?;
+        if state.remaining.is_none() {
+            cursor = <DefaultInterp as InterpParser<U32<{ Endianness::Little }>>>::parse(
+                &DefaultInterp,
+                &mut state.size_state,
+                cursor,
+                &mut state.remaining,
+            )?;
+        }
+        let remaining = state.remaining.ok_or((Some(OOB::Reject), cursor))?;
+        let len = core::cmp::min(remaining as usize, cursor.len());
+        let (wrapper, rest) = cursor.split_at(len);
+        state.remaining = Some(remaining - len as u32);
+
+        let mut block = UnescapedBlock::new();
+        for b in wrapper {
+            state.scan_byte(*b, &mut block).ok_or((Some(OOB::Reject), rest))?;
+        }
+        if let WrapperScan::CmdString = state.scan {
+            state.flush_cmd(&mut block).ok_or((Some(OOB::Reject), rest))?;
+        }
 
-                            with_public_keys_int(privkey, |key: &_, _: &PKH| {
-                                try_option(|| -> Option<()> {
-                                    let key_x = ed25519_public_key_bytes(key);
-                                    destination.as_mut()?.try_extend_from_slice(key_x).ok()
-                                }())
-                            })
-                            .or(Err((Some(OOB::Reject), cursor)))?;
-                            break Ok(cursor);
-                        }
-                        _ => {
-                            panic!("should have been set")
-                        }
-                    }
-                }
-            }
+        if state.remaining != Some(0) {
+            return Err((None, rest));
         }
+        *destination = Some(state.finish().ok_or((Some(OOB::Reject), rest))?);
+        Ok(rest)
     }
 }
 
@@ -1190,11 +3252,15 @@ impl InterpParser<MakeTransferTxParameters> for MakeTx {
 #[allow(clippy::large_enum_variant)]
 pub enum ParsersState {
     NoState,
-    SettingsState(u8),
     GetAddressState(<GetAddressImplT as ParserCommon<Bip32Key>>::State),
+    VerifyAddressState(<VerifyAddressImplT as ParserCommon<VerifyAddressParameters>>::State),
     SignState(<SignImplT as ParserCommon<SignParameters>>::State),
     SignHashState(<SignHashImplT as ParserCommon<SignHashParameters>>::State),
+    SignMessageState(<SignMessageImplT as ParserCommon<SignMessageParameters>>::State),
+    SignHashWithTxState(<SignHashWithTxImplT as ParserCommon<SignParameters>>::State),
     MakeTransferTxState(<MakeTransferTxImplT as ParserCommon<MakeTransferTxParameters>>::State),
+    SignBatchState(<SignBatchImplT as ParserCommon<SignBatchParameters>>::State),
+    SignWrappedState(<SignWrappedImplT as ParserCommon<SignWrappedParameters>>::State),
 }
 
 pub fn reset_parsers_state(state: &mut ParsersState) {
@@ -1206,6 +3272,7 @@ kadena_capability_definition! {}
 signer_definition! {}
 payload_definition! {}
 command_definition! {}
+verifier_definition! {}
 kadena_cmd_definition! {}
 
 #[inline(never)]
@@ -1229,6 +3296,27 @@ pub fn get_get_address_state(
     }
 }
 
+#[inline(never)]
+pub fn get_verify_address_state(
+    s: &mut ParsersState,
+) -> &mut <VerifyAddressImplT as ParserCommon<VerifyAddressParameters>>::State {
+    match s {
+        ParsersState::VerifyAddressState(_) => {}
+        _ => {
+            info!("Non-same state found; initializing state.");
+            *s = ParsersState::VerifyAddressState(<VerifyAddressImplT as ParserCommon<
+                VerifyAddressParameters,
+            >>::init(&VERIFY_ADDRESS_IMPL));
+        }
+    }
+    match s {
+        ParsersState::VerifyAddressState(ref mut a) => a,
+        _ => {
+            panic!("")
+        }
+    }
+}
+
 #[inline(never)]
 pub fn get_sign_state(
     s: &mut ParsersState,
@@ -1271,6 +3359,50 @@ pub fn get_sign_hash_state(
     }
 }
 
+#[inline(never)]
+pub fn get_sign_message_state(
+    s: &mut ParsersState,
+) -> &mut <SignMessageImplT as ParserCommon<SignMessageParameters>>::State {
+    match s {
+        ParsersState::SignMessageState(_) => {}
+        _ => {
+            info!("Non-same state found; initializing state.");
+            *s = ParsersState::SignMessageState(<SignMessageImplT as ParserCommon<
+                SignMessageParameters,
+            >>::init(&SIGN_MESSAGE_IMPL));
+        }
+    }
+    match s {
+        ParsersState::SignMessageState(ref mut a) => a,
+        _ => {
+            panic!("")
+        }
+    }
+}
+
+#[inline(never)]
+pub fn get_sign_hash_with_tx_state(
+    s: &mut ParsersState,
+) -> &mut <SignHashWithTxImplT as ParserCommon<SignParameters>>::State {
+    match s {
+        ParsersState::SignHashWithTxState(_) => {}
+        _ => {
+            info!("Non-same state found; initializing state.");
+            *s = ParsersState::SignHashWithTxState(
+                <SignHashWithTxImplT as ParserCommon<SignParameters>>::init(
+                    &SIGN_HASH_WITH_TX_IMPL,
+                ),
+            );
+        }
+    }
+    match s {
+        ParsersState::SignHashWithTxState(ref mut a) => a,
+        _ => {
+            panic!("")
+        }
+    }
+}
+
 #[inline(never)]
 pub fn get_make_transfer_tx_state(
     s: &mut ParsersState,
@@ -1291,3 +3423,45 @@ pub fn get_make_transfer_tx_state(
         }
     }
 }
+
+#[inline(never)]
+pub fn get_sign_batch_state(
+    s: &mut ParsersState,
+) -> &mut <SignBatchImplT as ParserCommon<SignBatchParameters>>::State {
+    match s {
+        ParsersState::SignBatchState(_) => {}
+        _ => {
+            info!("Non-same state found; initializing state.");
+            *s = ParsersState::SignBatchState(
+                <SignBatchImplT as ParserCommon<SignBatchParameters>>::init(&SIGN_BATCH_IMPL),
+            );
+        }
+    }
+    match s {
+        ParsersState::SignBatchState(ref mut a) => a,
+        _ => {
+            panic!("")
+        }
+    }
+}
+
+#[inline(never)]
+pub fn get_sign_wrapped_state(
+    s: &mut ParsersState,
+) -> &mut <SignWrappedImplT as ParserCommon<SignWrappedParameters>>::State {
+    match s {
+        ParsersState::SignWrappedState(_) => {}
+        _ => {
+            info!("Non-same state found; initializing state.");
+            *s = ParsersState::SignWrappedState(
+                <SignWrappedImplT as ParserCommon<SignWrappedParameters>>::init(&SIGN_WRAPPED_IMPL),
+            );
+        }
+    }
+    match s {
+        ParsersState::SignWrappedState(ref mut a) => a,
+        _ => {
+            panic!("")
+        }
+    }
+}
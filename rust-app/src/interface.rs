@@ -36,22 +36,66 @@ define_json_struct! { Payload 5 {
     exec: CommandSchema
 }}
 
+// Pact 5 verifier plugins, like Hyperlane, which grant capabilities based on a proof
+define_json_struct! { Verifier 5 {
+    name: JsonString,
+    proof: JsonString,
+    clist: Alt<JsonNull,JsonArray<KadenaCapabilitySchema>>
+}}
+
 define_json_struct! { KadenaCmd 16 {
   nonce: JsonString,
   meta: Alt<MetaSchema, JsonAny>,
   signers: JsonArray<SignerSchema>,
   payload: PayloadSchema,
-  networkId: Alt<JsonString,JsonNull>
+  networkId: Alt<JsonString,JsonNull>,
+  verifiers: JsonArray<VerifierSchema>
 }}
 
+// A command in JSON, preceded by its length
+pub type JsonCommand = LengthFallback<U32<{ Endianness::Little }>, Json<KadenaCmdSchema>>;
+
 // Payload for a signature request, content-agnostic.
-pub type SignParameters = (
-    LengthFallback<U32<{ Endianness::Little }>, Json<KadenaCmdSchema>>,
-    Bip32Key,
-);
+pub type SignParameters = (JsonCommand, Bip32Key);
+
+// A command as wrapped by the Kadena tooling, `{"hash": ..., "sigs": ..., "cmd": ...}`, in which
+// `cmd` is the command JSON encoded as a string; preceded by the length of the whole wrapper.
+pub struct WrappedJsonCommand;
+
+// Payload for a signature request of a wrapped command.
+pub type SignWrappedParameters = (WrappedJsonCommand, Bip32Key);
+
+// The most commands of a batch; larger batches are refused with `InvalidBatchSize`. The Nano S
+// has no RAM to spare for more than fit in a single response.
+#[cfg(target_os = "nanos")]
+pub const MAX_BATCH_SIZE: usize = 3;
+#[cfg(not(target_os = "nanos"))]
+pub const MAX_BATCH_SIZE: usize = 32;
+
+// The signatures a response has room for; those of the commands after the first ones of a batch
+// are fetched with GET_BATCH_SIGNATURES.
+pub const BATCH_SIGNATURES_PER_REPLY: usize = 3;
+
+// Payload for a batch signature request: the number of commands, the commands, and the key to
+// sign all of them with.
+pub type SignBatchParameters = (DArray<Byte, JsonCommand, MAX_BATCH_SIZE>, Bip32Key);
 
 pub type SignHashParameters = (Array<Byte, 32>, Bip32Key);
 
+// The longest message SIGN_MESSAGE accepts; its length is a single byte
+pub const MAX_MESSAGE_SIZE: usize = 255;
+
+// Payload for a message signature request: the UTF-8 text of the message, preceded by its
+// length, and the key to sign it with.
+pub type SignMessageParameters = (ByteDArray<MAX_MESSAGE_SIZE>, Bip32Key);
+
+// Payload for an address verification: the key, then the chain and the network to show with
+// its account, each of them empty if not given.
+pub type VerifyAddressParameters = (
+    Bip32Key,
+    (ByteDArray<PARAM_CHAIN_SIZE>, ByteDArray<PARAM_NETWORK_SIZE>),
+);
+
 pub type ByteDArray<const N: usize> = DArray<Byte, Byte, N>;
 
 pub const PARAM_AMOUNT_SIZE: usize = 32;
@@ -107,3 +151,28 @@ pub type MakeTransferTxParameters2 = (
         ),
     ),
 );
+
+// Status words specific to this app; the generic ones come from the SDK.
+#[repr(u16)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KadenaStatusWord {
+    BlindSigningRequired = 0x6808,
+    HashMismatch = 0x6A80,
+    DuplicateKey = 0x6A81,
+    UnknownNetwork = 0x6A82,
+    InvalidChain = 0x6A83,
+    UnscopedSigner = 0x6A84,
+    InvalidDescriptor = 0x6A85,
+    UntrustedDescriptor = 0x6A86,
+    InvalidAddress = 0x6A87,
+    AddressBookFull = 0x6A88,
+    UnknownAddress = 0x6A89,
+    RecipientNotAllowed = 0x6A8A,
+    OverSpendingLimit = 0x6A8B,
+    AddressRejected = 0x6A8C,
+    UserCancelled = 0x6A8D,
+    InvalidBatchSize = 0x6A8E,
+    // The generic error of the SDK, which declined prompts have always been answered with; it is
+    // only named to tell a decline apart from a value which could not be parsed.
+    UserRejected = 0x6D00,
+}
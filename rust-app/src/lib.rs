@@ -20,13 +20,25 @@ extern "C" fn sample_main() {
     exit_app(0);
 }
 
+pub mod decimal;
+pub mod display;
 pub mod interface;
+pub mod pact;
 
+#[cfg(all(target_family = "bolos"))]
+pub mod accounts;
+#[cfg(all(target_family = "bolos"))]
+pub mod address_book;
+#[cfg(all(target_family = "bolos"))]
+pub mod capabilities;
 #[cfg(all(target_family = "bolos"))]
 pub mod implementation;
 #[cfg(all(target_family = "bolos"))]
 pub mod settings;
 
+#[cfg(all(target_family = "bolos"))]
+pub mod menu;
+
 #[cfg(all(target_family = "bolos"))]
 pub mod main_nanos;
 
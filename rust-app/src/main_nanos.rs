@@ -1,5 +1,8 @@
+use crate::address_book::{add_address, delete_address, list_addresses};
+use crate::capabilities::add_descriptor;
 use crate::implementation::*;
 use crate::interface::*;
+use crate::menu::*;
 use crate::settings::*;
 
 use core::fmt::Write;
@@ -7,16 +10,91 @@ use ledger_log::{info, trace};
 use ledger_parser_combinators::interp_parser::{set_from_thunk, OOB};
 use ledger_prompts_ui::write_scroller;
 
-use nanos_sdk::buttons::ButtonEvent;
 use nanos_sdk::io;
-use nanos_ui::ui::SingleMessage;
+
+static ROOT_MENU: [MenuEntry; 4] = [
+    MenuEntry::Indicator {
+        active: Settings::blind_signing_allowed,
+        on: "Blind Signing ON",
+        off: concat!("Kadena ", env!("CARGO_PKG_VERSION")),
+    },
+    MenuEntry::Submenu("Settings", &SETTINGS_MENU),
+    MenuEntry::Submenu("About", &ABOUT_MENU),
+    MenuEntry::Event("Quit", MenuEvent::Quit),
+];
+
+static SETTINGS_MENU: [MenuEntry; 9] = [
+    MenuEntry::Toggle {
+        flag: SettingFlag::BlindSigning,
+        enable: "Enable Blind Signing",
+        disable: "Disable Blind Signing",
+    },
+    MenuEntry::Toggle {
+        flag: SettingFlag::BlindSigningSession,
+        enable: "Start Blind Session",
+        disable: "End Blind Session",
+    },
+    MenuEntry::Toggle {
+        flag: SettingFlag::ExpertMode,
+        enable: "Enable Expert Mode",
+        disable: "Disable Expert Mode",
+    },
+    MenuEntry::Toggle {
+        flag: SettingFlag::StrictNetworks,
+        enable: "Enable Strict Network",
+        disable: "Disable Strict Network",
+    },
+    MenuEntry::Toggle {
+        flag: SettingFlag::AllowUnscoped,
+        enable: "Allow Unscoped Sigs",
+        disable: "Forbid Unscoped Sigs",
+    },
+    MenuEntry::Toggle {
+        flag: SettingFlag::KnownRecipientsOnly,
+        enable: "Only Known Recipients",
+        disable: "Allow Any Recipient",
+    },
+    MenuEntry::Toggle {
+        flag: SettingFlag::RefuseOverLimit,
+        enable: "Refuse Over Limit",
+        disable: "Confirm Over Limit",
+    },
+    MenuEntry::Submenu("Warning Limits", &THRESHOLDS_MENU),
+    MenuEntry::Back,
+];
+
+static THRESHOLDS_MENU: [MenuEntry; 5] = [
+    MenuEntry::Threshold(Threshold::GasPrice),
+    MenuEntry::Threshold(Threshold::GasLimit),
+    MenuEntry::Threshold(Threshold::Ttl),
+    MenuEntry::Threshold(Threshold::SpendingLimit),
+    MenuEntry::Back,
+];
+
+static ABOUT_MENU: [MenuEntry; 3] = [
+    MenuEntry::Label(concat!("Version ", env!("CARGO_PKG_VERSION"))),
+    MenuEntry::Label(concat!("Commit ", env!("GIT_COMMIT"))),
+    MenuEntry::Back,
+];
+
+// Shown while a request is being processed
+static BUSY_MENU: [MenuEntry; 2] = [
+    MenuEntry::Status,
+    MenuEntry::Event("Cancel", MenuEvent::Cancel),
+];
 
 #[allow(dead_code)]
 pub fn app_main() {
     let mut comm = io::Comm::new();
     let mut states = ParsersState::NoState;
-    let mut menu = Menu::new(&[]);
+    let mut menu = MenuState::new(&ROOT_MENU);
     let mut settings = Settings::new();
+    let mut progress: Option<Progress> = None;
+    // A request the user cancelled; its next APDU is then rejected. An APDU starting a new
+    // request means the host gave up on the cancelled one.
+    let mut cancelled: Option<Cancelled> = None;
+    // The instruction of the last request, which the busy menu can cancel
+    let mut current: Option<Ins> = None;
 
     info!("Kadena app {}", env!("CARGO_PKG_VERSION"));
     info!(
@@ -25,83 +103,72 @@ pub fn app_main() {
         core::mem::size_of::<ParsersState>()
     );
 
-    idle_menu(&mut menu);
+    menu.show(&settings);
     loop {
         info!("Fetching next event.");
         // Wait for either a specific button push to exit the app
         // or an APDU command
         match comm.next_event::<Ins>() {
             io::Event::Command(ins) => {
+                let data = comm.get_data().unwrap_or(&[]);
+                if core::mem::take(&mut cancelled).map_or(false, |c| c.continued_by(ins, data)) {
+                    info!("Rejecting request cancelled by the user");
+                    comm.reply(KadenaStatusWord::UserCancelled);
+                    continue;
+                }
+                current = Some(ins);
+                if ins != Ins::GetBatchSignatures {
+                    clear_batch_signatures();
+                }
                 if let ParsersState::NoState = states {
-                    menu.reset()
+                    progress = None;
+                    menu.open(&BUSY_MENU)
                 };
-                {
-                    // Using arr is important here. `menu.show(&[ ... ])` doesn't work
-                    let arr = ["Working...", "Cancel"];
-                    menu.show(&arr);
+                if let Ins::Sign | Ins::SignHashWithTx | Ins::SignWrapped = ins {
+                    if let Ok(data) = comm.get_data() {
+                        update_progress(&mut progress, data);
+                    }
                 }
+                match &progress {
+                    Some(p) => menu.set_status(format_args!("Working... {}%", p.percent())),
+                    None => menu.set_status(format_args!("Working...")),
+                }
+                menu.show(&settings);
                 match handle_apdu(&mut comm, ins, &mut states, &mut settings) {
                     Ok(()) => comm.reply_ok(),
                     Err(sw) => comm.reply(sw),
                 }
                 if let ParsersState::NoState = states {
                     menu.reset();
-                    idle_menu(&mut menu)
+                    menu.show(&settings);
                 };
             }
-            io::Event::Button(btn) => match menu.update(btn) {
-                Some(0) =>
-                // be consistent others below, state machine
-                {
-                    #[allow(clippy::single_match)]
-                    match states {
-                        ParsersState::SettingsState(v) => {
-                            let new = match v {
-                                0 => 1,
-                                _ => 0,
-                            };
-                            settings.set(&new);
-                            set_from_thunk(&mut states, || ParsersState::SettingsState(new));
-                            settings_menu(&mut menu, new);
-                        }
-                        _ => {}
-                    }
-                }
-                Some(1) => match states {
-                    ParsersState::NoState => {
-                        let v = settings.get();
-                        set_from_thunk(&mut states, || ParsersState::SettingsState(v));
-                        menu.reset();
-                        settings_menu(&mut menu, v);
-                    }
-                    ParsersState::SettingsState(_) => {
-                        set_from_thunk(&mut states, || ParsersState::NoState);
-                        menu.reset();
-                        idle_menu(&mut menu);
-                    }
-                    _ => {
-                        info!("Resetting at user direction via busy menu");
-                        set_from_thunk(&mut states, || ParsersState::NoState);
-                        menu.reset();
-                        idle_menu(&mut menu);
-                    }
-                },
-                Some(2) => {
+            io::Event::Button(btn) => match menu.update(btn, &mut settings) {
+                Some(MenuEvent::Quit) => {
                     info!("Exiting app at user direction via root menu");
                     nanos_sdk::exit_app(0)
                 }
-                _ => match states {
-                    ParsersState::SettingsState(v) => {
-                        settings_menu(&mut menu, v);
-                    }
-                    ParsersState::NoState => {
-                        idle_menu(&mut menu);
-                    }
-                    _ => {}
-                },
+                Some(MenuEvent::Cancel) => {
+                    info!("Resetting at user direction via busy menu");
+                    set_from_thunk(&mut states, || ParsersState::NoState);
+                    cancelled = current.map(|ins| Cancelled {
+                        ins,
+                        left: progress
+                            .as_ref()
+                            .map(|p| p.total.saturating_sub(p.received)),
+                    });
+                    menu.reset();
+                    menu.show(&settings);
+                }
+                None => {}
             },
             io::Event::Ticker => {
-                trace!("Ignoring ticker event");
+                if settings.tick() {
+                    info!("Blind signing session timed out");
+                    if let ParsersState::NoState = states {
+                        menu.show(&settings);
+                    }
+                }
             }
         }
 
@@ -109,39 +176,84 @@ pub fn app_main() {
     }
 }
 
-#[inline(never)]
-fn idle_menu(menu: &mut Menu) {
-    let arr: [&str; 3] = [
-        concat!("Kadena ", env!("CARGO_PKG_VERSION")),
-        "Blind Signing",
-        "Quit",
-    ];
-    menu.show(&arr);
+// How much of a transaction sent with its length, as for SIGN_JSON_TX, has been received so far
+struct Progress {
+    received: u32,
+    total: u32,
 }
 
-#[inline(never)]
-fn settings_menu(menu: &mut Menu, v: u8) {
-    match v {
-        0 => {
-            // Using arr is important here. `menu.show(&[ ... ])` doesn't work
-            let arr = ["Enable Blind Signing", "Back"];
-            menu.show(&arr);
-        }
-        1 => {
-            let arr = ["Disable Blind Signing", "Back"];
-            menu.show(&arr);
+impl Progress {
+    fn percent(&self) -> u64 {
+        core::cmp::min(
+            100,
+            u64::from(self.received) * 100 / core::cmp::max(1, u64::from(self.total)),
+        )
+    }
+}
+
+// The first chunk starts with the little endian `tx_size` of the `LengthFallback` header
+fn update_progress(progress: &mut Option<Progress>, data: &[u8]) {
+    match progress {
+        Some(p) => p.received = p.received.saturating_add(data.len() as u32),
+        None => {
+            if let Some(size) = data.get(0..4) {
+                *progress = Some(Progress {
+                    received: (data.len() - 4) as u32,
+                    total: u32::from_le_bytes([size[0], size[1], size[2], size[3]]),
+                });
+            }
         }
-        _ => {}
+    }
+}
+
+// The most bytes a derivation path takes: its length, then up to 10 steps
+const MAX_PATH_SIZE: usize = 1 + 4 * 10;
+
+struct Cancelled {
+    ins: Ins,
+    // The bytes of its transaction not received yet, if it was sent with its length
+    left: Option<u32>,
+}
+
+impl Cancelled {
+    // Whether an APDU is the next one of the cancelled request rather than the first of a new
+    // request with the same instruction. The latter starts with its `tx_size`, whose high byte
+    // is 0, then the `{` of the transaction; the rest of a transaction is JSON text, which has no
+    // 0 byte, and past the transaction only the derivation path is left.
+    fn continued_by(&self, ins: Ins, data: &[u8]) -> bool {
+        let starts_transaction = matches!(data, [_, _, _, 0, b'{', ..]);
+        ins == self.ins
+            && match self.left {
+                Some(0) => !starts_transaction && data.len() <= MAX_PATH_SIZE,
+                Some(_) => !starts_transaction,
+                None => true,
+            }
+    }
+}
+
+impl From<KadenaStatusWord> for Reply {
+    fn from(sw: KadenaStatusWord) -> Reply {
+        Reply(sw as u16)
     }
 }
 
 #[repr(u8)]
-#[derive(Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum Ins {
     GetVersion,
     GetPubkey,
     Sign,
     SignHash,
+    SignHashWithTx,
+    SignBatch,
+    GetBatchSignatures,
+    SignWrapped,
+    ProvideCapDescriptor,
+    AddAddress,
+    ListAddresses,
+    DeleteAddress,
+    SignMessage,
+    VerifyAddress,
     MakeTransferTx,
     GetVersionStr,
     Exit,
@@ -154,6 +266,16 @@ impl From<u8> for Ins {
             2 => Ins::GetPubkey,
             3 => Ins::Sign,
             4 => Ins::SignHash,
+            5 => Ins::SignHashWithTx,
+            6 => Ins::SignBatch,
+            7 => Ins::SignWrapped,
+            8 => Ins::ProvideCapDescriptor,
+            9 => Ins::AddAddress,
+            0x0a => Ins::ListAddresses,
+            0x0b => Ins::DeleteAddress,
+            0x0c => Ins::SignMessage,
+            0x0d => Ins::VerifyAddress,
+            0x0e => Ins::GetBatchSignatures,
             0x10 => Ins::MakeTransferTx,
             0xfe => Ins::GetVersionStr,
             0xff => Ins::Exit,
@@ -162,17 +284,21 @@ impl From<u8> for Ins {
     }
 }
 
-use arrayvec::ArrayVec;
 use nanos_sdk::io::Reply;
 
 use ledger_parser_combinators::interp_parser::{InterpParser, ParserCommon};
-fn run_parser_apdu<P: InterpParser<A, Returning = ArrayVec<u8, 128>>, A>(
+fn run_parser_apdu<P: InterpParser<A>, A>(
     states: &mut ParsersState,
     get_state: fn(&mut ParsersState) -> &mut <P as ParserCommon<A>>::State,
     parser: &P,
     comm: &mut io::Comm,
-) -> Result<(), Reply> {
+) -> Result<(), Reply>
+where
+    <P as ParserCommon<A>>::Returning: AsRef<[u8]>,
+{
     let cursor = comm.get_data()?;
+    // Forget a reason left over from an input which was rejected without one
+    let _ = take_reject_reason();
 
     trace!("Parsing APDU input: {:?}\n", cursor);
     let mut parse_destination = None;
@@ -183,7 +309,10 @@ fn run_parser_apdu<P: InterpParser<A, Returning = ArrayVec<u8, 128>>, A>(
         // Explicit rejection; reset the parser. Possibly send error message to host?
         Err((Some(OOB::Reject), _)) => {
             reset_parsers_state(states);
-            Err(io::StatusWords::Unknown.into())
+            match take_reject_reason() {
+                Some(sw) => Err(sw.into()),
+                None => Err(io::StatusWords::Unknown.into()),
+            }
         }
         // Deliberately no catch-all on the Err((Some case; we'll get error messages if we
         // add to OOB's out-of-band actions and forget to implement them.
@@ -202,7 +331,7 @@ fn run_parser_apdu<P: InterpParser<A, Returning = ArrayVec<u8, 128>>, A>(
         Ok([]) => {
             trace!("Parser finished, resetting state\n");
             match parse_destination.as_ref() {
-                Some(rv) => comm.append(&rv[..]),
+                Some(rv) => comm.append(rv.as_ref()),
                 None => return Err(io::StatusWords::Unknown.into()),
             }
             // Parse finished; reset.
@@ -245,7 +374,7 @@ fn handle_apdu(
             run_parser_apdu::<_, SignParameters>(parser, get_sign_state, &SIGN_IMPL, comm)?
         }
         Ins::SignHash => {
-            if settings.get() != 1 {
+            if !settings.blind_signing_allowed() {
                 write_scroller(false, "Blind Signing must", |w| {
                     Ok(write!(w, "be enabled")?)
                 });
@@ -259,6 +388,47 @@ fn handle_apdu(
                 )?
             }
         }
+        Ins::SignHashWithTx => run_parser_apdu::<_, SignParameters>(
+            parser,
+            get_sign_hash_with_tx_state,
+            &SIGN_HASH_WITH_TX_IMPL,
+            comm,
+        )?,
+        Ins::SignBatch => run_parser_apdu::<_, SignBatchParameters>(
+            parser,
+            get_sign_batch_state,
+            &SIGN_BATCH_IMPL,
+            comm,
+        )?,
+        Ins::GetBatchSignatures => {
+            let reply = get_batch_signatures(comm.get_data()?)?;
+            comm.append(&reply);
+        }
+        Ins::SignWrapped => run_parser_apdu::<_, SignWrappedParameters>(
+            parser,
+            get_sign_wrapped_state,
+            &SIGN_WRAPPED_IMPL,
+            comm,
+        )?,
+        Ins::ProvideCapDescriptor => add_descriptor(comm.get_data()?)?,
+        Ins::AddAddress => add_address(comm.get_data()?)?,
+        Ins::ListAddresses => {
+            let reply = list_addresses(comm.get_data()?)?;
+            comm.append(&reply);
+        }
+        Ins::DeleteAddress => delete_address(comm.get_data()?)?,
+        Ins::SignMessage => run_parser_apdu::<_, SignMessageParameters>(
+            parser,
+            get_sign_message_state,
+            &SIGN_MESSAGE_IMPL,
+            comm,
+        )?,
+        Ins::VerifyAddress => run_parser_apdu::<_, VerifyAddressParameters>(
+            parser,
+            get_verify_address_state,
+            &VERIFY_ADDRESS_IMPL,
+            comm,
+        )?,
         Ins::MakeTransferTx => run_parser_apdu::<_, MakeTransferTxParameters>(
             parser,
             get_make_transfer_tx_state,
@@ -272,43 +442,3 @@ fn handle_apdu(
     }
     Ok(())
 }
-
-pub struct Menu {
-    screens_len: usize,
-    state: usize,
-}
-
-impl Menu {
-    pub fn new(init_screens: &[&str]) -> Menu {
-        Menu {
-            screens_len: init_screens.len(),
-            state: 0,
-        }
-    }
-
-    pub fn reset(&mut self) {
-        self.state = 0;
-    }
-
-    #[inline(never)]
-    pub fn show(&mut self, screens: &[&str]) {
-        self.screens_len = screens.len();
-        self.state = core::cmp::min(self.state, (self.screens_len) - 1);
-        SingleMessage::new(screens[self.state]).show();
-    }
-
-    #[inline(never)]
-    pub fn update(&mut self, btn: ButtonEvent) -> Option<usize> {
-        match btn {
-            ButtonEvent::LeftButtonRelease => {
-                self.state = if self.state > 0 { self.state - 1 } else { 0 }
-            }
-            ButtonEvent::RightButtonRelease => {
-                self.state = core::cmp::min(self.state + 1, (self.screens_len) - 1)
-            }
-            ButtonEvent::BothButtonsRelease => return Some(self.state),
-            _ => (),
-        }
-        None
-    }
-}
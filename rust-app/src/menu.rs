@@ -0,0 +1,160 @@
+use crate::settings::*;
+
+use arrayvec::{ArrayString, ArrayVec};
+use core::fmt::Write;
+use nanos_sdk::buttons::ButtonEvent;
+use nanos_ui::ui::SingleMessage;
+
+/// Events which the menu can not handle by itself, and are passed back to the app.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MenuEvent {
+    Quit,
+    Cancel,
+}
+
+/// One screen of a menu page.
+///
+/// A page is a `&'static [MenuEntry]`; the whole menu tree is declared as statics, and
+/// the text of toggles is derived from the current `Settings`.
+pub enum MenuEntry {
+    /// Informational screen; selecting it does nothing.
+    Label(&'static str),
+    /// Like `Label`, but showing the text set with `MenuState::set_status`.
+    Status,
+    /// Like `Label`, showing `on` instead of `off` while `active` holds.
+    Indicator {
+        active: fn(&Settings) -> bool,
+        on: &'static str,
+        off: &'static str,
+    },
+    /// Opens the given page; "Back" on that page returns here.
+    Submenu(&'static str, &'static [MenuEntry]),
+    /// Flips a setting, showing the action the user would take.
+    Toggle {
+        flag: SettingFlag,
+        enable: &'static str,
+        disable: &'static str,
+    },
+    /// Shows the current preset of a threshold; selecting it switches to the next one.
+    Threshold(Threshold),
+    /// Returns to the first screen of the parent page.
+    Back,
+    /// Passes the event to the app.
+    Event(&'static str, MenuEvent),
+}
+
+impl MenuEntry {
+    fn label(&self, settings: &Settings) -> &'static str {
+        match self {
+            MenuEntry::Label(l) => l,
+            MenuEntry::Status => "",
+            MenuEntry::Indicator { active, on, off } => {
+                if active(settings) {
+                    on
+                } else {
+                    off
+                }
+            }
+            MenuEntry::Submenu(l, _) => l,
+            MenuEntry::Toggle {
+                flag,
+                enable,
+                disable,
+            } => {
+                if settings.is_enabled(*flag) {
+                    disable
+                } else {
+                    enable
+                }
+            }
+            MenuEntry::Threshold(t) => settings.threshold_label(*t),
+            MenuEntry::Back => "Back",
+            MenuEntry::Event(l, _) => l,
+        }
+    }
+}
+
+const MENU_DEPTH: usize = 4;
+const STATUS_SIZE: usize = 20;
+
+pub struct MenuState {
+    root: &'static [MenuEntry],
+    page: &'static [MenuEntry],
+    cursor: usize,
+    // The pages above the current one, for "Back"
+    parents: ArrayVec<&'static [MenuEntry], MENU_DEPTH>,
+    status: ArrayString<STATUS_SIZE>,
+}
+
+impl MenuState {
+    pub fn new(root: &'static [MenuEntry]) -> MenuState {
+        MenuState {
+            root,
+            page: root,
+            cursor: 0,
+            parents: ArrayVec::new(),
+            status: ArrayString::new(),
+        }
+    }
+
+    /// Go back to the first screen of the root page.
+    pub fn reset(&mut self) {
+        self.open(self.root);
+    }
+
+    /// Show a page which is not part of the tree, like the busy screen.
+    pub fn open(&mut self, page: &'static [MenuEntry]) {
+        self.page = page;
+        self.cursor = 0;
+        self.parents.clear();
+    }
+
+    /// Set the text of the `Status` entries; it is truncated if too long.
+    pub fn set_status(&mut self, args: core::fmt::Arguments) {
+        self.status.clear();
+        let _ = self.status.write_fmt(args);
+    }
+
+    #[inline(never)]
+    pub fn show(&self, settings: &Settings) {
+        match self.page[self.cursor] {
+            MenuEntry::Status => SingleMessage::new(&self.status).show(),
+            ref entry => SingleMessage::new(entry.label(settings)).show(),
+        }
+    }
+
+    /// Handle a button press, and redraw the menu.
+    #[inline(never)]
+    pub fn update(&mut self, btn: ButtonEvent, settings: &mut Settings) -> Option<MenuEvent> {
+        let mut event = None;
+        match btn {
+            ButtonEvent::LeftButtonRelease => {
+                self.cursor = self.cursor.saturating_sub(1);
+            }
+            ButtonEvent::RightButtonRelease => {
+                self.cursor = core::cmp::min(self.cursor + 1, self.page.len() - 1);
+            }
+            ButtonEvent::BothButtonsRelease => match &self.page[self.cursor] {
+                MenuEntry::Label(_) | MenuEntry::Status | MenuEntry::Indicator { .. } => {}
+                MenuEntry::Submenu(_, sub) => {
+                    if self.parents.try_push(self.page).is_ok() {
+                        self.page = sub;
+                        self.cursor = 0;
+                    }
+                }
+                MenuEntry::Toggle { flag, .. } => settings.toggle(*flag),
+                MenuEntry::Threshold(t) => settings.cycle_threshold(*t),
+                MenuEntry::Back => {
+                    if let Some(page) = self.parents.pop() {
+                        self.page = page;
+                        self.cursor = 0;
+                    }
+                }
+                MenuEntry::Event(_, e) => event = Some(*e),
+            },
+            _ => {}
+        }
+        self.show(settings);
+        event
+    }
+}
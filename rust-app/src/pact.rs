@@ -0,0 +1,403 @@
+//! Pact values, as encoded in the JSON of capability args.
+//!
+//! Pact has more types than JSON, and writes the others as objects: `{"decimal": "1.5"}`,
+//! `{"int": 2}`, `{"time": "2021-10-12T03:27:53Z"}`, keysets, guards and module references.
+//! These are shown the way Pact itself writes them; anything else is shown as its JSON text.
+//!
+//! The text is the compact JSON rendered by the parser, without whitespace, and only valid
+//! values reach these functions; malformed ones are shown as they are.
+
+use crate::display::Sanitized;
+use arrayvec::ArrayVec;
+use core::fmt;
+
+// The most members of the objects Pact writes its values as
+const MAX_MEMBERS: usize = 3;
+
+/// A Pact value, borrowing its parts from the JSON text.
+pub enum PactValue<'a> {
+    Decimal(&'a str),
+    Integer(&'a str),
+    Time(&'a str),
+    KeySet { keys: &'a str, pred: &'a str },
+    KeySetRef(&'a str),
+    UserGuard { fun: &'a str, args: &'a str },
+    ModuleGuard { module: &'a str, name: &'a str },
+    PactGuard { pact_id: &'a str, name: &'a str },
+    CapabilityGuard { name: &'a str, args: &'a str, pact_id: &'a str },
+    ModuleRef { name: &'a str, spec: &'a str },
+    /// Strings, numbers, booleans, and other arrays and objects, as JSON
+    Json(&'a str),
+}
+
+impl<'a> PactValue<'a> {
+    pub fn parse(text: &'a str) -> PactValue<'a> {
+        parse_typed(text).unwrap_or(PactValue::Json(text))
+    }
+}
+
+fn parse_typed(text: &str) -> Option<PactValue> {
+    let members = members(text)?;
+    let field = |key: &str| members.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+    let keys = |expected: &[&str]| {
+        members.len() == expected.len() && expected.iter().all(|k| field(k).is_some())
+    };
+    Some(if keys(&["decimal"]) {
+        PactValue::Decimal(decimal(as_string(field("decimal")?)?)?)
+    } else if keys(&["int"]) {
+        // Pact only writes integers as strings when JSON numbers cannot hold them exactly
+        let int = field("int")?;
+        PactValue::Integer(match as_string(int) {
+            Some(big) => unsafe_integer(big)?,
+            None => integer(int)?,
+        })
+    } else if keys(&["time"]) || keys(&["timep"]) {
+        PactValue::Time(as_string(field("time").or_else(|| field("timep"))?)?)
+    } else if keys(&["keys", "pred"]) {
+        let keys = field("keys")?;
+        elements(keys)?.try_for_each(|k| as_string(k).map(|_| ()))?;
+        PactValue::KeySet {
+            keys,
+            pred: as_string(field("pred")?)?,
+        }
+    } else if keys(&["keysetref"]) {
+        PactValue::KeySetRef(field("keysetref")?)
+    } else if keys(&["fun", "args"]) {
+        PactValue::UserGuard {
+            fun: as_string(field("fun")?)?,
+            args: field("args")?,
+        }
+    } else if keys(&["moduleName", "name"]) {
+        PactValue::ModuleGuard {
+            module: field("moduleName")?,
+            name: as_string(field("name")?)?,
+        }
+    } else if keys(&["pactId", "name"]) {
+        PactValue::PactGuard {
+            pact_id: as_string(field("pactId")?)?,
+            name: as_string(field("name")?)?,
+        }
+    } else if keys(&["cgName", "cgArgs", "cgPactId"]) {
+        PactValue::CapabilityGuard {
+            name: as_string(field("cgName")?)?,
+            args: field("cgArgs")?,
+            pact_id: string_or_null(field("cgPactId")?)?,
+        }
+    } else if keys(&["refName", "refSpec"]) {
+        PactValue::ModuleRef {
+            name: field("refName")?,
+            spec: field("refSpec")?,
+        }
+    } else {
+        return None;
+    })
+}
+
+/// The contents of a JSON string, escapes included.
+pub fn as_string(text: &str) -> Option<&str> {
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+    // The closing quote must not be escaped
+    let trailing = inner.bytes().rev().take_while(|b| *b == b'\\').count();
+    (trailing % 2 == 0).then(|| inner)
+}
+
+/// The decimal text of an amount: a JSON number, or a Pact decimal or integer.
+pub fn amount(text: &str) -> Option<&str> {
+    match PactValue::parse(text) {
+        PactValue::Decimal(d) | PactValue::Integer(d) => Some(d),
+        PactValue::Json(n) => number(n),
+        _ => None,
+    }
+}
+
+fn string_or_null(text: &str) -> Option<&str> {
+    (text == "null" || as_string(text).is_some()).then(|| text)
+}
+
+fn digits(s: &str) -> Option<&str> {
+    let end = s.bytes().position(|b| !b.is_ascii_digit()).unwrap_or(s.len());
+    (end > 0).then(|| &s[end..])
+}
+
+fn integer(s: &str) -> Option<&str> {
+    digits(s.strip_prefix('-').unwrap_or(s))?
+        .is_empty()
+        .then(|| s)
+}
+
+// An integer beyond 2^53 - 1, the largest JSON numbers hold exactly, as Pact writes it
+fn unsafe_integer(s: &str) -> Option<&str> {
+    const MAX_SAFE: &str = "9007199254740991";
+    let magnitude = s.strip_prefix('-').unwrap_or(s);
+    let unsafe_len = magnitude.len() > MAX_SAFE.len()
+        || (magnitude.len() == MAX_SAFE.len() && magnitude > MAX_SAFE);
+    if !unsafe_len || magnitude.starts_with('0') {
+        return None;
+    }
+    integer(s)
+}
+
+fn decimal(s: &str) -> Option<&str> {
+    let rest = digits(s.strip_prefix('-').unwrap_or(s))?;
+    let rest = match rest.strip_prefix('.') {
+        Some(fraction) => digits(fraction)?,
+        None => rest,
+    };
+    rest.is_empty().then(|| s)
+}
+
+// A JSON number, with an optional exponent
+fn number(s: &str) -> Option<&str> {
+    let mantissa = match s.find(|c| c == 'e' || c == 'E') {
+        Some(i) => {
+            let exponent = &s[i + 1..];
+            integer(exponent.strip_prefix('+').unwrap_or(exponent))?;
+            &s[..i]
+        }
+        None => s,
+    };
+    decimal(mantissa).map(|_| s)
+}
+
+// The length of the JSON value at the start of `s`
+fn value_len(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, b) in s.bytes().enumerate() {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' if depth == 0 => return Some(i + 1),
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' | b',' | b':' if depth == 0 => return (i > 0).then(|| i),
+            b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    (depth == 0 && !in_string && !s.is_empty()).then(|| s.len())
+}
+
+// Splits the JSON value at the start of `s` from what follows it
+fn split_value(s: &str) -> Option<(&str, &str)> {
+    let len = value_len(s)?;
+    Some((&s[..len], &s[len..]))
+}
+
+/// The elements of a JSON array.
+pub fn elements(text: &str) -> Option<impl Iterator<Item = &str>> {
+    let mut rest = text.strip_prefix('[')?.strip_suffix(']')?;
+    Some(core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let (value, after) = split_value(rest)?;
+        rest = after.strip_prefix(',').unwrap_or(after);
+        Some(value)
+    }))
+}
+
+// The members of a small JSON object; None for anything else
+fn members(text: &str) -> Option<ArrayVec<(&str, &str), MAX_MEMBERS>> {
+    let mut rest = text.strip_prefix('{')?.strip_suffix('}')?;
+    let mut members = ArrayVec::new();
+    while !rest.is_empty() {
+        let (key, after) = split_value(rest)?;
+        let (value, after) = split_value(after.strip_prefix(':')?)?;
+        members.try_push((as_string(key)?, value)).ok()?;
+        rest = match after.strip_prefix(',') {
+            Some(next) if !next.is_empty() => next,
+            None if after.is_empty() => after,
+            _ => return None,
+        };
+    }
+    Some(members)
+}
+
+// Module and keyset names are written as {"namespace": ns, "name": name}, or {"ns", "ksn"}
+struct QualifiedName<'a>(&'a str);
+
+impl fmt::Display for QualifiedName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = as_string(self.0) {
+            return write!(f, "{}", Sanitized(name));
+        }
+        let parts = members(self.0).and_then(|m| {
+            let field = |key: &str| m.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+            let name = as_string(field("name").or_else(|| field("ksn"))?)?;
+            let namespace = field("namespace").or_else(|| field("ns")).and_then(as_string);
+            Some((namespace, name))
+        });
+        match parts {
+            Some((Some(namespace), name)) => {
+                write!(f, "{}.{}", Sanitized(namespace), Sanitized(name))
+            }
+            Some((None, name)) => write!(f, "{}", Sanitized(name)),
+            None => write!(f, "{}", Sanitized(self.0)),
+        }
+    }
+}
+
+impl fmt::Display for PactValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PactValue::Decimal(d) | PactValue::Integer(d) => write!(f, "{}", Sanitized(d)),
+            PactValue::Time(t) => write!(f, "{}", Sanitized(t)),
+            PactValue::KeySet { keys, pred } => {
+                write!(f, "KeySet {{keys: [")?;
+                for (i, key) in elements(keys).into_iter().flatten().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", Sanitized(as_string(key).unwrap_or(key)))?;
+                }
+                write!(f, "], pred: {}}}", Sanitized(pred))
+            }
+            PactValue::KeySetRef(name) => write!(f, "KeySetRef {}", QualifiedName(name)),
+            PactValue::UserGuard { fun, args } => {
+                write!(f, "UserGuard {{fun: {}, args: {}}}", Sanitized(fun), Sanitized(args))
+            }
+            PactValue::ModuleGuard { module, name } => write!(
+                f,
+                "ModuleGuard {{module: {}, name: {}}}",
+                QualifiedName(module),
+                Sanitized(name)
+            ),
+            PactValue::PactGuard { pact_id, name } => write!(
+                f,
+                "PactGuard {{pactId: {}, name: {}}}",
+                Sanitized(pact_id),
+                Sanitized(name)
+            ),
+            PactValue::CapabilityGuard {
+                name,
+                args,
+                pact_id,
+            } => write!(
+                f,
+                "CapabilityGuard {{name: {}, args: {}, pactId: {}}}",
+                Sanitized(name),
+                Sanitized(args),
+                Sanitized(as_string(pact_id).unwrap_or(pact_id))
+            ),
+            PactValue::ModuleRef { name, spec } => {
+                write!(f, "ModRef {{name: {}, spec: ", QualifiedName(name))?;
+                match elements(spec) {
+                    Some(interfaces) => {
+                        write!(f, "[")?;
+                        for (i, interface) in interfaces.enumerate() {
+                            if i > 0 {
+                                write!(f, ", ")?;
+                            }
+                            write!(f, "{}", QualifiedName(interface))?;
+                        }
+                        write!(f, "]}}")
+                    }
+                    None => write!(f, "{}}}", Sanitized(spec)),
+                }
+            }
+            PactValue::Json(text) => write!(f, "{}", Sanitized(text)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{amount, PactValue};
+    use arrayvec::ArrayString;
+    use core::fmt::Write;
+    #[cfg(target_family = "bolos")]
+    use nanos_sdk::{assert_eq_err as assert_eq, TestType};
+    #[cfg(target_family = "bolos")]
+    use testmacro::test_item as test;
+
+    fn show(text: &str) -> ArrayString<128> {
+        let mut shown = ArrayString::new();
+        write!(shown, "{}", PactValue::parse(text)).unwrap();
+        shown
+    }
+
+    #[test]
+    fn accepts_amounts_in_pact_encodings() {
+        assert_eq!(amount("11"), Some("11"));
+        assert_eq!(amount("1.0e-5"), Some("1.0e-5"));
+        assert_eq!(amount("{\"decimal\":\"1.5\"}"), Some("1.5"));
+        assert_eq!(amount("{\"int\":2}"), Some("2"));
+        assert_eq!(
+            amount("{\"int\":\"9007199254740992\"}"),
+            Some("9007199254740992")
+        );
+    }
+
+    #[test]
+    fn refuses_amounts_pact_does_not_write() {
+        assert_eq!(amount("{\"int\":\"2\"}"), None);
+        assert_eq!(amount("{\"int\":\"9007199254740991\"}"), None);
+        assert_eq!(amount("{\"int\":\"09007199254740992\"}"), None);
+        assert_eq!(amount("{\"int\":2.5}"), None);
+        assert_eq!(amount("{\"decimal\":1.5}"), None);
+        assert_eq!(amount("{\"decimal\":\"1e5\"}"), None);
+        assert_eq!(amount("{\"decimal\":\"1.5\",\"int\":2}"), None);
+        assert_eq!(amount("\"11\""), None);
+        assert_eq!(amount("1."), None);
+    }
+
+    #[test]
+    fn shows_every_member_of_guards() {
+        assert_eq!(
+            show("{\"cgName\":\"coin.GAS\",\"cgArgs\":[],\"cgPactId\":null}").as_str(),
+            "CapabilityGuard {name: coin.GAS, args: [], pactId: null}"
+        );
+        assert_eq!(
+            show("{\"cgName\":\"m.C\",\"cgArgs\":[1],\"cgPactId\":\"abc\"}").as_str(),
+            "CapabilityGuard {name: m.C, args: [1], pactId: abc}"
+        );
+        assert_eq!(
+            show("{\"pactId\":\"abc\",\"name\":\"p\"}").as_str(),
+            "PactGuard {pactId: abc, name: p}"
+        );
+        assert_eq!(
+            show("{\"moduleName\":{\"namespace\":\"free\",\"name\":\"m\"},\"name\":\"g\"}")
+                .as_str(),
+            "ModuleGuard {module: free.m, name: g}"
+        );
+        assert_eq!(
+            show("{\"keys\":[\"alice\",\"bob\"],\"pred\":\"keys-any\"}").as_str(),
+            "KeySet {keys: [alice, bob], pred: keys-any}"
+        );
+    }
+
+    #[test]
+    fn shows_the_interfaces_of_module_references() {
+        assert_eq!(
+            show("{\"refName\":{\"namespace\":null,\"name\":\"coin\"},\"refSpec\":[{\"namespace\":null,\"name\":\"fungible-v2\"}]}").as_str(),
+            "ModRef {name: coin, spec: [fungible-v2]}"
+        );
+        assert_eq!(
+            show("{\"refName\":{\"namespace\":null,\"name\":\"coin\"},\"refSpec\":null}").as_str(),
+            "ModRef {name: coin, spec: null}"
+        );
+    }
+
+    #[test]
+    fn shows_other_values_as_json() {
+        assert_eq!(
+            show("{\"cgName\":\"m.C\",\"cgArgs\":[],\"cgPactId\":5}").as_str(),
+            "{\"cgName\":\"m.C\",\"cgArgs\":[],\"cgPactId\":5}"
+        );
+        assert_eq!(show("[1,2]").as_str(), "[1,2]");
+    }
+}
@@ -1,26 +1,13 @@
+use crate::decimal::Decimal;
 #[cfg(target_os = "nanos")]
 use nanos_sdk::nvm::*;
 #[cfg(target_os = "nanos")]
 use nanos_sdk::Pic;
-// use bitflags::bitflags;
-
-// TODO: use bitflags, and better types for Settings
-// bitflags! {
-//     pub struct Flags: u32 {
-//         const HashSigning = 0b00000001;
-//     }
-// }
-
-// #[derive(PartialEq, Debug)]
-// enum HashSigningSettings {
-//     HashSigningSettingsDisabled,
-//     HashSigningSettingsEnabled
-// }
-
 // This is necessary to store the object in NVM and not in RAM
 #[cfg(target_os = "nanos")]
 #[link_section = ".nvm_data"]
-static mut SETTINGS: Pic<AtomicStorage<u8>> = Pic::new(AtomicStorage::new(&0));
+static mut SETTINGS: Pic<AtomicStorage<StoredSettings>> =
+    Pic::new(AtomicStorage::new(&StoredSettings::DEFAULT));
 
 // In the program, `SETTINGS` must not be used directly. It is a static variable
 // and using it would require unsafe everytime. Instead, a reference must be
@@ -44,34 +31,40 @@ impl Settings {
     }
 
     #[inline(never)]
-    pub fn get(&self) -> u8 {
+    pub fn get(&self) -> StoredSettings {
         let settings = unsafe { SETTINGS.get_mut() };
         return *settings.get_ref();
     }
 
     // The inline(never) is important. Otherwise weird segmentation faults happen on speculos.
     #[inline(never)]
-    pub fn set(&mut self, v: &u8) {
+    pub fn set(&mut self, v: &StoredSettings) {
         let settings = unsafe { SETTINGS.get_mut() };
         settings.update(v);
     }
 }
 
+// On the other devices the settings are only kept in RAM, but they still live in a single
+// static so that every `Settings` handle (including the ones taken by the parsers) sees the
+// value chosen in the menu.
 #[cfg(not(target_os = "nanos"))]
-pub struct Settings(u8);
+static mut SETTINGS: StoredSettings = StoredSettings::DEFAULT;
+
+#[cfg(not(target_os = "nanos"))]
+pub struct Settings;
 
 #[cfg(not(target_os = "nanos"))]
 impl Settings {
     pub fn new() -> Settings {
-        Settings(0)
+        Settings
     }
 
-    pub fn get(&self) -> u8 {
-        self.0
+    pub fn get(&self) -> StoredSettings {
+        unsafe { SETTINGS }
     }
 
-    pub fn set(&mut self, v: &u8) {
-        self.0 = *v;
+    pub fn set(&mut self, v: &StoredSettings) {
+        unsafe { SETTINGS = *v };
     }
 }
 
@@ -80,3 +73,216 @@ impl Default for Settings {
         Self::new()
     }
 }
+
+/// What is kept in NVM. The flags come first, so that they keep their place from when the
+/// settings were a single byte.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct StoredSettings {
+    flags: u8,
+    // The chosen preset of each threshold, indexed by `Threshold`
+    levels: [u8; THRESHOLD_COUNT],
+}
+
+impl StoredSettings {
+    const DEFAULT: StoredSettings = StoredSettings {
+        flags: 0,
+        levels: [0; THRESHOLD_COUNT],
+    };
+}
+
+/// The on/off settings; each one is stored as a single bit of the flags, except for
+/// `BlindSigningSession` which is never persisted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SettingFlag {
+    BlindSigning,
+    BlindSigningSession,
+    ExpertMode,
+    StrictNetworks,
+    AllowUnscoped,
+    KnownRecipientsOnly,
+    RefuseOverLimit,
+}
+
+impl SettingFlag {
+    const fn mask(self) -> u8 {
+        match self {
+            SettingFlag::BlindSigning => 0b01,
+            SettingFlag::BlindSigningSession => 0,
+            SettingFlag::ExpertMode => 0b10,
+            SettingFlag::StrictNetworks => 0b100,
+            SettingFlag::AllowUnscoped => 0b1000,
+            SettingFlag::KnownRecipientsOnly => 0b10000,
+            SettingFlag::RefuseOverLimit => 0b100000,
+        }
+    }
+}
+
+/// The limits above which the transaction review shows a warning, or for the spending limit asks
+/// for another confirmation. Each one has a few presets, the first being the default, which the
+/// user cycles through from the menu.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Threshold {
+    GasPrice,
+    GasLimit,
+    Ttl,
+    SpendingLimit,
+}
+
+const THRESHOLD_COUNT: usize = 4;
+const PRESET_COUNT: usize = 3;
+
+// The value of each preset, in KDA for the gas price, and in gas units and seconds for the
+// others, along with its menu label.
+const GAS_PRICE_PRESETS: [(Decimal, &str); PRESET_COUNT] = [
+    (Decimal(1_000_000_000), "Gas Price > 0.001"),
+    (Decimal(100_000_000), "Gas Price > 0.0001"),
+    (Decimal(10_000_000_000), "Gas Price > 0.01"),
+];
+// 150000 is the block gas limit of chainweb
+const GAS_LIMIT_PRESETS: [(u64, &str); PRESET_COUNT] = [
+    (150_000, "Gas Limit > 150000"),
+    (10_000, "Gas Limit > 10000"),
+    (50_000, "Gas Limit > 50000"),
+];
+const TTL_PRESETS: [(u64, &str); PRESET_COUNT] = [
+    (24 * 60 * 60, "TTL > 1 day"),
+    (8 * 60 * 60, "TTL > 8 hours"),
+    (2 * 24 * 60 * 60, "TTL > 2 days"),
+];
+// The most KDA the transfers of a transaction can add up to without another confirmation
+const SPENDING_LIMIT_PRESETS: [(Option<u64>, &str); 5] = [
+    (None, "No Spending Limit"),
+    (Some(10), "Spending > 10 KDA"),
+    (Some(100), "Spending > 100 KDA"),
+    (Some(1_000), "Spending > 1000 KDA"),
+    (Some(10_000), "Spending > 10000 KDA"),
+];
+
+const fn preset_count(threshold: Threshold) -> usize {
+    match threshold {
+        Threshold::SpendingLimit => SPENDING_LIMIT_PRESETS.len(),
+        _ => PRESET_COUNT,
+    }
+}
+
+impl Settings {
+    fn level(&self, threshold: Threshold) -> usize {
+        // Out of range if the stored settings come from another version
+        let level = self.get().levels[threshold as usize] as usize;
+        if level < preset_count(threshold) {
+            level
+        } else {
+            0
+        }
+    }
+
+    pub fn max_gas_price(&self) -> Decimal {
+        GAS_PRICE_PRESETS[self.level(Threshold::GasPrice)].0
+    }
+
+    pub fn max_gas_limit(&self) -> u64 {
+        GAS_LIMIT_PRESETS[self.level(Threshold::GasLimit)].0
+    }
+
+    pub fn max_ttl(&self) -> u64 {
+        TTL_PRESETS[self.level(Threshold::Ttl)].0
+    }
+
+    pub fn spending_limit(&self) -> Option<Decimal> {
+        SPENDING_LIMIT_PRESETS[self.level(Threshold::SpendingLimit)]
+            .0
+            .map(Decimal::from_integer)
+    }
+
+    pub fn threshold_label(&self, threshold: Threshold) -> &'static str {
+        let level = self.level(threshold);
+        match threshold {
+            Threshold::GasPrice => GAS_PRICE_PRESETS[level].1,
+            Threshold::GasLimit => GAS_LIMIT_PRESETS[level].1,
+            Threshold::Ttl => TTL_PRESETS[level].1,
+            Threshold::SpendingLimit => SPENDING_LIMIT_PRESETS[level].1,
+        }
+    }
+
+    /// Switch to the next preset of the threshold.
+    pub fn cycle_threshold(&mut self, threshold: Threshold) {
+        let mut v = self.get();
+        let next = (self.level(threshold) + 1) % preset_count(threshold);
+        v.levels[threshold as usize] = next as u8;
+        self.set(&v);
+    }
+}
+
+// Blind signing enabled "for this session only" lasts for this many hash signatures, or this
+// many ticker events (about ten minutes), whichever comes first.
+const SESSION_MAX_SIGNATURES: u8 = 10;
+const SESSION_MAX_TICKS: u32 = 10 * 60 * 10;
+
+struct BlindSigningSession {
+    signatures_left: u8,
+    ticks_left: u32,
+}
+
+// Kept in RAM, so it is dropped when the app exits
+static mut BLIND_SIGNING_SESSION: Option<BlindSigningSession> = None;
+
+impl Settings {
+    pub fn is_enabled(&self, flag: SettingFlag) -> bool {
+        match flag {
+            SettingFlag::BlindSigningSession => unsafe { BLIND_SIGNING_SESSION.is_some() },
+            _ => self.get().flags & flag.mask() != 0,
+        }
+    }
+
+    pub fn toggle(&mut self, flag: SettingFlag) {
+        match flag {
+            SettingFlag::BlindSigningSession => unsafe {
+                BLIND_SIGNING_SESSION = match BLIND_SIGNING_SESSION {
+                    Some(_) => None,
+                    None => Some(BlindSigningSession {
+                        signatures_left: SESSION_MAX_SIGNATURES,
+                        ticks_left: SESSION_MAX_TICKS,
+                    }),
+                }
+            },
+            _ => {
+                let mut v = self.get();
+                v.flags ^= flag.mask();
+                self.set(&v);
+            }
+        }
+    }
+
+    /// Whether blind signing is enabled, either permanently or for this session.
+    pub fn blind_signing_allowed(&self) -> bool {
+        self.is_enabled(SettingFlag::BlindSigning)
+            || self.is_enabled(SettingFlag::BlindSigningSession)
+    }
+
+    /// Count a blind signature against the session, ending it after the last allowed one.
+    pub fn blind_signature_made(&mut self) {
+        unsafe {
+            if let Some(ref mut session) = BLIND_SIGNING_SESSION {
+                session.signatures_left -= 1;
+                if session.signatures_left == 0 {
+                    BLIND_SIGNING_SESSION = None;
+                }
+            }
+        }
+    }
+
+    /// Called on every ticker event; returns true when this ended the session.
+    pub fn tick(&mut self) -> bool {
+        unsafe {
+            if let Some(ref mut session) = BLIND_SIGNING_SESSION {
+                session.ticks_left -= 1;
+                if session.ticks_left == 0 {
+                    BLIND_SIGNING_SESSION = None;
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
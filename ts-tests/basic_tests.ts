@@ -15,9 +15,21 @@ import { instantiate, Nacl } from "js-nacl";
 let nacl : Nacl =null;
 
 let ignoredScreens = [ "W e l c o m e", "Cancel", "Working...", "Quit", "Kadena 0.2.2", "Back"
-  , "Blind Signing", "Enable Blind Signing", "Disable Blind Signing"
+  , "Settings", "About", "Enable Blind Signing", "Disable Blind Signing", "Enable Expert Mode", "Disable Expert Mode"
+  , "Start Blind Session", "End Blind Session", "Blind Signing ON"
+  , "Enable Strict Network", "Disable Strict Network", "Allow Unscoped Sigs", "Forbid Unscoped Sigs"
+  , "Only Known Recipients", "Allow Any Recipient", "Refuse Over Limit", "Confirm Over Limit"
+  , "Warning Limits"
+  , "Gas Price > 0.001", "Gas Price > 0.0001", "Gas Price > 0.01"
+  , "Gas Limit > 150000", "Gas Limit > 10000", "Gas Limit > 50000"
+  , "TTL > 1 day", "TTL > 8 hours", "TTL > 2 days"
+  , "No Spending Limit", "Spending > 10 KDA", "Spending > 100 KDA", "Spending > 1000 KDA", "Spending > 10000 KDA"
   /* The next ones are specifically for S+ in which OCR is broken */
-  , "Blind igning", "Enable Blind igning", "Disable Blind igning", "Blind igningQuit", "QuitQuit" ];
+  , "ettings", "Enable Blind igning", "Disable Blind igning", "ettingsQuit", "QuitQuit"
+  , "tart Blind ession", "End Blind ession", "Blind igning ON" ];
+
+// The busy screen shows the upload progress of long transactions
+const progressScreen = /^Working\.\.\. \d+%$/;
 
 const API_PORT: number = 5005;
 
@@ -38,7 +50,7 @@ let setAcceptAutomationRules = async function() {
 }
 
 let processPrompts = function(prompts: [any]) {
-  let i = prompts.filter((a : any) => !ignoredScreens.includes(a["text"])); // .values();
+  let i = prompts.filter((a : any) => !ignoredScreens.includes(a["text"]) && !progressScreen.test(a["text"])); // .values();
   let header = "";
   let prompt = "";
   let rv = [];
@@ -166,6 +178,24 @@ let sendCommandExpectFail = async function(command : any) {
   expect.fail("Test should have failed");
 }
 
+let sendCommandExpectStatus = async function(statusCode : number, command : any) {
+  await setAcceptAutomationRules();
+  await Axios.delete(BASE_URL + "/events");
+
+  let transport = await Transport.open("http://127.0.0.1:5000/apdu");
+  let kda = new Kda(transport);
+  try { await command(kda); } catch(e) {
+    expect(e.statusCode).to.equal(statusCode);
+    return;
+  }
+  expect.fail("Test should have failed");
+}
+
+const SW_DUPLICATE_KEY = 0x6A81;
+const SW_UNKNOWN_NETWORK = 0x6A82;
+const SW_INVALID_CHAIN = 0x6A83;
+const SW_UNSCOPED_SIGNER = 0x6A84;
+
 instantiate(n => { nacl=n; });
 describe('basic tests', async function() {
 
@@ -217,22 +247,95 @@ describe('basic tests', async function() {
 });
 
 
-function testTransaction(path: string, txn: string, prompts: any[]) {
+// With `expert`, expert mode is turned on while signing, and off again even if the test fails
+function testTransaction(path: string, txn: string, prompts: any[], expert = false) {
+     const toggle = expert ? toggleExpertMode : async () => {};
+     return testTransactionWithSettings(toggle, toggle, path, txn, prompts);
+}
+
+// Signs with SIGN_JSON_TX, returning the signature in hex
+let signJsonTx = async function(kda : Kda, path: string, txn: string) {
+  let rv = await kda.signTransaction(path, Buffer.from(txn, "utf-8").toString("hex"));
+  return rv.signature;
+}
+
+// Like testTransaction, with settings changed by `setup` while signing. `teardown` restores them
+// even if the test fails, so that the next tests start from the default settings.
+function testTransactionWithSettings(setup: (kda : Kda) => Promise<any>, teardown: () => Promise<any>, path: string, txn: string, prompts: any[], sign = signJsonTx) {
      return async () => {
        await sendCommandAndAccept(
          async (kda : Kda) => {
            let pubkey = (await kda.getPublicKey(path)).publicKey;
-           await Axios.delete(BASE_URL + "/events");
+           await setup(kda);
+           try {
+             await Axios.delete(BASE_URL + "/events");
 
-           let rv = await kda.signTransaction(path, Buffer.from(txn, "utf-8").toString("hex"));
-           expect(rv.signature.length).to.equal(128);
-           let hash = blake2b(32).update(Buffer.from(txn, "utf-8")).digest();
-           let pass = nacl.crypto_sign_verify_detached(Buffer.from(rv.signature, 'hex'), hash, Buffer.from(pubkey, 'hex'));
-           expect(pass).to.equal(true);
+             let signature = await sign(kda, path, txn);
+             expect(signature.length).to.equal(128);
+             let hash = blake2b(32).update(Buffer.from(txn, "utf-8")).digest();
+             let pass = nacl.crypto_sign_verify_detached(Buffer.from(signature, 'hex'), hash, Buffer.from(pubkey, 'hex'));
+             expect(pass).to.equal(true);
+           } finally {
+             await teardown();
+           }
          }, prompts);
      }
 }
 
+let pressButton = async function(button: string) {
+  await Axios.post(BASE_URL + "/button/" + button, {"action":"press-and-release"});
+}
+
+// The entries of the Settings page, "Back" included
+const SETTINGS_ENTRIES = 9;
+
+// Flips the setting at the given position of the Settings page, and goes back to the main menu
+let toggleSetting = async function(index: number) {
+  await pressButton("right");
+  await pressButton("both");
+  for (let i = 0; i < index; i++) await pressButton("right");
+  await pressButton("both");
+  for (let i = index; i < SETTINGS_ENTRIES - 1; i++) await pressButton("right");
+  await pressButton("both");
+}
+
+let toggleExpertMode = () => toggleSetting(2);
+
+let toggleStrictNetwork = () => toggleSetting(3);
+
+let toggleAllowUnscoped = () => toggleSetting(4);
+
+// Switches a threshold of the "Warning Limits" page to its next preset, `times` times
+// The thresholds, then "Back"
+const THRESHOLD_ENTRIES = 5;
+
+let cycleThreshold = async function(index: number, times = 1) {
+  await pressButton("right");
+  await pressButton("both");
+  for (let i = 0; i < SETTINGS_ENTRIES - 2; i++) await pressButton("right");
+  await pressButton("both");
+  for (let i = 0; i < index; i++) await pressButton("right");
+  for (let i = 0; i < times; i++) await pressButton("both");
+  for (let i = index; i < THRESHOLD_ENTRIES - 1; i++) await pressButton("right");
+  await pressButton("both");
+  for (let i = 0; i < SETTINGS_ENTRIES - 1; i++) await pressButton("right");
+  await pressButton("both");
+}
+
+function testStrictNetworkRejected(path: string, txn: string) {
+     return async () => {
+       await toggleStrictNetwork();
+       try {
+         await sendCommandExpectStatus(SW_UNKNOWN_NETWORK,
+           async (kda : Kda) => {
+             await kda.signTransaction(path, Buffer.from(txn, "utf-8").toString("hex"));
+           });
+       } finally {
+         await toggleStrictNetwork();
+       }
+     }
+}
+
 describe("Signing tests", function() {
 
   it("can sign a simple transfer",
@@ -241,15 +344,101 @@ describe("Signing tests", function() {
        '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
        [
          { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+  it("shows hash, nonce, keys, raw capabilities and path in expert mode",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
          { "header": "Requiring", "prompt": "Capabilities" },
          { "header": "Of Key", "prompt": "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" },
          { "header": "Paying Gas", "prompt": " " },
+         { "header": "Raw Capability", "prompt": '{"args":[],"name":"coin.GAS"}' },
          { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "Raw Capability", "prompt": '{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}' },
          { "header": "On Chain", "prompt": "0" },
          { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Nonce", "prompt": '\\"2021-10-12T03:27:53.700Z\\"' },
          { "header": "Transaction hash", "prompt": "fPSCfMUaoK1N31qwhwBFUPwG-YR_guPP894uixsNZgk" },
          { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Derivation Path", "prompt": "0/0" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ],
+       true
+     ));
+  it("shows the verifiers and their capabilities, but not their proofs",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"verifiers":[{"name":"hyperlane_v3_message","proof":"AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA","clist":[{"args":["9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42"],"name":"hyperlane.MESSAGE"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "Verifier", "prompt": "hyperlane_v3_message" },
+         { "header": "Unknown Capability 1", "prompt": "name: hyperlane.MESSAGE, arg 1: \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+  it("counts a transfer granted by several signers once, and not those of verifiers",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]},{"pubKey":"4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfd","clist":[{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"verifiers":[{"name":"hyperlane_v3_message","proof":"AAAA","clist":[{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfd",5],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "Verifier", "prompt": "hyperlane_v3_message" },
+         { "header": "Transfer 1", "prompt": "5 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfd\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
          {
            "text": "Sign Transaction?",
            "x": 19,
@@ -268,9 +457,7 @@ describe("Signing tests", function() {
           '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a\\" \\"4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfd\\" 2.0)"}},"signers":[{"pubKey":"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a","clist":[{"args":["aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a","4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfd",2],"name":"coin.TRANSFER"},{"args":[1,true,null],"name":"coin.GAS"}]}],"meta":{"creationTime":1634009195,"ttl":900,"gasLimit":600,"chainId":"0","gasPrice":1.0e-6,"sender":"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a"},"nonce":"\\"2021-10-12T03:27:35.231Z\\""}',
           [
             { "header": "Signing", "prompt": "Transaction" },
-            { "header": "On Network", "prompt": "mainnet01" },
-            { "header": "Requiring", "prompt": "Capabilities" },
-            { "header": "Of Key", "prompt": "aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a" },
+            { "header": "On Network", "prompt": "Mainnet" },
             {
               "header": "Transfer 1",
               "prompt": "2 from \"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a\" to \"4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfd\"",
@@ -281,14 +468,11 @@ describe("Signing tests", function() {
             },
             { "header": "On Chain", "prompt": "0" },
             { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-6" },
-            {
-              "header": "Transaction hash",
-              "prompt": "anrl4cUVN53NFJCQ9tH4szt-ZzlCQ_SZuDI7e8OLyco",
-            },
             {
               "header": "Sign for Address",
               "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c",
             },
+            { "header": "Total", "prompt": "2 KDA to 1 recipient" },
             {
               "text": "Sign Transaction?",
               "x": 19,
@@ -306,14 +490,38 @@ describe("Signing tests", function() {
        '{"networkId":null,"payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
        [
          { "header": "Signing", "prompt": "Transaction" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" },
+         { "header": "WARNING", "prompt": "Replayable on any network" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+  it("warns about an unknown network",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet02","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "WARNING", "prompt": "Unknown network mainnet02" },
          { "header": "Paying Gas", "prompt": " " },
          { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
          { "header": "On Chain", "prompt": "0" },
          { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
-         { "header": "Transaction hash", "prompt": "epv3lSVeZCWEYpPZet-ddYqpFSekJiIcw2azMb-Cn8w" },
          { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
          {
            "text": "Sign Transaction?",
            "x": 19,
@@ -326,18 +534,20 @@ describe("Signing tests", function() {
          }
        ]
      ));
+  it("refuses an unknown network with Strict Network enabled",
+     testStrictNetworkRejected("0/0", '{"networkId":"mainnet02","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}'));
+  it("refuses a null network with Strict Network enabled",
+     testStrictNetworkRejected("0/0", '{"networkId":null,"payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}'));
      it("can sign a simple transfer with decimal amount",
         testTransaction(
           "0/0",
           '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a\\" \\"4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfd\\" 2.0)"}},"signers":[{"pubKey":"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a","clist":[{"args":["aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a","4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfd",{"decimal":"123456789.0123456789"}],"name":"coin.TRANSFER"},{"args":[],"name":"coin.GAS"}]}],"meta":{"creationTime":1634009195,"ttl":900,"gasLimit":600,"chainId":"0","gasPrice":1.0e-6,"sender":"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a"},"nonce":"\\"2021-10-12T03:27:35.231Z\\""}',
           [
             { "header": "Signing", "prompt": "Transaction" },
-            { "header": "On Network", "prompt": "mainnet01" },
-            { "header": "Requiring", "prompt": "Capabilities" },
-            { "header": "Of Key", "prompt": "aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a" },
+            { "header": "On Network", "prompt": "Mainnet" },
             {
               "header": "Transfer 1",
-              "prompt": "{\"decimal\":\"123456789.0123456789\"} from \"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a\" to \"4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfd\"",
+              "prompt": "123456789.0123456789 from \"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a\" to \"4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfd\"",
             },
             {
               "header": "Paying Gas",
@@ -345,14 +555,11 @@ describe("Signing tests", function() {
             },
             { "header": "On Chain", "prompt": "0" },
             { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-6" },
-            {
-              "header": "Transaction hash",
-              "prompt": "u4kRsc0DEmRbOOG2gePtMADMTOGGtRsXrMQ2R4bAvk4",
-            },
             {
               "header": "Sign for Address",
               "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c",
             },
+            { "header": "Total", "prompt": "123456789.0123456789 KDA to 1 recipient" },
             {
               "text": "Sign Transaction?",
               "x": 19,
@@ -370,9 +577,7 @@ describe("Signing tests", function() {
        '{"networkId":"mainnet01","payload":{"exec":{"data":{"recp-ks":{"pred":"keys-all","keys":["875e4493e19c8721583bfb46f0768f10266ebcca33c4a0e04bc099a7044a90f7"]}},"code":"(coin.transfer-create \\"e4a1b2980c086c4551ab7d2148cf56e9774c64eb86f795d5fd83e39ccfd2ec66\\" \\"875e4493e19c8721583bfb46f0768f10266ebcca33c4a0e04bc099a7044a90f7\\" (read-keyset \\"recp-ks\\") 4.98340488)"}},"signers":[{"pubKey":"e4a1b2980c086c4551ab7d2148cf56e9774c64eb86f795d5fd83e39ccfd2ec66","clist":[{"args":[],"name":"coin.GAS"},{"args":["e4a1b2980c086c4551ab7d2148cf56e9774c64eb86f795d5fd83e39ccfd2ec66","875e4493e19c8721583bfb46f0768f10266ebcca33c4a0e04bc099a7044a90f7",4.98340488],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009142,"ttl":28800,"gasLimit":60000,"chainId":"0","gasPrice":1.0e-6,"sender":"e4a1b2980c086c4551ab7d2148cf56e9774c64eb86f795d5fd83e39ccfd2ec66"},"nonce":"\\"1634009156943\\""}',
        [
          { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "e4a1b2980c086c4551ab7d2148cf56e9774c64eb86f795d5fd83e39ccfd2ec66" },
+         { "header": "On Network", "prompt": "Mainnet" },
          {
            "header": "Paying Gas",
            "prompt": " ",
@@ -383,14 +588,11 @@ describe("Signing tests", function() {
          },
          { "header": "On Chain", "prompt": "0" },
          { "header": "Using Gas", "prompt": "at most 60000 at price 1.0e-6" },
-         {
-           "header": "Transaction hash",
-           "prompt": "SrjHkjfzLHLiOS-5_lcZvLOhiU42NynfAfezMzbeXsw",
-         },
          {
            "header": "Sign for Address",
            "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c",
          },
+         { "header": "Total", "prompt": "4.98340488 KDA to 1 recipient" },
          {
            "text": "Sign Transaction?",
            "x": 19,
@@ -410,15 +612,7 @@ describe("Signing tests", function() {
          { "header": "Signing",
            "prompt": "Transaction"
          },
-         { "header": "On Network", "prompt": "mainnet01" },
-         {
-           "header": "Requiring",
-           "prompt": "Capabilities",
-         },
-         {
-           "header": "Of Key",
-           "prompt": "73580ffb3e5ca9859442395d4c1cb0bf3aa4e7246564ce943b7ae508b3ee7c03"
-         },
+         { "header": "On Network", "prompt": "Mainnet" },
          {
            "header": "Paying Gas",
            "prompt": " ",
@@ -429,14 +623,11 @@ describe("Signing tests", function() {
          },
          { "header": "On Chain", "prompt": "0" },
          { "header": "Using Gas", "prompt": "at most 60000 at price 1.0e-6" },
-         {
-           "header": "Transaction hash",
-           "prompt": "pJsk0-vgbqfzOBFc4zHtFMSMa0aCZpXBZ_QQFxox1-k",
-         },
          {
            "header": "Sign for Address",
            "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c",
          },
+         { "header": "Total", "prompt": "4.89093455 KDA to 1 recipient" },
          {
            "text": "Sign Transaction?",
            "x": 19,
@@ -455,9 +646,7 @@ describe("Signing tests", function() {
        '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42"],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
        [
          { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" },
+         { "header": "On Network", "prompt": "Mainnet" },
          { "header": "Paying Gas", "prompt": " " },
          {
            "header": "Unknown Capability 1",
@@ -465,8 +654,8 @@ describe("Signing tests", function() {
          },
          { "header": "On Chain", "prompt": "0" },
          { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
-         { "header": "Transaction hash", "prompt": "FmmZBoFdyW_0T7oD1fXldK_MgKyvxTd4B3i7ew7VnMY" },
          { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "Unknown amount of KDA to unknown recipients" },
          {
            "text": "Sign Transaction?",
            "x": 19,
@@ -486,9 +675,7 @@ describe("Signing tests", function() {
 '{"networkId":"mainnet01","payload":{"exec":{"data":{"ks":{"pred":"keys-all","keys":["d3300d284f4bcfbc91555184ef026a356e57ff0fa97b5e6c9830750892cd3093"]}},"code":"(coin.rotate \\"d3300d284f4bcfbc91555184ef026a356e57ff0fa97b5e6c9830750892cd3093\\" (read-keyset \\"ks\\"))"}},"signers":[{"pubKey":"81b4511b257fb975dace13e823c257c17ac6a695da65f91b6036d6e1429268fc","clist":[{"args":[],"name":"coin.GAS"},{"args":["d3300d284f4bcfbc91555184ef026a356e57ff0fa97b5e6c9830750892cd3093"],"name":"coin.ROTATE"}]}],"meta":{"creationTime":1633466764,"ttl":28800,"gasLimit":1500,"chainId":"0","gasPrice":1.0e-5,"sender":"81b4511b257fb975dace13e823c257c17ac6a695da65f91b6036d6e1429268fc"},"nonce":"\\"1633466764\\""}',
        [
          { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "81b4511b257fb975dace13e823c257c17ac6a695da65f91b6036d6e1429268fc" },
+         { "header": "On Network", "prompt": "Mainnet" },
          {
            "header": "Paying Gas",
            "prompt": " ",
@@ -499,10 +686,6 @@ describe("Signing tests", function() {
          },
          { "header": "On Chain", "prompt": "0" },
          { "header": "Using Gas", "prompt": "at most 1500 at price 1.0e-5" },
-         {
-           "header": "Transaction hash",
-           "prompt": "WQImvdxCaI7U5Qy2U_3Mxoa3i-Lp-PyNu9aZNtXclHo",
-         },
          {
            "header": "Sign for Address",
            "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c",
@@ -524,9 +707,7 @@ describe("Signing tests", function() {
 '{"networkId":"mainnet01","payload":{"exec":{"data":{"ks":{"pred":"keys-all","keys":["d3300d284f4bcfbc91555184ef026a356e57ff0fa97b5e6c9830750892cd3093"]}},"code":"(coin.rotate \\"d3300d284f4bcfbc91555184ef026a356e57ff0fa97b5e6c9830750892cd3093\\" (read-keyset \\"ks\\"))"}},"signers":[{"pubKey":"81b4511b257fb975dace13e823c257c17ac6a695da65f91b6036d6e1429268fc","clist":[{"args":[],"name":"coin.GAS"},{"args":["d3300d284f4bcfbc91555184ef026a356e57ff0fa97b5e6c9830750892cd3093",null],"name":"coin.ROTATE"}]}],"meta":{"creationTime":1633466764,"ttl":28800,"gasLimit":1500,"chainId":"0","gasPrice":1.0e-5,"sender":"81b4511b257fb975dace13e823c257c17ac6a695da65f91b6036d6e1429268fc"},"nonce":"\\"1633466764\\""}',
        [
          { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "81b4511b257fb975dace13e823c257c17ac6a695da65f91b6036d6e1429268fc" },
+         { "header": "On Network", "prompt": "Mainnet" },
          {
            "header": "Paying Gas",
            "prompt": " ",
@@ -537,10 +718,6 @@ describe("Signing tests", function() {
          },
          { "header": "On Chain", "prompt": "0" },
          { "header": "Using Gas", "prompt": "at most 1500 at price 1.0e-5" },
-         {
-           "header": "Transaction hash",
-           "prompt": "Rr78KvlVRiX59dDOqZFaK9vgW6GzgMss13p67yGOkN4",
-         },
          {
            "header": "Sign for Address",
            "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c",
@@ -565,15 +742,7 @@ describe("Signing tests", function() {
                 "header": "Signing",
                 "prompt": "Transaction",
               },
-              { "header": "On Network", "prompt": "testnet04" },
-              {
-                "header": "Requiring",
-                "prompt": "Capabilities",
-              },
-              {
-                "header": "Of Key",
-                "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c",
-              },
+              { "header": "On Network", "prompt": "TESTNET" },
               {
                 "header": "Unscoped Signer",
                 "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c"
@@ -584,10 +753,6 @@ describe("Signing tests", function() {
                 "header": "WARNING",
                 "prompt": "UNSAFE TRANSACTION. This transaction's code was not recognized and does not limit capabilities for all signers. Signing this transaction may make arbitrary actions on the chain including loss of all funds.",
               },
-              {
-                "header": "Transaction hash",
-                "prompt": "EsF-vcYfXYn8-NpYIvBcOMYCfUxiV6wxECU5FWNFz5g",
-              },
               {
                 "header": "Sign for Address",
                 "prompt": "8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2",
@@ -612,15 +777,7 @@ describe("Signing tests", function() {
                 "header": "Signing",
                 "prompt": "Transaction",
               },
-              { "header": "On Network", "prompt": "testnet04" },
-              {
-                "header": "Requiring",
-                "prompt": "Capabilities",
-              },
-              {
-                "header": "Of Key",
-                "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c",
-              },
+              { "header": "On Network", "prompt": "TESTNET" },
               {
                 "header": "Unscoped Signer",
                 "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c"
@@ -631,10 +788,6 @@ describe("Signing tests", function() {
                 "header": "WARNING",
                 "prompt": "UNSAFE TRANSACTION. This transaction's code was not recognized and does not limit capabilities for all signers. Signing this transaction may make arbitrary actions on the chain including loss of all funds.",
               },
-              {
-                "header": "Transaction hash",
-                "prompt": "0j8JyVmew5_ibulW2WO-OXb9j5woNPX1T9Y1BQQvmFM",
-              },
               {
                 "header": "Sign for Address",
                 "prompt": "8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2",
@@ -659,11 +812,7 @@ describe("Signing tests", function() {
                 "header": "Signing",
                 "prompt": "Transaction",
               },
-              { "header": "On Network", "prompt": "testnet04" },
-              {
-                "header": "Requiring",
-                "prompt": "Capabilities",
-              },
+              { "header": "On Network", "prompt": "TESTNET" },
               {
                 "header": "Paying Gas",
                 "prompt": " ",
@@ -672,20 +821,13 @@ describe("Signing tests", function() {
                 "header": "Transfer 1",
                 "prompt": "2 from \"k:b9ac3ca5559cc6f394ea0e31c11be16efd6c6ff6804b98ce7cee496bcca96164\" to \"k:dfdb3896919544490637c0fd2f34f8bf4463d416fbd915990c8a136b1a970ca5\"",
               },
-              {
-                "header": "Of Key",
-                "prompt": "b9ac3ca5559cc6f394ea0e31c11be16efd6c6ff6804b98ce7cee496bcca96164",
-              },
               { "header": "On Chain", "prompt": "1" },
               { "header": "Using Gas", "prompt": "at most 600 at price 0.00001" },
-              {
-                "header": "Transaction hash",
-                "prompt": "9VlNQ6wmY5UpfOcazQNGpBZDt9Cd_sl_DO0POpiBDvU",
-              },
               {
                 "header": "Sign for Address",
                 "prompt": "8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2",
               },
+              { "header": "Total", "prompt": "2 KDA to 1 recipient" },
               {
                 "text": "Sign Transaction?",
                 "x": 19,
@@ -706,15 +848,43 @@ describe("Signing tests", function() {
                 "header": "Signing",
                 "prompt": "Transaction",
               },
-              { "header": "On Network", "prompt": "testnet04" },
+              { "header": "On Network", "prompt": "TESTNET" },
+              {
+                "header": "Paying Gas",
+                "prompt": " ",
+              },
+              {
+                "header": "Transfer 1",
+                "prompt": "Cross-chain 1.0 from \"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\" to \"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\" to chain \"0\"",
+              },
+              { "header": "On Chain", "prompt": "1" },
+              { "header": "Using Gas", "prompt": "at most 600 at price 0.00001" },
+              {
+                "header": "Sign for Address",
+                "prompt": "8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2",
+              },
+              { "header": "Total", "prompt": "1 KDA to 1 recipient" },
               {
-                "header": "Requiring",
-                "prompt": "Capabilities",
+                "text": "Sign Transaction?",
+                "x": 19,
+                "y": 11,
               },
               {
-                "header": "Of Key",
-                "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c",
+                "text": "Confirm",
+                "x": 43,
+                "y": 11,
+              }
+            ]
+          ));
+  it("Warns about a cross-chain transfer to the chain it is sent from.",
+          testTransaction(
+            "44'/626'/0'", "{\"networkId\":\"testnet04\",\"payload\":{\"exec\":{\"data\":{\"ks\":{\"keys\":[\"ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\"],\"pred\":\"keys-all\"}},\"code\":\"(coin.transfer-crosschain \\\"ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\\\" \\\"ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\\\" (read-keyset \\\"ks\\\") \\\"0\\\" 1.0)\"}},\"signers\":[{\"pubKey\":\"ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\",\"clist\":[{\"name\":\"coin.GAS\",\"args\":[]},{\"name\":\"coin.TRANSFER_XCHAIN\",\"args\":[\"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\",\"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\",1.0,\"0\"]}]}],\"meta\":{\"creationTime\":1640290267,\"ttl\":28800,\"gasLimit\":600,\"chainId\":\"0\",\"gasPrice\":0.00001,\"sender\":\"ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\"},\"nonce\":\"\\\"\\\\\\\"2021-12-23T20:12:06.664Z\\\\\\\"\\\"\"}",
+            [
+              {
+                "header": "Signing",
+                "prompt": "Transaction",
               },
+              { "header": "On Network", "prompt": "TESTNET" },
               {
                 "header": "Paying Gas",
                 "prompt": " ",
@@ -723,16 +893,14 @@ describe("Signing tests", function() {
                 "header": "Transfer 1",
                 "prompt": "Cross-chain 1.0 from \"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\" to \"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\" to chain \"0\"",
               },
-              { "header": "On Chain", "prompt": "1" },
+              { "header": "On Chain", "prompt": "0" },
+              { "header": "WARNING", "prompt": "Cross-chain transfer to chain 0, the chain it is sent from" },
               { "header": "Using Gas", "prompt": "at most 600 at price 0.00001" },
-              {
-                "header": "Transaction hash",
-                "prompt": "nw3YtHZ5EgogG2oQ9JbOOEqyhy7IN4cevGjdEKuWgQM",
-              },
               {
                 "header": "Sign for Address",
                 "prompt": "8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2",
               },
+              { "header": "Total", "prompt": "1 KDA to 1 recipient" },
               {
                 "text": "Sign Transaction?",
                 "x": 19,
@@ -753,33 +921,22 @@ describe("Signing tests", function() {
                 "header": "Signing",
                 "prompt": "Transaction",
               },
-              { "header": "On Network", "prompt": "testnet04" },
-              {
-                "header": "Requiring",
-                "prompt": "Capabilities",
-              },
-              {
-                "header": "Of Key",
-                "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c",
-              },
+              { "header": "On Network", "prompt": "TESTNET" },
               {
                 "header": "Paying Gas",
                 "prompt": " ",
               },
               {
                 "header": "Transfer 1",
-                "prompt": "Cross-chain {\"decimal\":\"123456789.0123456789\"} from \"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\" to \"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\" to chain \"0\"",
+                "prompt": "Cross-chain 123456789.0123456789 from \"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\" to \"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\" to chain \"0\"",
               },
               { "header": "On Chain", "prompt": "1" },
               { "header": "Using Gas", "prompt": "at most 600 at price 0.00001" },
-              {
-                "header": "Transaction hash",
-                "prompt": "gaYu1-LR6N9V0bUt1u_N9p4cbm_dwy7IeHC52rD92gs",
-              },
               {
                 "header": "Sign for Address",
                 "prompt": "8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2",
               },
+              { "header": "Total", "prompt": "123456789.0123456789 KDA to 1 recipient" },
               {
                 "text": "Sign Transaction?",
                 "x": 19,
@@ -800,33 +957,22 @@ describe("Signing tests", function() {
                 "header": "Signing",
                 "prompt": "Transaction",
               },
-              { "header": "On Network", "prompt": "testnet04" },
-              {
-                "header": "Requiring",
-                "prompt": "Capabilities",
-              },
-              {
-                "header": "Of Key",
-                "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c",
-              },
+              { "header": "On Network", "prompt": "TESTNET" },
               {
                 "header": "Paying Gas",
                 "prompt": " ",
               },
               {
                 "header": "Unknown Capability 1",
-                "prompt": "name: coin.TRANSFER_XCHAIN, arg 1: \"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\", arg 2: \"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\", arg 3: {\"decimal\":\"123456789.0123456789\"}, arg 4: \"0\", arg 5: true",
+                "prompt": "name: coin.TRANSFER_XCHAIN, arg 1: \"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\", arg 2: \"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c\", arg 3: 123456789.0123456789, arg 4: \"0\", arg 5: true",
               },
               { "header": "On Chain", "prompt": "1" },
               { "header": "Using Gas", "prompt": "at most 600 at price 0.00001" },
-              {
-                "header": "Transaction hash",
-                "prompt": "LY8HM_kQ2nRO7Wl0PD9flhbibi0K1CXxv27KmlDBQmo",
-              },
               {
                 "header": "Sign for Address",
                 "prompt": "8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2",
               },
+              { "header": "Total", "prompt": "Unknown amount of KDA to unknown recipients" },
               {
                 "text": "Sign Transaction?",
                 "x": 19,
@@ -845,9 +991,7 @@ describe("Signing tests", function() {
           '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a\\" \\"4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfd\\" 2.0)"}},"signers":[{"pubKey":"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a","clist":[{"args":["aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a","4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfa",1],"name":"coin.TRANSFER"},{"args":["aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a","4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfb",2],"name":"coin.TRANSFER"},{"args":["aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a","4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfc",3],"name":"coin.TRANSFER"},{"args":["aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a","4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfd",4],"name":"coin.TRANSFER"},{"args":[],"name":"coin.GAS"}]}],"meta":{"creationTime":1634009195,"ttl":900,"gasLimit":600,"chainId":"0","gasPrice":1.0e-6,"sender":"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a"},"nonce":"\\"2021-10-12T03:27:35.231Z\\""}',
           [
             { "header": "Signing", "prompt": "Transaction" },
-            { "header": "On Network", "prompt": "mainnet01" },
-            { "header": "Requiring", "prompt": "Capabilities" },
-            { "header": "Of Key", "prompt": "aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a" },
+            { "header": "On Network", "prompt": "Mainnet" },
             {
               "header": "Transfer 1",
               "prompt": "1 from \"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a\" to \"4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfa\"",
@@ -870,14 +1014,11 @@ describe("Signing tests", function() {
             },
             { "header": "On Chain", "prompt": "0" },
             { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-6" },
-            {
-              "header": "Transaction hash",
-              "prompt": "cYmajadc0EPG3ifvKR1Yd_-wlG79UZirK47JOREfZhk",
-            },
             {
               "header": "Sign for Address",
               "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c",
             },
+            { "header": "Total", "prompt": "10 KDA to 4 recipients" },
             {
               "text": "Sign Transaction?",
               "x": 19,
@@ -895,9 +1036,7 @@ describe("Signing tests", function() {
           '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a\\" \\"4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfd\\" 2.0)"}},"signers":[{"pubKey":"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a","clist":[{"args":["aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a","4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfa",1],"name":"coin.TRANSFER"},{"args":["aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a","4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfb",2,"3"],"name":"coin.TRANSFER_XCHAIN"},{"args":["aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a","4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfc",3,"2"],"name":"coin.TRANSFER_XCHAIN"},{"args":["aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a","4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfd",4],"name":"coin.TRANSFER"},{"args":[],"name":"coin.GAS"}]}],"meta":{"creationTime":1634009195,"ttl":900,"gasLimit":600,"chainId":"0","gasPrice":1.0e-6,"sender":"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a"},"nonce":"\\"2021-10-12T03:27:35.231Z\\""}',
           [
             { "header": "Signing", "prompt": "Transaction" },
-            { "header": "On Network", "prompt": "mainnet01" },
-            { "header": "Requiring", "prompt": "Capabilities" },
-            { "header": "Of Key", "prompt": "aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a" },
+            { "header": "On Network", "prompt": "Mainnet" },
             {
               "header": "Transfer 1",
               "prompt": "1 from \"aab7d3e457f3f78480832d6ac4ace7387f460620a63a5b68c8c799d6bff1566a\" to \"4c310df6224d674d80463a29cde00cb0ecfb71e0cfdce494243a61b8ea572dfa\"",
@@ -920,14 +1059,11 @@ describe("Signing tests", function() {
             },
             { "header": "On Chain", "prompt": "0" },
             { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-6" },
-            {
-              "header": "Transaction hash",
-              "prompt": "AoXqSSMScM_u4glsmLV3C8Eawexbm2YEFgFMHYFzm4o",
-            },
             {
               "header": "Sign for Address",
               "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c",
             },
+            { "header": "Total", "prompt": "10 KDA to 4 recipients" },
             {
               "text": "Sign Transaction?",
               "x": 19,
@@ -945,16 +1081,64 @@ describe("Signing tests", function() {
        '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"unknown-field":true,"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
        [
          { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" },
+         { "header": "On Network", "prompt": "Mainnet" },
          { "header": "Paying Gas", "prompt": " " },
          { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
          {
            "header": "CAUTION",
            "prompt": "'meta' field of transaction not recognized",
          },
-         { "header": "Transaction hash", "prompt": "fysHQicr1iPz-sbSntIM3Rx_Iw_agBhRxt-XL9X7ENk" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+  it("shows non-ASCII characters of account names escaped, with a warning",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"bøb\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","bøb",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Transfer 1", "prompt": '11 from "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" to "b\\u00F8b"' },
+         { "header": "WARNING", "prompt": "Account name has non-ASCII characters, which can look like other ones" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+  it("warns about escaped characters in account names",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"\\u0430lice\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","\\u0430lice",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Transfer 1", "prompt": '11 from "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" to "\\u0430lice"' },
+         { "header": "WARNING", "prompt": "Account name has non-ASCII characters, which can look like other ones" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
          { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
          {
            "text": "Sign Transaction?",
@@ -971,12 +1155,12 @@ describe("Signing tests", function() {
 });
 
 
-function testSignHash(path: string, hash: string, prompts: any[]) {
+function testSignHash(path: string, hash: string, prompts: any[], toggle = toggleHashSettings) {
      return async () => {
        await sendCommandAndAccept(
          async (kda : Kda) => {
            let pubkey = (await kda.getPublicKey(path)).publicKey;
-           await toggleHashSettings();
+           await toggle();
            await Axios.delete(BASE_URL + "/events");
            let rv = await kda.signHash(path, hash);
            expect(rv.signature.length).to.equal(128);
@@ -984,7 +1168,7 @@ function testSignHash(path: string, hash: string, prompts: any[]) {
            let pass = nacl.crypto_sign_verify_detached(Buffer.from(rv.signature, 'hex'), rawHash, Buffer.from(pubkey, 'hex'));
            expect(pass).to.equal(true);
            // reset setting
-           await toggleHashSettings();
+           await toggle();
          }, prompts);
      }
 }
@@ -1011,13 +1195,9 @@ function testSignHashFail2(path: string, hash: string) {
   }
 }
 
-let toggleHashSettings = async function() {
-  await Axios.post(BASE_URL + "/button/right", {"action":"press-and-release"});
-  await Axios.post(BASE_URL + "/button/both", {"action":"press-and-release"});
-  await Axios.post(BASE_URL + "/button/both", {"action":"press-and-release"});
-  await Axios.post(BASE_URL + "/button/right", {"action":"press-and-release"});
-  await Axios.post(BASE_URL + "/button/both", {"action":"press-and-release"});
-}
+let toggleHashSettings = () => toggleSetting(0);
+
+let toggleBlindSigningSession = () => toggleSetting(1);
 
 describe('Hash Signing Tests', function() {
   it("cannot sign a hash without settings enabled",
@@ -1076,30 +1256,95 @@ describe('Hash Signing Tests', function() {
          }
        ]
      ));
-})
-
-const WARNING_FOR_CAP_NOT_SHOWN = "Transaction too large for Ledger to display.  PROCEED WITH GREAT CAUTION.  Do you want to continue?";
-
-describe("Capability Signing tests", function() {
+  it("can sign a hash during a blind signing session",
+     testSignHash(
+       "0/0",
+       'ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c',
+       [
+         {
+           "header": "WARNING",
+           "prompt": "Blind Signing a Transaction Hash is a very unusual operation. Do not continue unless you know what you are doing",
+         },
+         { "header": "Transaction hash", "prompt": "_9jNed65Vvo8fZvg-DbyCshLFAFooIeoQr5HYOQOKxw" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Sign Transaction Hash?",
+           "x": 4,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ],
+       toggleBlindSigningSession
+     ));
+})
 
-  it("can sign an arbitrary cap with no args",
-     testTransaction(
+// Encodes a command as for SIGN_JSON_TX
+let lengthPrefixed = function(txn: string) {
+  const tx = Buffer.from(txn, "utf-8");
+  const size = Buffer.alloc(4);
+  size.writeUInt32LE(tx.length);
+  return Buffer.concat([size, tx]);
+}
+
+let encodeBip32Path = function(path: string) {
+  const steps = path.split("/").map(s => s.endsWith("'") ? (parseInt(s) | 0x80000000) >>> 0 : parseInt(s));
+  const bip32Path = Buffer.alloc(1 + 4 * steps.length);
+  bip32Path.writeUInt8(steps.length, 0);
+  steps.forEach((s, i) => bip32Path.writeUInt32LE(s, 1 + 4 * i));
+  return bip32Path;
+}
+
+// Sends the payload in chunks, as hw-app-kda does, and returns the response to the last one
+let sendChunked = async function(kda : Kda, ins: number, payload: Buffer) {
+  let rv = null;
+  for (let i = 0; i < payload.length; i += 230) {
+    rv = await kda.transport.send(0x00, ins, 0x00, 0x00, payload.slice(i, i + 230));
+  }
+  return rv;
+}
+
+// SIGN_TX_HASH_WITH_TX takes the same input as SIGN_JSON_TX, which hw-app-kda has no call for
+let signHashWithTx = async function(kda : Kda, path: string, txn: string) {
+  const rv = await sendChunked(kda, 0x05, Buffer.concat([lengthPrefixed(txn), encodeBip32Path(path)]));
+  return rv.slice(0, 64).toString("hex");
+}
+
+function testSignHashWithTx(path: string, txn: string, prompts: any[]) {
+     return async () => {
+       await sendCommandAndAccept(
+         async (kda : Kda) => {
+           let pubkey = (await kda.getPublicKey(path)).publicKey;
+           await Axios.delete(BASE_URL + "/events");
+           let signature = await signHashWithTx(kda, path, txn);
+           let hash = blake2b(32).update(Buffer.from(txn, "utf-8")).digest();
+           let pass = nacl.crypto_sign_verify_detached(Buffer.from(signature, 'hex'), hash, Buffer.from(pubkey, 'hex'));
+           expect(pass).to.equal(true);
+         }, prompts);
+     }
+}
+
+describe('Hash Signing With Transaction Tests', function() {
+  it("shows a fully parsed transaction before its hash",
+     testSignHashWithTx(
        "0/0",
-       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":[],"name":"mycoin.MY_TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
        [
-         { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" },
+         { "header": "Signing", "prompt": "Transaction Hash" },
+         { "header": "On Network", "prompt": "Mainnet" },
          { "header": "Paying Gas", "prompt": " " },
-         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER, no args" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
          { "header": "On Chain", "prompt": "0" },
          { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
-         { "header": "Transaction hash", "prompt": "hnaoFEVgtSMrwKbm2Ui4wnARtUwMo6rtB3fnvZGb8oE" },
+         { "header": "Transaction hash", "prompt": "fPSCfMUaoK1N31qwhwBFUPwG-YR_guPP894uixsNZgk" },
          { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
          {
-           "text": "Sign Transaction?",
-           "x": 19,
+           "text": "Sign Transaction Hash?",
+           "x": 4,
            "y": 11,
          },
          {
@@ -1109,25 +1354,119 @@ describe("Capability Signing tests", function() {
          }
        ]
      ));
+  it("skips the fields it can not parse and counts them",
+     testTransactionWithSettings(toggleAllowUnscoped, toggleAllowUnscoped,
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"weight":1,"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction Hash" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "WARNING", "prompt": "UNSAFE TRANSACTION. This transaction's code was not recognized and does not limit capabilities for all signers. Signing this transaction may make arbitrary actions on the chain including loss of all funds." },
+         { "header": "WARNING", "prompt": "1 field not shown" },
+         { "header": "Transaction hash", "prompt": "5CXEVG57cLaWC_P2YX02QqDTztNFnOj0dhUHlstwaJg" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Sign Transaction Hash?",
+           "x": 4,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ],
+       signHashWithTx
+     ));
+  // Any of the signers which were not shown could be the signing key, without capabilities
+  it("refuses to sign when the signers are dropped",
+     async () => {
+       await sendCommandExpectStatus(SW_UNSCOPED_SIGNER,
+         async (kda : Kda) => {
+           await signHashWithTx(kda, "0/0", '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"weight":1,"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}');
+         });
+     });
+  it("refuses to sign when there are no signers",
+     async () => {
+       await sendCommandExpectStatus(SW_UNSCOPED_SIGNER,
+         async (kda : Kda) => {
+           await signHashWithTx(kda, "0/0", '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}');
+         });
+     });
+  it("cannot sign an unrecognized transaction without blind signing enabled",
+     async () => {
+       await sendCommandExpectFail(
+         async (kda : Kda) => {
+           await signHashWithTx(kda, "0/0", '{"hello":"world"}');
+         });
+     });
+})
 
-  it("can sign an arbitrary cap with single string arg",
-     testTransaction(
+// SIGN_BATCH takes the number of commands, the commands as for SIGN_JSON_TX, and then the path
+const SW_INVALID_BATCH_SIZE = 0x6A8E;
+
+// Set by runTests; the Nano S takes smaller batches than the other devices
+const DEVICE = process.env.DEVICE || "nanos";
+const MAX_BATCH_SIZE = DEVICE == "nanos" ? 3 : 32;
+const BATCH_SIGNATURES_PER_REPLY = 3;
+
+let getBatchSignatures = (kda : Kda, start: number) => kda.transport.send(0x00, 0x0e, 0x00, 0x00, Buffer.from([start]));
+
+let signBatch = async function(kda : Kda, path: string, txns: string[]) {
+  const payload = Buffer.concat([Buffer.from([txns.length]), ...txns.map(lengthPrefixed), encodeBip32Path(path)]);
+  let rv = await sendChunked(kda, 0x06, payload);
+  // The signatures which do not fit in the response are fetched afterwards
+  for (let i = BATCH_SIGNATURES_PER_REPLY; i < txns.length; i += BATCH_SIGNATURES_PER_REPLY) {
+    rv = Buffer.concat([rv.slice(0, 64 * i), await getBatchSignatures(kda, i)]);
+  }
+  return txns.map((_, i) => rv.slice(64 * i, 64 * (i + 1)).toString("hex"));
+}
+
+function testSignBatch(path: string, txns: string[], prompts: any[]) {
+     return async () => {
+       await sendCommandAndAccept(
+         async (kda : Kda) => {
+           let pubkey = (await kda.getPublicKey(path)).publicKey;
+           await Axios.delete(BASE_URL + "/events");
+           let signatures = await signBatch(kda, path, txns);
+           txns.forEach((txn, i) => {
+             let hash = blake2b(32).update(Buffer.from(txn, "utf-8")).digest();
+             let pass = nacl.crypto_sign_verify_detached(Buffer.from(signatures[i], 'hex'), hash, Buffer.from(pubkey, 'hex'));
+             expect(pass).to.equal(true);
+           });
+         }, prompts);
+     }
+}
+
+describe('Batch Signing Tests', function() {
+  it("reviews each command and signs all of them after one approval",
+     testSignBatch(
        "0/0",
-       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"],"name":"mycoin.MY_TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
        [
-         { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" },
+         '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+         '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 12.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",12],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"1","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:54.700Z\\""}'
+       ],
+       [
+         { "header": "Signing", "prompt": "Command 1 of 2" },
+         { "header": "On Network", "prompt": "Mainnet" },
          { "header": "Paying Gas", "prompt": " " },
-         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\"" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
          { "header": "On Chain", "prompt": "0" },
          { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
-         { "header": "Transaction hash", "prompt": "kQqVYwYzDNSKqcRwJ3Yd4xgG2UW9j2sdcupQx-T6XEY" },
+         { "header": "Signing", "prompt": "Command 2 of 2" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Transfer 1", "prompt": "12 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "On Chain", "prompt": "1" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Summary", "prompt": "2 commands" },
          { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "23 KDA to 1 recipient" },
          {
-           "text": "Sign Transaction?",
-           "x": 19,
+           "text": "Sign Transactions?",
+           "x": 16,
            "y": 11,
          },
          {
@@ -1137,25 +1476,47 @@ describe("Capability Signing tests", function() {
          }
        ]
      ));
-
-  it("can sign an arbitrary cap with two string args",
-     testTransaction(
+  // Only the other devices take batches with more signatures than fit in a response
+  (DEVICE == "nanos" ? it.skip : it)("returns the signatures after the first ones with GET_BATCH_SIGNATURES",
+     testSignBatch(
        "0/0",
-       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","second arg"],"name":"mycoin.MY_TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
        [
-         { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" },
+         '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+         '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 12.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",12],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+         '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 13.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",13],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+         '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 14.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",14],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}'
+       ],
+       [
+         { "header": "Signing", "prompt": "Command 1 of 4" },
+         { "header": "On Network", "prompt": "Mainnet" },
          { "header": "Paying Gas", "prompt": " " },
-         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\", arg 2: \"second arg\"" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Signing", "prompt": "Command 2 of 4" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Transfer 1", "prompt": "12 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Signing", "prompt": "Command 3 of 4" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Transfer 1", "prompt": "13 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Signing", "prompt": "Command 4 of 4" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Transfer 1", "prompt": "14 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
          { "header": "On Chain", "prompt": "0" },
          { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
-         { "header": "Transaction hash", "prompt": "ONXn9kz2V9InGB-RddO3kUCy-GHQOEs8jRYqO2vzxuY" },
+         { "header": "Summary", "prompt": "4 commands" },
          { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "50 KDA to 1 recipient" },
          {
-           "text": "Sign Transaction?",
-           "x": 19,
+           "text": "Sign Transactions?",
+           "x": 16,
            "y": 11,
          },
          {
@@ -1165,22 +1526,60 @@ describe("Capability Signing tests", function() {
          }
        ]
      ));
+  it("cannot sign more commands than the device takes",
+     async () => {
+       await sendCommandExpectStatus(SW_INVALID_BATCH_SIZE,
+         async (kda : Kda) => {
+           await signBatch(kda, "0/0", Array(MAX_BATCH_SIZE + 1).fill('{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}'));
+         });
+     });
+  it("has no signatures to return without a batch",
+     async () => {
+       await sendCommandExpectStatus(SW_INVALID_BATCH_SIZE,
+         async (kda : Kda) => {
+           await getBatchSignatures(kda, BATCH_SIGNATURES_PER_REPLY);
+         });
+     });
+})
 
-  it("can sign an arbitrary cap with two string args and one number",
-     testTransaction(
+// SIGN_WRAPPED_TX takes a {hash, sigs, cmd} object in place of the transaction of SIGN_JSON_TX
+let signWrapped = async function(kda : Kda, path: string, wrapper: string) {
+  const rv = await sendChunked(kda, 0x07, Buffer.concat([lengthPrefixed(wrapper), encodeBip32Path(path)]));
+  return rv.slice(0, 64).toString("hex");
+}
+
+let wrapCommand = function(txn: string, hash = Buffer.from(blake2b(32).update(Buffer.from(txn, "utf-8")).digest()).toString("base64url")) {
+  return JSON.stringify({ "hash": hash, "sigs": [null], "cmd": txn });
+}
+
+function testSignWrapped(path: string, txn: string, prompts: any[]) {
+     return async () => {
+       await sendCommandAndAccept(
+         async (kda : Kda) => {
+           let pubkey = (await kda.getPublicKey(path)).publicKey;
+           await Axios.delete(BASE_URL + "/events");
+           let signature = await signWrapped(kda, path, wrapCommand(txn));
+           let hash = blake2b(32).update(Buffer.from(txn, "utf-8")).digest();
+           let pass = nacl.crypto_sign_verify_detached(Buffer.from(signature, 'hex'), hash, Buffer.from(pubkey, 'hex'));
+           expect(pass).to.equal(true);
+         }, prompts);
+     }
+}
+
+describe('Wrapped Command Signing Tests', function() {
+  it("can sign the cmd string of a wrapped command",
+     testSignWrapped(
        "0/0",
-       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","second arg",22.2],"name":"mycoin.MY_TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
        [
          { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" },
+         { "header": "On Network", "prompt": "Mainnet" },
          { "header": "Paying Gas", "prompt": " " },
-         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\", arg 2: \"second arg\", arg 3: 22.2" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
          { "header": "On Chain", "prompt": "0" },
          { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
-         { "header": "Transaction hash", "prompt": "OEV1W2Adz7vvU3qYzV9V48pDhxRdFDi2KG4JXx73WTA" },
          { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
          {
            "text": "Sign Transaction?",
            "x": 19,
@@ -1193,22 +1592,130 @@ describe("Capability Signing tests", function() {
          }
        ]
      ));
+  it("rejects a wrapped command whose hash does not match",
+     async () => {
+       await sendCommandExpectFail(
+         async (kda : Kda) => {
+           await signWrapped(kda, "0/0", wrapCommand('{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}', "_9jNed65Vvo8fZvg-DbyCshLFAFooIeoQr5HYOQOKxw"));
+         });
+     });
+})
 
-  it("can sign an arbitrary cap with various json types",
+function testTransactionRejected(path: string, txn: string, statusCode: number) {
+     return async () => {
+       await sendCommandExpectStatus(statusCode,
+         async (kda : Kda) => {
+           await kda.signTransaction(path, Buffer.from(txn, "utf-8").toString("hex"));
+         });
+     }
+}
+
+// A node could take a different value for a repeated key than the one shown on the device
+describe("Duplicate Key tests", function() {
+  it("rejects a command with two networkId keys",
+     testTransactionRejected(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\"","networkId":"testnet04"}',
+       SW_DUPLICATE_KEY
+     ));
+  it("rejects a command with two signers keys",
+     testTransactionRejected(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\"","signers":[]}',
+       SW_DUPLICATE_KEY
+     ));
+  it("rejects a command with two chainId keys in meta",
+     testTransactionRejected(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","chainId":"1","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       SW_DUPLICATE_KEY
+     ));
+  it("rejects a signer with two pubKey keys",
+     testTransactionRejected(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","pubKey":"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       SW_DUPLICATE_KEY
+     ));
+  it("rejects a capability with two name keys",
+     testTransactionRejected(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS","name":"coin.TRANSFER"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       SW_DUPLICATE_KEY
+     ));
+  it("rejects a payload with two exec keys",
+     testTransactionRejected(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.details \\"k\\")"},"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       SW_DUPLICATE_KEY
+     ));
+  it("rejects a wrapped command with two hash keys",
+     async () => {
+       await sendCommandExpectStatus(SW_DUPLICATE_KEY,
+         async (kda : Kda) => {
+           await signWrapped(kda, "0/0", wrapCommand('{"networkId":"mainnet01"}').replace('{"hash":', '{"hash":"_9jNed65Vvo8fZvg-DbyCshLFAFooIeoQr5HYOQOKxw","hash":'));
+         });
+     });
+})
+
+describe("Chain Id tests", function() {
+  it("rejects chain 20, which does not exist",
+     testTransactionRejected(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"20","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       SW_INVALID_CHAIN
+     ));
+  it("rejects a chain id with a leading zero",
+     testTransactionRejected(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"01","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       SW_INVALID_CHAIN
+     ));
+  it("rejects a cross-chain transfer to a chain which does not exist",
+     testTransactionRejected(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11,"20"],"name":"coin.TRANSFER_XCHAIN"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       SW_INVALID_CHAIN
+     ));
+  it("rejects building a cross-chain transfer to a chain which does not exist",
+     async () => {
+       await sendCommandExpectStatus(SW_INVALID_CHAIN,
+         async (kda : Kda) => {
+           await kda.signTransferCrossChainTx({
+             path: "44'/626'/0'/0/0",
+             recipient: '83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790',
+             recipient_chainId: 20,
+             amount: "23.67",
+             network: "testnet04",
+             chainId: 1,
+             gasPrice: "1.0e-6",
+             gasLimit: "2300",
+             creationTime: 1665722463,
+             ttl: "600",
+             nonce: "2022-10-14 04:41:03.193557 UTC"
+           });
+         });
+     });
+})
+
+// From the default 0.001 to 0.0001, and back
+let lowerGasPriceLimit = () => cycleThreshold(0);
+let resetGasPriceLimit = () => cycleThreshold(0, 2);
+
+describe("Gas and TTL warning tests", function() {
+  it("warns about a high gas price",
      testTransaction(
        "0/0",
-       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":[{"key1":{"key2":"val2"},"key3":-2.46,"key4":{"key5":true,"key6":{"key7":0.01},"key8":["a",false,null,9,10.23,-58.24]}},{},[],false,null],"name":"mycoin.MY_TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":0.01,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
        [
          { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" },
+         { "header": "On Network", "prompt": "Mainnet" },
          { "header": "Paying Gas", "prompt": " " },
-         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER, arg 1: {\"key1\":{\"key2\":\"val2\"},\"key3\":-2.46,\"key4\":{\"key5\":true,\"key6\":{\"key7\":0.01},\"key8\":[\"a\",false,null,9,10.23,-58.24]}}, arg 2: {}, arg 3: [], arg 4: false, arg 5: null" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
          { "header": "On Chain", "prompt": "0" },
-         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
-         { "header": "Transaction hash", "prompt": "5RygRqoczKtecEebMtaPLrulHa5aprNcjkRhMAAogNc" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 0.01" },
+         { "header": "WARNING", "prompt": "Gas price of 0.01 KDA is above 0.001" },
          { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
          {
            "text": "Sign Transaction?",
            "x": 19,
@@ -1221,26 +1728,20 @@ describe("Capability Signing tests", function() {
          }
        ]
      ));
-
-  it("can sign multiple arbitrary caps",
+  it("warns about a gas limit above the block gas limit",
      testTransaction(
        "0/0",
-       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":[],"name":"mycoin.MY_TRANSFER0"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"],"name":"mycoin.MY_TRANSFER1"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","second arg"],"name":"mycoin.MY_TRANSFER2"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","second arg",22.2],"name":"mycoin.MY_TRANSFER3"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","second arg",5000,22.2],"name":"mycoin.MY_TRANSFER4"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":200000,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
        [
          { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" },
+         { "header": "On Network", "prompt": "Mainnet" },
          { "header": "Paying Gas", "prompt": " " },
-         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER0, no args" },
-         { "header": "Unknown Capability 2", "prompt": "name: mycoin.MY_TRANSFER1, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\"" },
-         { "header": "Unknown Capability 3", "prompt": "name: mycoin.MY_TRANSFER2, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\", arg 2: \"second arg\"" },
-         { "header": "Unknown Capability 4", "prompt": "name: mycoin.MY_TRANSFER3, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\", arg 2: \"second arg\", arg 3: 22.2" },
-         { "header": "Unknown Capability 5", "prompt": "name: mycoin.MY_TRANSFER4, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\", arg 2: \"second arg\", arg 3: 5000, arg 4: 22.2" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
          { "header": "On Chain", "prompt": "0" },
-         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
-         { "header": "Transaction hash", "prompt": "QJDO0ks635Xpnq2GC85cqoQUxLgESujMgun7NUgrf5E" },
+         { "header": "Using Gas", "prompt": "at most 200000 at price 1.0e-5" },
+         { "header": "WARNING", "prompt": "Gas limit of 200000 is above 150000" },
          { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
          {
            "text": "Sign Transaction?",
            "x": 19,
@@ -1253,26 +1754,20 @@ describe("Capability Signing tests", function() {
          }
        ]
      ));
-
-  it("can sign multiple arbitrary caps along with multiple transfers",
+  it("warns about a long TTL",
      testTransaction(
        "0/0",
-       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":[],"name":"mycoin.MY_TRANSFER0"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"],"name":"mycoin.MY_TRANSFER1"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471791",4],"name":"coin.TRANSFER"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471791",22.2,"4"],"name":"coin.TRANSFER_XCHAIN"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471792",5000,"0"],"name":"mycoin.MY_TRANSFER4"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":7776000,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
        [
          { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" },
+         { "header": "On Network", "prompt": "Mainnet" },
          { "header": "Paying Gas", "prompt": " " },
-         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER0, no args" },
-         { "header": "Unknown Capability 2", "prompt": "name: mycoin.MY_TRANSFER1, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\"" },
-         { "header": "Transfer 1", "prompt": "4 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471791\""},
-         { "header": "Transfer 2", "prompt": "Cross-chain 22.2 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471791\" to chain \"4\""},
-         { "header": "Unknown Capability 3", "prompt": "name: mycoin.MY_TRANSFER4, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\", arg 2: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471792\", arg 3: 5000, arg 4: \"0\"" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
          { "header": "On Chain", "prompt": "0" },
          { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
-         { "header": "Transaction hash", "prompt": "yMXcVG1vcnLrbtdiKHI1MAYgrBgoDqr15YSRID70DyU" },
+         { "header": "WARNING", "prompt": "Valid for 7776000 seconds, more than 86400" },
          { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
          {
            "text": "Sign Transaction?",
            "x": 19,
@@ -1285,22 +1780,20 @@ describe("Capability Signing tests", function() {
          }
        ]
      ));
-  it("can sign an arbitrary cap with large number of args, showing warning",
-     testTransaction(
+  it("warns about a gas price above the limit chosen in the settings",
+     testTransactionWithSettings(lowerGasPriceLimit, resetGasPriceLimit,
        "0/0",
-       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","adfas",4,5,6,7,8],"name":"mycoin.MY_TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":0.0005,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
        [
          { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" },
+         { "header": "On Network", "prompt": "Mainnet" },
          { "header": "Paying Gas", "prompt": " " },
-         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER, args cannot be displayed on Ledger" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
          { "header": "On Chain", "prompt": "0" },
-         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
-         { "header": "WARNING", "prompt": WARNING_FOR_CAP_NOT_SHOWN },
-         { "header": "Transaction hash", "prompt": "Y2q38WX4sd5fWzw2knr7mfAltsaYxhWnDGtFaZ7NV40" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 0.0005" },
+         { "header": "WARNING", "prompt": "Gas price of 0.0005 KDA is above 0.0001" },
          { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
          {
            "text": "Sign Transaction?",
            "x": 19,
@@ -1313,23 +1806,30 @@ describe("Capability Signing tests", function() {
          }
        ]
      ));
+})
 
-  it("can sign an arbitrary cap with BIG JSON in args, showing warning", async function () {
-    this.timeout(60*1000);
-    let path = "0/0";
-    let file = "marmalade-tx.json";
-    let prompts =
+// A signature without capabilities can be used for anything the key is allowed to do
+describe("Unscoped signer tests", function() {
+  it("refuses to sign for a key without capabilities",
+     testTransactionRejected(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c"}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       SW_UNSCOPED_SIGNER
+     ));
+  it("signs for a key without capabilities with Allow Unscoped Sigs enabled",
+     testTransactionWithSettings(toggleAllowUnscoped, toggleAllowUnscoped,
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c"}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
        [
          { "header": "Signing", "prompt": "Transaction" },
-         { "header": "On Network", "prompt": "mainnet01" },
-         { "header": "Requiring", "prompt": "Capabilities" },
-         { "header": "Of Key", "prompt": "83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790" },
-         { "header": "Paying Gas", "prompt": " " },
-         { "header": "Unknown Capability 1", "prompt": "name: marmalade.ledger.transfer, args cannot be displayed on Ledger" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Unscoped Signer", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
          { "header": "On Chain", "prompt": "0" },
          { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
-         { "header": "WARNING", "prompt": WARNING_FOR_CAP_NOT_SHOWN },
-         { "header": "Transaction hash", "prompt": "TX4rKze978k7T-MAzSJfTTHy1WCwAK8yi4RhZfAQzQE" },
+         {
+           "header": "WARNING",
+           "prompt": "UNSAFE TRANSACTION. This transaction's code was not recognized and does not limit capabilities for all signers. Signing this transaction may make arbitrary actions on the chain including loss of all funds.",
+         },
          { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
          {
            "text": "Sign Transaction?",
@@ -1341,24 +1841,383 @@ describe("Capability Signing tests", function() {
            "x": 43,
            "y": 11,
          }
-       ];
+       ]
+     ));
+})
 
-    await sendCommandAndAccept(
-      async (kda : Kda) => {
-        let pubkey = (await kda.getPublicKey(path)).publicKey;
-        await Axios.delete(BASE_URL + "/events");
+// The JSON text of the value starting at `start`, as written in `text`
+let rawJsonValue = function(text: string, start: number) {
+  let depth = 0;
+  let inString = false;
+  for (let i = start; i < text.length; i++) {
+    let c = text[i];
+    if (inString) {
+      if (c == "\\") i++;
+      else if (c == '"') inString = false;
+    } else if (c == '"') {
+      inString = true;
+    } else if (c == "[" || c == "{") {
+      depth++;
+    } else if (c == "]" || c == "}") {
+      depth--;
+    }
+    if (depth == 0 && !inString && (c == '"' || c == "]" || c == "}")) return text.substring(start, i + 1);
+  }
+  throw new Error("Unterminated JSON value");
+}
 
-        let txn = await fs.readFileSync(file);
-        let rv = await kda.signTransaction(path, txn);
-        expect(rv.signature.length).to.equal(128);
-        let hash = blake2b(32).update(txn).digest();
-        let pass = nacl.crypto_sign_verify_detached(Buffer.from(rv.signature, 'hex'), hash, Buffer.from(pubkey, 'hex'));
-        expect(pass).to.equal(true);
-      }, prompts);
-  });
-})
+// The prompts of an arg shown on its own, in pages of 128 characters
+let streamedArgPrompts = function(index: number, arg: string) {
+  let pages = arg.match(/[^]{1,128}/g);
+  if (pages.length == 1) return [{ "header": "Arg " + index, "prompt": arg }];
+  return pages.map((page, i) => ({ "header": "Arg " + index + ", part " + (i + 1), "prompt": page }));
+}
 
-function checkSignTransferTxAPIs(apiName: any,
+describe("Capability Signing tests", function() {
+
+  it("can sign an arbitrary cap with no args",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":[],"name":"mycoin.MY_TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER, no args" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("can sign an arbitrary cap with single string arg",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"],"name":"mycoin.MY_TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("can sign an arbitrary cap with two string args",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","second arg"],"name":"mycoin.MY_TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\", arg 2: \"second arg\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("can sign an arbitrary cap with two string args and one number",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","second arg",22.2],"name":"mycoin.MY_TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\", arg 2: \"second arg\", arg 3: 22.2" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("can sign an arbitrary cap with various json types",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":[{"key1":{"key2":"val2"},"key3":-2.46,"key4":{"key5":true,"key6":{"key7":0.01},"key8":["a",false,null,9,10.23,-58.24]}},{},[],false,null],"name":"mycoin.MY_TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER, arg 1: {\"key1\":{\"key2\":\"val2\"},\"key3\":-2.46,\"key4\":{\"key5\":true,\"key6\":{\"key7\":0.01},\"key8\":[\"a\",false,null,9,10.23,-58.24]}}, arg 2: {}, arg 3: [], arg 4: false, arg 5: null" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("shows Pact keysets, times, module references and integers in args",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":[{"keys":["alice"],"pred":"keys-all"},{"time":"2021-10-12T03:27:53Z"},{"refName":{"namespace":null,"name":"coin"},"refSpec":[{"namespace":null,"name":"fungible-v2"}]},{"int":5}],"name":"mycoin.MY_TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER, arg 1: KeySet {keys: [alice], pred: keys-all}, arg 2: 2021-10-12T03:27:53Z, arg 3: ModRef {name: coin, spec: [fungible-v2]}, arg 4: 5" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("recognizes a transfer with a Pact integer amount",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",{"int":"11"}],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("can sign multiple arbitrary caps",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":[],"name":"mycoin.MY_TRANSFER0"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"],"name":"mycoin.MY_TRANSFER1"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","second arg"],"name":"mycoin.MY_TRANSFER2"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","second arg",22.2],"name":"mycoin.MY_TRANSFER3"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","second arg",5000,22.2],"name":"mycoin.MY_TRANSFER4"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER0, no args" },
+         { "header": "Unknown Capability 2", "prompt": "name: mycoin.MY_TRANSFER1, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\"" },
+         { "header": "Unknown Capability 3", "prompt": "name: mycoin.MY_TRANSFER2, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\", arg 2: \"second arg\"" },
+         { "header": "Unknown Capability 4", "prompt": "name: mycoin.MY_TRANSFER3, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\", arg 2: \"second arg\", arg 3: 22.2" },
+         { "header": "Unknown Capability 5", "prompt": "name: mycoin.MY_TRANSFER4, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\", arg 2: \"second arg\", arg 3: 5000, arg 4: 22.2" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("can sign multiple arbitrary caps along with multiple transfers",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":[],"name":"mycoin.MY_TRANSFER0"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"],"name":"mycoin.MY_TRANSFER1"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471791",4],"name":"coin.TRANSFER"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471791",22.2,"4"],"name":"coin.TRANSFER_XCHAIN"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471792",5000,"0"],"name":"mycoin.MY_TRANSFER4"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER0, no args" },
+         { "header": "Unknown Capability 2", "prompt": "name: mycoin.MY_TRANSFER1, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\"" },
+         { "header": "Transfer 1", "prompt": "4 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471791\""},
+         { "header": "Transfer 2", "prompt": "Cross-chain 22.2 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471791\" to chain \"4\""},
+         { "header": "Unknown Capability 3", "prompt": "name: mycoin.MY_TRANSFER4, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\", arg 2: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471792\", arg 3: 5000, arg 4: \"0\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "26.2 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+  it("can sign an arbitrary cap with large number of args, showing each arg",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","adfas",4,5,6,7,8],"name":"mycoin.MY_TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Arg 1", "prompt": "\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\"" },
+         { "header": "Arg 2", "prompt": "\"adfas\"" },
+         { "header": "Arg 3", "prompt": "4" },
+         { "header": "Arg 4", "prompt": "5" },
+         { "header": "Arg 5", "prompt": "6" },
+         { "header": "Arg 6", "prompt": "7" },
+         { "header": "Arg 7", "prompt": "8" },
+         { "header": "Unknown Capability 1", "prompt": "name: mycoin.MY_TRANSFER, with the 7 args above" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("shows a coin.TRANSFER with too many args as too large to display, of an unknown amount",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11,1,2,3],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Arg 1", "prompt": "\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\"" },
+         { "header": "Arg 2", "prompt": "\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "Arg 3", "prompt": "11" },
+         { "header": "Arg 4", "prompt": "1" },
+         { "header": "Arg 5", "prompt": "2" },
+         { "header": "Arg 6", "prompt": "3" },
+         { "header": "Unknown Capability 1", "prompt": "name: coin.TRANSFER, with the 6 args above" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "WARNING", "prompt": "Transaction too large for Ledger to display.  PROCEED WITH GREAT CAUTION.  Do you want to continue?" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "Unknown amount of KDA to unknown recipients" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("can sign an arbitrary cap with BIG JSON in args, showing it page by page", async function () {
+    this.timeout(600*1000);
+    let path = "0/0";
+    let file = "marmalade-tx.json";
+    let raw = fs.readFileSync(file, "utf-8");
+    let arg = rawJsonValue(raw, raw.indexOf('{"args":[{"hash"') + '{"args":['.length);
+    let prompts =
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         ...streamedArgPrompts(1, arg),
+         { "header": "Unknown Capability 1", "prompt": "name: marmalade.ledger.transfer, with the arg above" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ];
+
+    await sendCommandAndAccept(
+      async (kda : Kda) => {
+        let pubkey = (await kda.getPublicKey(path)).publicKey;
+        await Axios.delete(BASE_URL + "/events");
+
+        let txn = await fs.readFileSync(file);
+        let rv = await kda.signTransaction(path, txn);
+        expect(rv.signature.length).to.equal(128);
+        let hash = blake2b(32).update(txn).digest();
+        let pass = nacl.crypto_sign_verify_detached(Buffer.from(rv.signature, 'hex'), hash, Buffer.from(pubkey, 'hex'));
+        expect(pass).to.equal(true);
+      }, prompts);
+  });
+})
+
+function checkSignTransferTxAPIs(apiName: any,
                         params: any,
                         txn: string,
                         prompts: any[]) {
@@ -1439,14 +2298,75 @@ describe('Create Tx tests', function() {
          {"text": "Confirm", "x": 43, "y": 11,}
        ]
      ));
-  it("can build a cross-chain transfer tx",
+  it("can build a cross-chain transfer tx",
+     checkSignTransferTxAPIs(
+       "signTransferCrossChainTx",
+       {
+         path: "44'/626'/0'/0/0",
+         recipient: '83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790',
+         recipient_chainId: 2,
+         amount: "23.67",
+         network: "testnet04",
+         chainId: 1,
+         gasPrice: "1.0e-6",
+         gasLimit: "2300",
+         creationTime: 1665722463,
+         ttl: "600",
+         nonce: "2022-10-14 04:41:03.193557 UTC"
+       },
+       "{\"networkId\":\"testnet04\",\"payload\":{\"exec\":{\"data\":{\"ks\":{\"pred\":\"keys-all\",\"keys\":[\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\"]}},\"code\":\"(coin.transfer-crosschain \\\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\\\" \\\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\\" (read-keyset \\\"ks\\\") \\\"2\\\" 23.67)\"}},\"signers\":[{\"pubKey\":\"9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"clist\":[{\"args\":[\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\",23.67,\"2\"],\"name\":\"coin.TRANSFER_XCHAIN\"},{\"args\":[],\"name\":\"coin.GAS\"}]}],\"meta\":{\"creationTime\":1665722463,\"ttl\":600,\"gasLimit\":2300,\"chainId\":\"1\",\"gasPrice\":1.0e-6,\"sender\":\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\"},\"nonce\":\"2022-10-14 04:41:03.193557 UTC\"}",
+       [
+         { "header": "Transfer", "prompt": "KDA" },
+         { "header": "From", "prompt": "k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995", "paginate": true },
+         { "header": "To", "prompt": "k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790", "paginate": true },
+         { "header": "To Chain", "prompt": "2" },
+         { "header": "Amount", "prompt": "23.67" },
+         { "header": "Paying Gas (1/2)", "prompt": "At most 2300" },
+         { "header": "Paying Gas (2/2)", "prompt": "Price 1.0e-6" },
+         {"text": "Sign Transaction?", "x": 19, "y": 11,},
+         {"text": "Confirm", "x": 43, "y": 11,}
+       ]
+     ));
+
+  it("can build a custom token transfer tx",
+     checkSignTransferTxAPIs(
+       "signTransferTx",
+       {
+         path: "44'/626'/0'/0/0",
+         recipient: '83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790',
+         amount: "1.23",
+         namespace: "free",
+         module: "mytoken-123",
+         network: "testnet04",
+         chainId: 0,
+         gasPrice: "1.0e-6",
+         gasLimit: "2300",
+         creationTime: 1665647810,
+         ttl: "600",
+         nonce: "2022-10-13 07:56:50.893257 UTC"
+       },
+       "{\"networkId\":\"testnet04\",\"payload\":{\"exec\":{\"data\":{},\"code\":\"(free.mytoken-123.transfer \\\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\\\" \\\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\\" 1.23)\"}},\"signers\":[{\"pubKey\":\"9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"clist\":[{\"args\":[\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\",1.23],\"name\":\"free.mytoken-123.TRANSFER\"},{\"args\":[],\"name\":\"coin.GAS\"}]}],\"meta\":{\"creationTime\":1665647810,\"ttl\":600,\"gasLimit\":2300,\"chainId\":\"0\",\"gasPrice\":1.0e-6,\"sender\":\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\"},\"nonce\":\"2022-10-13 07:56:50.893257 UTC\"}",
+       [
+         { "header": "Transfer", "prompt": "free.mytoken-123" },
+         { "header": "From", "prompt": "k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995", "paginate": true },
+         { "header": "To", "prompt": "k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790", "paginate": true },
+         { "header": "Amount", "prompt": "1.23" },
+         { "header": "Paying Gas (1/2)", "prompt": "At most 2300" },
+         { "header": "Paying Gas (2/2)", "prompt": "Price 1.0e-6" },
+         {"text": "Sign Transaction?", "x": 19, "y": 11,},
+         {"text": "Confirm", "x": 43, "y": 11,}
+       ]
+     ));
+
+  it("can build a custom token transfer-create tx",
      checkSignTransferTxAPIs(
-       "signTransferCrossChainTx",
+       "signTransferCreateTx",
        {
          path: "44'/626'/0'/0/0",
          recipient: '83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790',
-         recipient_chainId: 2,
          amount: "23.67",
+         namespace: "free",
+         module: "mytoken-123",
          network: "testnet04",
          chainId: 1,
          gasPrice: "1.0e-6",
@@ -1455,12 +2375,11 @@ describe('Create Tx tests', function() {
          ttl: "600",
          nonce: "2022-10-14 04:41:03.193557 UTC"
        },
-       "{\"networkId\":\"testnet04\",\"payload\":{\"exec\":{\"data\":{\"ks\":{\"pred\":\"keys-all\",\"keys\":[\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\"]}},\"code\":\"(coin.transfer-crosschain \\\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\\\" \\\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\\" (read-keyset \\\"ks\\\") \\\"2\\\" 23.67)\"}},\"signers\":[{\"pubKey\":\"9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"clist\":[{\"args\":[\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\",23.67,\"2\"],\"name\":\"coin.TRANSFER_XCHAIN\"},{\"args\":[],\"name\":\"coin.GAS\"}]}],\"meta\":{\"creationTime\":1665722463,\"ttl\":600,\"gasLimit\":2300,\"chainId\":\"1\",\"gasPrice\":1.0e-6,\"sender\":\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\"},\"nonce\":\"2022-10-14 04:41:03.193557 UTC\"}",
+       "{\"networkId\":\"testnet04\",\"payload\":{\"exec\":{\"data\":{\"ks\":{\"pred\":\"keys-all\",\"keys\":[\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\"]}},\"code\":\"(free.mytoken-123.transfer-create \\\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\\\" \\\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\\" (read-keyset \\\"ks\\\") 23.67)\"}},\"signers\":[{\"pubKey\":\"9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"clist\":[{\"args\":[\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\",23.67],\"name\":\"free.mytoken-123.TRANSFER\"},{\"args\":[],\"name\":\"coin.GAS\"}]}],\"meta\":{\"creationTime\":1665722463,\"ttl\":600,\"gasLimit\":2300,\"chainId\":\"1\",\"gasPrice\":1.0e-6,\"sender\":\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\"},\"nonce\":\"2022-10-14 04:41:03.193557 UTC\"}",
        [
-         { "header": "Transfer", "prompt": "KDA" },
+         { "header": "Transfer", "prompt": "free.mytoken-123" },
          { "header": "From", "prompt": "k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995", "paginate": true },
          { "header": "To", "prompt": "k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790", "paginate": true },
-         { "header": "To Chain", "prompt": "2" },
          { "header": "Amount", "prompt": "23.67" },
          { "header": "Paying Gas (1/2)", "prompt": "At most 2300" },
          { "header": "Paying Gas (2/2)", "prompt": "Price 1.0e-6" },
@@ -1468,95 +2387,870 @@ describe('Create Tx tests', function() {
          {"text": "Confirm", "x": 43, "y": 11,}
        ]
      ));
-
-  it("can build a custom token transfer tx",
+  it("can build a custom token cross-chain transfer tx",
      checkSignTransferTxAPIs(
-       "signTransferTx",
+       "signTransferCrossChainTx",
        {
          path: "44'/626'/0'/0/0",
          recipient: '83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790',
-         amount: "1.23",
+         recipient_chainId: 2,
+         amount: "23.67",
          namespace: "free",
          module: "mytoken-123",
          network: "testnet04",
-         chainId: 0,
+         chainId: 1,
          gasPrice: "1.0e-6",
          gasLimit: "2300",
-         creationTime: 1665647810,
+         creationTime: 1665722463,
          ttl: "600",
-         nonce: "2022-10-13 07:56:50.893257 UTC"
+         nonce: "2022-10-14 04:41:03.193557 UTC"
        },
-       "{\"networkId\":\"testnet04\",\"payload\":{\"exec\":{\"data\":{},\"code\":\"(free.mytoken-123.transfer \\\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\\\" \\\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\\" 1.23)\"}},\"signers\":[{\"pubKey\":\"9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"clist\":[{\"args\":[\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\",1.23],\"name\":\"free.mytoken-123.TRANSFER\"},{\"args\":[],\"name\":\"coin.GAS\"}]}],\"meta\":{\"creationTime\":1665647810,\"ttl\":600,\"gasLimit\":2300,\"chainId\":\"0\",\"gasPrice\":1.0e-6,\"sender\":\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\"},\"nonce\":\"2022-10-13 07:56:50.893257 UTC\"}",
+       "{\"networkId\":\"testnet04\",\"payload\":{\"exec\":{\"data\":{\"ks\":{\"pred\":\"keys-all\",\"keys\":[\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\"]}},\"code\":\"(free.mytoken-123.transfer-crosschain \\\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\\\" \\\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\\" (read-keyset \\\"ks\\\") \\\"2\\\" 23.67)\"}},\"signers\":[{\"pubKey\":\"9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"clist\":[{\"args\":[\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\",23.67,\"2\"],\"name\":\"free.mytoken-123.TRANSFER_XCHAIN\"},{\"args\":[],\"name\":\"coin.GAS\"}]}],\"meta\":{\"creationTime\":1665722463,\"ttl\":600,\"gasLimit\":2300,\"chainId\":\"1\",\"gasPrice\":1.0e-6,\"sender\":\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\"},\"nonce\":\"2022-10-14 04:41:03.193557 UTC\"}",
        [
          { "header": "Transfer", "prompt": "free.mytoken-123" },
          { "header": "From", "prompt": "k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995", "paginate": true },
          { "header": "To", "prompt": "k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790", "paginate": true },
-         { "header": "Amount", "prompt": "1.23" },
+         { "header": "To Chain", "prompt": "2" },
+         { "header": "Amount", "prompt": "23.67" },
          { "header": "Paying Gas (1/2)", "prompt": "At most 2300" },
          { "header": "Paying Gas (2/2)", "prompt": "Price 1.0e-6" },
          {"text": "Sign Transaction?", "x": 19, "y": 11,},
          {"text": "Confirm", "x": 43, "y": 11,}
        ]
      ));
+  })
+
+const SW_INVALID_DESCRIPTOR = 0x6A85;
+const SW_UNTRUSTED_DESCRIPTOR = 0x6A86;
+
+// Only the test-descriptors derivations build the app with the test_descriptor_key feature, trusting this key
+let descriptorKey = () => nacl.crypto_sign_seed_keypair(Buffer.from("Kadena capability descriptors!!!", "utf-8"));
+
+type DescribedAmount = { index: number, decimals: number, token: string, receiver?: number };
+
+let encodeDescriptor = function(name: string, title: string, labels: string[], amount?: DescribedAmount) {
+  const text = (s: string) => Buffer.concat([Buffer.from([s.length]), Buffer.from(s, "utf-8")]);
+  const encodedAmount = amount === undefined ? [Buffer.from([0xFF])] : [
+    Buffer.from([amount.index, amount.decimals]), text(amount.token), Buffer.from([amount.receiver === undefined ? 0xFF : amount.receiver])
+  ];
+  return Buffer.concat([
+    Buffer.from([1]), text(name), Buffer.from([labels.length]), text(title),
+    ...labels.map(text), ...encodedAmount
+  ]);
+}
+
+let signDescriptor = function(body: Buffer, secretKey: Uint8Array = descriptorKey().signSk) {
+  return Buffer.concat([body, Buffer.from(nacl.crypto_sign_detached(body, secretKey))]);
+}
+
+let provideDescriptor = (kda: Kda, descriptor: Buffer) => kda.transport.send(0x00, 0x08, 0x00, 0x00, descriptor);
+
+const SWAP_DESCRIPTOR = encodeDescriptor("mydex.SWAP", "Swap", ["account", "amount"], { index: 1, decimals: 2, token: "MYT" });
+const SEND_DESCRIPTOR = encodeDescriptor("mydex.SEND", "Send", ["from", "to", "amount"], { index: 2, decimals: 2, token: "MYT", receiver: 1 });
+
+// Signed when the test runs, once nacl is instantiated; descriptors are kept until the app exits, so there is nothing to restore
+let withDescriptor = (body: Buffer) => (kda : Kda) => provideDescriptor(kda, signDescriptor(body));
+let keepDescriptors = async () => {};
+
+describe("Capability descriptor tests", function() {
+  it("shows a capability with the title and labels of its descriptor",
+     testTransactionWithSettings(withDescriptor(SWAP_DESCRIPTOR), keepDescriptors,
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(mydex.swap \\"alice\\" 1.5)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["alice",1.5],"name":"mydex.SWAP"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Swap", "prompt": "name: mydex.SWAP, account: \"alice\", amount: 1.50 MYT" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("shows a capability as unknown when its amount has more decimals than its descriptor allows",
+     testTransactionWithSettings(withDescriptor(SWAP_DESCRIPTOR), keepDescriptors,
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(mydex.swap \\"alice\\" 1.505)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["alice",1.505],"name":"mydex.SWAP"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Unknown Capability 1", "prompt": "name: mydex.SWAP, arg 1: \"alice\", arg 2: 1.505" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("adds up the transfers of a descriptor giving their receiver",
+     testTransactionWithSettings(withDescriptor(SEND_DESCRIPTOR), keepDescriptors,
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(mydex.send \\"alice\\" \\"bob\\" 1.5)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["alice","bob",1.5],"name":"mydex.SEND"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Send", "prompt": "name: mydex.SEND, from: \"alice\", to: \"bob\", amount: 1.50 MYT" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "1.5 mydex to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("refuses a descriptor whose receiver is its amount",
+     async () => {
+       await sendCommandExpectStatus(SW_INVALID_DESCRIPTOR,
+         async (kda : Kda) => {
+           await provideDescriptor(kda, signDescriptor(encodeDescriptor("mydex.SEND", "Send", ["from", "to", "amount"], { index: 2, decimals: 2, token: "MYT", receiver: 2 })));
+         });
+     });
+
+  it("refuses a descriptor not signed by the trusted key",
+     async () => {
+       const otherKey = nacl.crypto_sign_seed_keypair(Buffer.alloc(32, 1)).signSk;
+       await sendCommandExpectStatus(SW_UNTRUSTED_DESCRIPTOR,
+         async (kda : Kda) => {
+           await provideDescriptor(kda, signDescriptor(SWAP_DESCRIPTOR, otherKey));
+         });
+     });
+
+  it("refuses a descriptor for a capability the app knows",
+     async () => {
+       await sendCommandExpectStatus(SW_INVALID_DESCRIPTOR,
+         async (kda : Kda) => {
+           await provideDescriptor(kda, signDescriptor(encodeDescriptor("coin.TRANSFER", "Transfer", ["from", "to", "amount"], { index: 2, decimals: 12, token: "KDA", receiver: 1 })));
+         });
+     });
+})
+
+// Run on the release build, which must not trust the key of the tests
+describe("Release descriptor key tests", function() {
+  it("refuses a descriptor signed by the key of the tests",
+     async () => {
+       await sendCommandExpectStatus(SW_UNTRUSTED_DESCRIPTOR,
+         async (kda : Kda) => {
+           await provideDescriptor(kda, signDescriptor(SWAP_DESCRIPTOR));
+         });
+     });
+})
+
+describe("Gas station tests", function() {
+  it("shows the station paying for the gas of a sponsored transfer",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":["free-gas",{"int":600},1.0e-5],"name":"free.gas-station.GAS_PAYER"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"free-gas"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Paying Gas", "prompt": "Gas paid by station free-gas of free.gas-station" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("warns when the signer's own account pays for the gas of a gas station",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":["k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c",{"int":600},1.0e-5],"name":"free.gas-station.GAS_PAYER"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Paying Gas", "prompt": "Gas paid by station k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c of free.gas-station" },
+         { "header": "WARNING", "prompt": "Gas is paid by your own account, not by the gas station" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("warns when the gas of the station is not that of the command",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":["free-gas",{"int":1},1.0],"name":"free.gas-station.GAS_PAYER"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"free-gas"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Paying Gas", "prompt": "Gas paid by station free-gas of free.gas-station" },
+         { "header": "WARNING", "prompt": "Gas limit or price of the GAS_PAYER capability is not that of the command" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("warns when the user of the gas station is not the sender",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":["alice",{"int":600},1.0e-5],"name":"free.gas-station.GAS_PAYER"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"free-gas"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Paying Gas", "prompt": "Gas paid by station free-gas of free.gas-station" },
+         { "header": "WARNING", "prompt": "User of the GAS_PAYER capability is not the sender: free.gas-station for user alice, at most 600 at price 0.00001" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("shows a second gas station which is not the first",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":["free-gas",{"int":600},1.0e-5],"name":"free.gas-station.GAS_PAYER"},{"args":["other-gas",{"int":600},1.0e-5],"name":"free.gas-station.GAS_PAYER"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"free-gas"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "WARNING", "prompt": "Another GAS_PAYER capability, not that of the first: free.gas-station for user other-gas, at most 600 at price 0.00001" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Paying Gas", "prompt": "Gas paid by station free-gas of free.gas-station" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("shows the gas station when the meta is missing",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":["free-gas",{"int":600},1.0e-5],"name":"free.gas-station.GAS_PAYER"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "WARNING", "prompt": "Gas paid by a station, but the meta cannot be shown: free.gas-station for user free-gas, at most 600 at price 0.00001" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+})
+
+describe("Own account tests", function() {
+  it("labels a transfer from an account of this device",
+     testTransaction(
+       "44'/626'/0'",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"k:8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2\\" \\"k:9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2","clist":[{"args":["k:8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2","k:9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"},{"args":[],"name":"coin.GAS"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"k:8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Transfer 1", "prompt": "11 from \"k:8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2\" (your account #0) to \"k:9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
 
-  it("can build a custom token transfer-create tx",
+  it("labels the recipient of a transfer tx when it is an account of this device",
      checkSignTransferTxAPIs(
-       "signTransferCreateTx",
+       "signTransferTx",
        {
          path: "44'/626'/0'/0/0",
-         recipient: '83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790',
-         amount: "23.67",
-         namespace: "free",
-         module: "mytoken-123",
+         recipient: '8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2',
+         amount: "1.23",
          network: "testnet04",
-         chainId: 1,
+         chainId: 0,
          gasPrice: "1.0e-6",
          gasLimit: "2300",
-         creationTime: 1665722463,
+         creationTime: 1665647810,
          ttl: "600",
-         nonce: "2022-10-14 04:41:03.193557 UTC"
+         nonce: "2022-10-13 07:56:50.893257 UTC"
        },
-       "{\"networkId\":\"testnet04\",\"payload\":{\"exec\":{\"data\":{\"ks\":{\"pred\":\"keys-all\",\"keys\":[\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\"]}},\"code\":\"(free.mytoken-123.transfer-create \\\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\\\" \\\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\\" (read-keyset \\\"ks\\\") 23.67)\"}},\"signers\":[{\"pubKey\":\"9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"clist\":[{\"args\":[\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\",23.67],\"name\":\"free.mytoken-123.TRANSFER\"},{\"args\":[],\"name\":\"coin.GAS\"}]}],\"meta\":{\"creationTime\":1665722463,\"ttl\":600,\"gasLimit\":2300,\"chainId\":\"1\",\"gasPrice\":1.0e-6,\"sender\":\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\"},\"nonce\":\"2022-10-14 04:41:03.193557 UTC\"}",
+       "{\"networkId\":\"testnet04\",\"payload\":{\"exec\":{\"data\":{},\"code\":\"(coin.transfer \\\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\\\" \\\"k:8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2\\\" 1.23)\"}},\"signers\":[{\"pubKey\":\"9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"clist\":[{\"args\":[\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"k:8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2\",1.23],\"name\":\"coin.TRANSFER\"},{\"args\":[],\"name\":\"coin.GAS\"}]}],\"meta\":{\"creationTime\":1665647810,\"ttl\":600,\"gasLimit\":2300,\"chainId\":\"0\",\"gasPrice\":1.0e-6,\"sender\":\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\"},\"nonce\":\"2022-10-13 07:56:50.893257 UTC\"}",
        [
-         { "header": "Transfer", "prompt": "free.mytoken-123" },
+         { "header": "Transfer", "prompt": "KDA" },
          { "header": "From", "prompt": "k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995", "paginate": true },
-         { "header": "To", "prompt": "k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790", "paginate": true },
-         { "header": "Amount", "prompt": "23.67" },
+         { "header": "To", "prompt": "k:8d5d63bb1071a8dfc5c09ac96cfa50341a74eb91b6ea9ee5724cde09ef758bf2 (your account #0)", "paginate": true },
+         { "header": "Amount", "prompt": "1.23" },
          { "header": "Paying Gas (1/2)", "prompt": "At most 2300" },
          { "header": "Paying Gas (2/2)", "prompt": "Price 1.0e-6" },
          {"text": "Sign Transaction?", "x": 19, "y": 11,},
          {"text": "Confirm", "x": 43, "y": 11,}
        ]
      ));
-  it("can build a custom token cross-chain transfer tx",
-     checkSignTransferTxAPIs(
-       "signTransferCrossChainTx",
-       {
-         path: "44'/626'/0'/0/0",
-         recipient: '83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790',
-         recipient_chainId: 2,
-         amount: "23.67",
-         namespace: "free",
-         module: "mytoken-123",
-         network: "testnet04",
-         chainId: 1,
-         gasPrice: "1.0e-6",
-         gasLimit: "2300",
-         creationTime: 1665722463,
-         ttl: "600",
-         nonce: "2022-10-14 04:41:03.193557 UTC"
-       },
-       "{\"networkId\":\"testnet04\",\"payload\":{\"exec\":{\"data\":{\"ks\":{\"pred\":\"keys-all\",\"keys\":[\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\"]}},\"code\":\"(free.mytoken-123.transfer-crosschain \\\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\\\" \\\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\\" (read-keyset \\\"ks\\\") \\\"2\\\" 23.67)\"}},\"signers\":[{\"pubKey\":\"9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"clist\":[{\"args\":[\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\",\"k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\",23.67,\"2\"],\"name\":\"free.mytoken-123.TRANSFER_XCHAIN\"},{\"args\":[],\"name\":\"coin.GAS\"}]}],\"meta\":{\"creationTime\":1665722463,\"ttl\":600,\"gasLimit\":2300,\"chainId\":\"1\",\"gasPrice\":1.0e-6,\"sender\":\"k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995\"},\"nonce\":\"2022-10-14 04:41:03.193557 UTC\"}",
+})
+
+const SW_INVALID_ADDRESS = 0x6A87;
+const SW_RECIPIENT_NOT_ALLOWED = 0x6A8A;
+
+// A length of one byte, unlike the four bytes of lengthPrefixed
+let bytePrefixed = (s: string) => Buffer.concat([Buffer.from([s.length]), Buffer.from(s, "utf-8")]);
+
+let addAddress = (kda: Kda, label: string, account: string) =>
+  kda.transport.send(0x00, 0x09, 0x00, 0x00, Buffer.concat([bytePrefixed(label), bytePrefixed(account)]));
+
+let listAddresses = (kda: Kda, index: number) => kda.transport.send(0x00, 0x0A, 0x00, 0x00, Buffer.from([index]));
+
+let deleteAddress = (kda: Kda, account: string) => kda.transport.send(0x00, 0x0B, 0x00, 0x00, bytePrefixed(account));
+
+let toggleKnownRecipientsOnly = () => toggleSetting(5);
+
+function testRecipientNotAllowed(txn: string, sign = signJsonTx) {
+     return async () => {
+       await toggleKnownRecipientsOnly();
+       try {
+         await sendCommandExpectStatus(SW_RECIPIENT_NOT_ALLOWED,
+           async (kda : Kda) => {
+             await sign(kda, "0/0", txn);
+           });
+       } finally {
+         await toggleKnownRecipientsOnly();
+       }
+     }
+}
+
+const TREASURY = "9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42";
+
+// The tests of this block run in order, sharing the address book
+describe("Address book tests", function() {
+  it("adds an address after the user confirms it",
+     async () => {
+       await sendCommandAndAccept(
+         async (kda : Kda) => {
+           await addAddress(kda, "Treasury", TREASURY);
+           let rv = await listAddresses(kda, 0);
+           // The count, the entry, and the status word
+           expect(rv).to.deep.equal(Buffer.concat([Buffer.from([1]), bytePrefixed("Treasury"), bytePrefixed(TREASURY), Buffer.from([0x90, 0x00])]));
+         },
+         [
+           { "header": "Add Address", "prompt": "Treasury" },
+           { "header": "Account", "prompt": TREASURY, "paginate": true },
+           {
+             "text": "Confirm",
+             "x": 43,
+             "y": 11,
+           }
+         ]);
+     });
+
+  it("shows the label of a recipient in the address book",
+     testTransaction(
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
        [
-         { "header": "Transfer", "prompt": "free.mytoken-123" },
-         { "header": "From", "prompt": "k:9ed54a1020ebbbf8bbe425346498434edd79e4cd36fe874ea58853e78eab4995", "paginate": true },
-         { "header": "To", "prompt": "k:83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790", "paginate": true },
-         { "header": "To Chain", "prompt": "2" },
-         { "header": "Amount", "prompt": "23.67" },
-         { "header": "Paying Gas (1/2)", "prompt": "At most 2300" },
-         { "header": "Paying Gas (2/2)", "prompt": "Price 1.0e-6" },
-         {"text": "Sign Transaction?", "x": 19, "y": 11,},
-         {"text": "Confirm", "x": 43, "y": 11,}
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to Treasury (9790d1...)" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
        ]
      ));
-  })
+
+  it("deletes an address after the user confirms it",
+     async () => {
+       await sendCommandAndAccept(
+         async (kda : Kda) => {
+           await deleteAddress(kda, TREASURY);
+           let rv = await listAddresses(kda, 0);
+           expect(rv).to.deep.equal(Buffer.from([0, 0x90, 0x00]));
+         },
+         [
+           { "header": "Delete Address", "prompt": "Treasury" },
+           { "header": "Account", "prompt": TREASURY, "paginate": true },
+           {
+             "text": "Confirm",
+             "x": 43,
+             "y": 11,
+           }
+         ]);
+     });
+
+  it("refuses a transfer to an unknown recipient with Only Known Recipients",
+     testRecipientNotAllowed(
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}'
+     ));
+
+  it("refuses a transfer whose receiver is not an account name with Only Known Recipients",
+     testRecipientNotAllowed(
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790",{"account":"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42"},11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}'
+     ));
+
+  it("refuses a transfer with too many args to check with Only Known Recipients",
+     testRecipientNotAllowed(
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11,1,2,3],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}'
+     ));
+
+  it("refuses to sign when the signers are dropped with Only Known Recipients",
+     testRecipientNotAllowed(
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"weight":1,"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       signHashWithTx
+     ));
+
+  it("refuses an account which could not be written as a JSON string",
+     async () => {
+       await sendCommandExpectStatus(SW_INVALID_ADDRESS,
+         async (kda : Kda) => {
+           await addAddress(kda, "Quoted", 'k:"abc"');
+         });
+     });
+})
+
+const SW_OVER_SPENDING_LIMIT = 0x6A8B;
+
+let toggleRefuseOverLimit = () => toggleSetting(6);
+
+// From no limit to 10 KDA, and back
+let setSpendingLimit = () => cycleThreshold(3);
+let resetSpendingLimit = () => cycleThreshold(3, 4);
+
+describe("Spending limit tests", function() {
+  it("asks for another confirmation when the transfers are above the spending limit",
+     testTransactionWithSettings(setSpendingLimit, resetSpendingLimit,
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Transfer 1", "prompt": "11 from \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\" to \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "11 KDA to 1 recipient" },
+         { "header": "Over Limit", "prompt": "11 KDA, above the spending limit of 10" },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("asks for another confirmation for a coin.TRANSFER it cannot add up",
+     testTransactionWithSettings(setSpendingLimit, resetSpendingLimit,
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 1000.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42","1000"],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "Paying Gas", "prompt": " " },
+         { "header": "Unknown Capability 1", "prompt": "name: coin.TRANSFER, arg 1: \"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\", arg 2: \"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\", arg 3: \"1000\"" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Total", "prompt": "Unknown amount of KDA to unknown recipients" },
+         { "header": "Over Limit", "prompt": "Unknown KDA amount, the spending limit is 10" },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         },
+         {
+           "text": "Sign Transaction?",
+           "x": 19,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("asks for another confirmation when the signers are dropped, as their transfers cannot be added up",
+     testTransactionWithSettings(
+       async () => { await setSpendingLimit(); await toggleAllowUnscoped(); },
+       async () => { await toggleAllowUnscoped(); await resetSpendingLimit(); },
+       "0/0",
+       '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"weight":1,"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}',
+       [
+         { "header": "Signing", "prompt": "Transaction Hash" },
+         { "header": "On Network", "prompt": "Mainnet" },
+         { "header": "On Chain", "prompt": "0" },
+         { "header": "Using Gas", "prompt": "at most 600 at price 1.0e-5" },
+         { "header": "WARNING", "prompt": "UNSAFE TRANSACTION. This transaction's code was not recognized and does not limit capabilities for all signers. Signing this transaction may make arbitrary actions on the chain including loss of all funds." },
+         { "header": "WARNING", "prompt": "1 field not shown" },
+         { "header": "Transaction hash", "prompt": "5CXEVG57cLaWC_P2YX02QqDTztNFnOj0dhUHlstwaJg" },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         { "header": "Over Limit", "prompt": "Unknown KDA amount, the spending limit is 10" },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         },
+         {
+           "text": "Sign Transaction Hash?",
+           "x": 4,
+           "y": 11,
+         },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ],
+       signHashWithTx
+     ));
+
+  it("refuses transfers above the spending limit with Refuse Over Limit",
+     async () => {
+       await setSpendingLimit();
+       await toggleRefuseOverLimit();
+       try {
+         await sendCommandExpectStatus(SW_OVER_SPENDING_LIMIT,
+           async (kda : Kda) => {
+             let txn = '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}';
+             await kda.signTransaction("0/0", Buffer.from(txn, "utf-8").toString("hex"));
+           });
+       } finally {
+         await toggleRefuseOverLimit();
+         await resetSpendingLimit();
+       }
+     });
+})
+
+// The digest SIGN_MESSAGE signs, as described in docs/apdu.md
+let messageDigest = function(message: Buffer) {
+  const prefix = Buffer.from("\x19Kadena Signed Message:\n" + message.length + "\n", "utf-8");
+  return blake2b(32).update(Buffer.concat([prefix, message])).digest();
+}
+
+let signMessage = async function(kda : Kda, path: string, message: Buffer) {
+  const rv = await sendChunked(kda, 0x0C, Buffer.concat([Buffer.from([message.length]), message, encodeBip32Path(path)]));
+  return rv.slice(0, 64).toString("hex");
+}
+
+function testSignMessage(path: string, message: string, prompts: any[]) {
+     return async () => {
+       await sendCommandAndAccept(
+         async (kda : Kda) => {
+           let pubkey = (await kda.getPublicKey(path)).publicKey;
+           await Axios.delete(BASE_URL + "/events");
+           const bytes = Buffer.from(message, "utf-8");
+           let signature = await signMessage(kda, path, bytes);
+           let pass = nacl.crypto_sign_verify_detached(Buffer.from(signature, 'hex'), messageDigest(bytes), Buffer.from(pubkey, 'hex'));
+           expect(pass).to.equal(true);
+         }, prompts);
+     }
+}
+
+describe("Message signing tests", function() {
+  it("signs a message in a prefixed digest",
+     testSignMessage(
+       "0/0",
+       "Sign in to example.com\nNonce: 8f2a61c4",
+       [
+         { "header": "Signing", "prompt": "Message" },
+         { "header": "Message", "prompt": "Sign in to example.com\\u000ANonce: 8f2a61c4", "paginate": true },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("shows characters outside of ASCII as escapes",
+     testSignMessage(
+       "0/0",
+       "Café ☕",
+       [
+         { "header": "Signing", "prompt": "Message" },
+         { "header": "Message", "prompt": "Caf\\u00E9 \\u2615", "paginate": true },
+         { "header": "Sign for Address", "prompt": "ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c" },
+         {
+           "text": "Confirm",
+           "x": 43,
+           "y": 11,
+         }
+       ]
+     ));
+
+  it("rejects a message which is not UTF-8",
+     async () => {
+       await sendCommandExpectFail(
+         async (kda : Kda) => {
+           await signMessage(kda, "0/0", Buffer.from([0x68, 0x69, 0xff]));
+         });
+     });
+})
+
+const SW_ADDRESS_REJECTED = 0x6A8C;
+
+let verifyAddress = async function(kda : Kda, path: string, chain: string, network: string) {
+  const rv = await kda.transport.send(0x00, 0x0D, 0x00, 0x00, Buffer.concat([encodeBip32Path(path), bytePrefixed(chain), bytePrefixed(network)]));
+  return rv.slice(1, 1 + rv[0]).toString("hex");
+}
+
+// Like setAcceptAutomationRules, but choosing "Reject" after the "Confirm" screen
+let setRejectAutomationRules = async function() {
+    await Axios.post(BASE_URL + "/automation", {
+      version: 1,
+      rules: [
+        ... ignoredScreens.map(txt => { return { "text": txt, "actions": [] } }),
+        { "y": 16, "actions": [] },
+        { "y": 31, "actions": [] },
+        { "y": 46, "actions": [] },
+        { "text": "Reject", "actions": [ [ "button", 1, true ], [ "button", 2, true ], [ "button", 2, false ], [ "button", 1, false ] ]},
+        { "actions": [ [ "button", 2, true ], [ "button", 2, false ] ]}
+      ]
+    });
+}
+
+describe("Verify address tests", function() {
+  it("shows the account with its chain and network",
+     async () => {
+       await sendCommandAndAccept(
+         async (kda : Kda) => {
+           let key = await verifyAddress(kda, "0/0", "1", "mainnet01");
+           expect(key).to.equal("ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c");
+         },
+         [
+           { "header": "Verify Address", "prompt": "k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c", "paginate": true },
+           { "header": "On Chain", "prompt": "1" },
+           { "header": "On Network", "prompt": "Mainnet" },
+           {
+             "text": "Approve Address?",
+             "x": 16,
+             "y": 11,
+           },
+           {
+             "text": "Confirm",
+             "x": 43,
+             "y": 11,
+           }
+         ]);
+     });
+
+  it("shows only the account without a chain and network",
+     async () => {
+       await sendCommandAndAccept(
+         async (kda : Kda) => {
+           let key = await verifyAddress(kda, "0/0", "", "");
+           expect(key).to.equal("ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c");
+         },
+         [
+           { "header": "Verify Address", "prompt": "k:ffd8cd79deb956fa3c7d9be0f836f20ac84b140168a087a842be4760e40e2b1c", "paginate": true },
+           {
+             "text": "Approve Address?",
+             "x": 16,
+             "y": 11,
+           },
+           {
+             "text": "Confirm",
+             "x": 43,
+             "y": 11,
+           }
+         ]);
+     });
+
+  it("rejects an invalid chain",
+     async () => {
+       await sendCommandExpectStatus(SW_INVALID_CHAIN,
+         async (kda : Kda) => {
+           await verifyAddress(kda, "0/0", "20", "mainnet01");
+         });
+     });
+
+  it("answers a rejected address with its own status word",
+     async () => {
+       await setRejectAutomationRules();
+       let transport = await Transport.open(BASE_URL + "/apdu");
+       let kda = new Kda(transport);
+       try {
+         await verifyAddress(kda, "0/0", "0", "mainnet01");
+       } catch(e) {
+         expect(e.statusCode).to.equal(SW_ADDRESS_REJECTED);
+         return;
+       }
+       expect.fail("Test should have failed");
+     });
+})
+
+const SW_USER_CANCELLED = 0x6A8D;
+
+// Sends the first chunk of a transaction, and cancels it from the busy screen
+let startAndCancel = async function(kda : Kda) {
+  const txn = '{"networkId":"mainnet01","payload":{"exec":{"data":{},"code":"(coin.transfer \\"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790\\" \\"9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42\\" 11.0)"}},"signers":[{"pubKey":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","clist":[{"args":[],"name":"coin.GAS"},{"args":["83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790","9790d119589a26114e1a42d92598b3f632551c566819ec48e0e8c54dae6ebb42",11],"name":"coin.TRANSFER"}]}],"meta":{"creationTime":1634009214,"ttl":28800,"gasLimit":600,"chainId":"0","gasPrice":1.0e-5,"sender":"83934c0f9b005f378ba3520f9dea952fb0a90e5aa36f1b5ff837d9b30c471790"},"nonce":"\\"2021-10-12T03:27:53.700Z\\""}';
+  const payload = Buffer.concat([lengthPrefixed(txn), encodeBip32Path("0/0")]);
+  await kda.transport.send(0x00, 0x03, 0x00, 0x00, payload.slice(0, 230));
+  await pressButton("right");
+  await pressButton("both");
+  return payload.slice(230, 460);
+}
+
+describe("Cancel tests", function() {
+  afterEach( async function() {
+    await Axios.delete(BASE_URL + "/events");
+  });
+
+  it("answers the next APDU of a cancelled request with SW_USER_CANCELLED",
+     async () => {
+       // No automation, so that the busy screen stays as it is until cancelled
+       await Axios.post(BASE_URL + "/automation", {version: 1, rules: []});
+       let transport = await Transport.open(BASE_URL + "/apdu");
+       let kda = new Kda(transport);
+       const next = await startAndCancel(kda);
+       let statusCode = null;
+       try {
+         await kda.transport.send(0x00, 0x03, 0x00, 0x00, next);
+       } catch(e) {
+         statusCode = e.statusCode;
+       }
+       expect(statusCode).to.equal(SW_USER_CANCELLED);
+     });
+
+  it("does not refuse a new request with the same instruction after a cancel",
+     async () => {
+       await Axios.post(BASE_URL + "/automation", {version: 1, rules: []});
+       let transport = await Transport.open(BASE_URL + "/apdu");
+       let kda = new Kda(transport);
+       await startAndCancel(kda);
+       // The host gives up on the cancelled request and starts the transaction again
+       const next = await startAndCancel(kda);
+       let statusCode = null;
+       try {
+         await kda.transport.send(0x00, 0x03, 0x00, 0x00, next);
+       } catch(e) {
+         statusCode = e.statusCode;
+       }
+       expect(statusCode).to.equal(SW_USER_CANCELLED);
+     });
+
+  it("does not refuse a request with another instruction after a cancel",
+     async () => {
+       await Axios.post(BASE_URL + "/automation", {version: 1, rules: []});
+       let transport = await Transport.open(BASE_URL + "/apdu");
+       let kda = new Kda(transport);
+       await startAndCancel(kda);
+       let rv = await kda.transport.send(0x00, 0xFE, 0x00, 0x00);
+       expect(rv.slice(0, 7).toString()).to.equal("Kadena ");
+     });
+})